@@ -0,0 +1,121 @@
+use crate::{
+    ray::Ray,
+    vec3::{Point, Vec3},
+};
+
+// Axis aligned bounding box.
+#[derive(Clone, Copy, Debug)]
+pub struct Aabb {
+    pub min: Point,
+    pub max: Point,
+}
+
+impl Aabb {
+    pub fn new(min: Point, max: Point) -> Aabb {
+        return Aabb { min, max };
+    }
+
+    pub fn from_points(points: &[Point]) -> Aabb {
+        let mut aabb = Aabb::new(points[0], points[0]);
+        for p in &points[1..] {
+            aabb = aabb.include(*p);
+        }
+        return aabb;
+    }
+
+    pub fn include(&self, p: Point) -> Aabb {
+        return Aabb::new(
+            Vec3::new(
+                self.min.x.min(p.x),
+                self.min.y.min(p.y),
+                self.min.z.min(p.z),
+            ),
+            Vec3::new(
+                self.max.x.max(p.x),
+                self.max.y.max(p.y),
+                self.max.z.max(p.z),
+            ),
+        );
+    }
+
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        return self.include(other.min).include(other.max);
+    }
+
+    pub fn expand(&self, amount: f64) -> Aabb {
+        let delta = Vec3::new(amount, amount, amount);
+        return Aabb::new(self.min - delta, self.max + delta);
+    }
+
+    pub fn centroid(&self) -> Point {
+        return 0.5 * (self.min + self.max);
+    }
+
+    // 0, 1 or 2 for x, y or z
+    pub fn longest_axis(&self) -> usize {
+        let d = self.max - self.min;
+        if d.x >= d.y && d.x >= d.z {
+            return 0;
+        }
+        if d.y >= d.z {
+            return 1;
+        }
+        return 2;
+    }
+
+    // slab test
+    pub fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> bool {
+        return self.interval(ray, t_min, t_max).is_some();
+    }
+
+    // the part of [t_min, t_max] where the ray is inside the box
+    pub fn interval(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<(f64, f64)> {
+        let mut t0 = t_min;
+        let mut t1 = t_max;
+        for axis in 0..3 {
+            let inv = 1.0 / axis_of(ray.dir, axis);
+            let mut near = (axis_of(self.min, axis) - axis_of(ray.org, axis)) * inv;
+            let mut far = (axis_of(self.max, axis) - axis_of(ray.org, axis)) * inv;
+            if inv < 0.0 {
+                std::mem::swap(&mut near, &mut far);
+            }
+            // nan when the ray lies in a slab plane, treated as a hit
+            if near > t0 {
+                t0 = near;
+            }
+            if far < t1 {
+                t1 = far;
+            }
+            if t0 > t1 {
+                return None;
+            }
+        }
+        return Some((t0, t1));
+    }
+}
+
+pub fn axis_of(v: Vec3, axis: usize) -> f64 {
+    return match axis {
+        0 => v.x,
+        1 => v.y,
+        _ => v.z,
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hit() {
+        let aabb = Aabb::from_points(&[Point::new(-1.0, -1.0, -1.0), Point::new(1.0, 1.0, 1.0)]);
+        let ray = Ray::new(Point::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(aabb.hit(&ray, 0.001, f64::INFINITY));
+        assert!(!aabb.hit(&ray, 0.001, 3.0));
+
+        let miss = Ray::new(Point::new(2.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(!aabb.hit(&miss, 0.001, f64::INFINITY));
+        assert_eq!(aabb.interval(&ray, 0.001, f64::INFINITY), Some((4.0, 6.0)));
+        assert_eq!(aabb.longest_axis(), 0);
+    }
+}
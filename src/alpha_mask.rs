@@ -0,0 +1,104 @@
+use crate::{
+    aabb::Aabb,
+    hittable::{HitRecord, Hittable, INTERVAL_EPSILON},
+    image::luminance,
+    ray::Ray,
+    sampler::random,
+    texture::Texture,
+};
+
+// Cuts holes into any object. Hits are kept with a probability equal to the luminance
+// of the opacity texture at the hit, so black texels are skipped, white ones are solid
+// and gray ones let a share of the rays through. Skipped hits are stepped over inside
+// hit() itself, which keeps shadow rays and materials unaware of the mask.
+pub struct AlphaMask {
+    object: Box<dyn Hittable>,
+    opacity: Box<dyn Texture>,
+}
+
+impl AlphaMask {
+    pub fn new(object: Box<dyn Hittable>, opacity: Box<dyn Texture>) -> AlphaMask {
+        return AlphaMask { object, opacity };
+    }
+}
+
+impl Hittable for AlphaMask {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        let mut t_min = t_min;
+        loop {
+            let record = self.object.hit(ray, t_min, t_max)?;
+            let opacity = luminance(self.opacity.value(&record));
+            if opacity >= 1.0 || (opacity > 0.0 && random() < opacity) {
+                return Some(record);
+            }
+            t_min = record.t + INTERVAL_EPSILON * record.t.abs().max(1.0);
+        }
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        return self.object.bounding_box();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        lambertian::Lambertian,
+        sphere::Sphere,
+        texture::SolidColor,
+        vec3::{Color, Point, Vec3},
+    };
+
+    const EPSILON: f64 = 0.000001;
+
+    fn sphere() -> Box<dyn Hittable> {
+        let material = Box::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        return Box::new(Sphere::new(Point::default(), 1.0, material));
+    }
+
+    // solid on the inside of the sphere only
+    struct BackFaces;
+
+    impl Texture for BackFaces {
+        fn value(&self, record: &HitRecord) -> Color {
+            let opacity = if record.front_face { 0.0 } else { 1.0 };
+            return Color::new(opacity, opacity, opacity);
+        }
+    }
+
+    #[test]
+    fn test_cutout() {
+        let ray = Ray::new(Point::new(0.0, 0.0, 3.0), Vec3::new(0.0, 0.0, -1.0));
+
+        let solid = AlphaMask::new(
+            sphere(),
+            Box::new(SolidColor::new(Color::new(1.0, 1.0, 1.0))),
+        );
+        assert!((solid.hit(&ray, 0.001, f64::INFINITY).unwrap().t - 2.0).abs() <= EPSILON);
+
+        let hidden = AlphaMask::new(sphere(), Box::new(SolidColor::new(Color::default())));
+        assert!(hidden.hit(&ray, 0.001, f64::INFINITY).is_none());
+
+        // the near side is cut away, the ray continues to the far side
+        let open = AlphaMask::new(sphere(), Box::new(BackFaces));
+        let record = open.hit(&ray, 0.001, f64::INFINITY).unwrap();
+        assert!((record.t - 4.0).abs() <= EPSILON);
+        assert!(!record.front_face);
+        assert!(open.hit(&ray, 0.001, 3.5).is_none());
+    }
+
+    #[test]
+    fn test_partial_opacity() {
+        let ray = Ray::new(Point::new(0.0, 0.0, 3.0), Vec3::new(0.0, 0.0, -1.0));
+        let half = Color::new(0.5, 0.5, 0.5);
+        let mask = AlphaMask::new(sphere(), Box::new(SolidColor::new(half)));
+
+        // both sides are skipped for a quarter of the rays
+        let count = 10000;
+        let missed = (0..count)
+            .filter(|_| mask.hit(&ray, 0.001, f64::INFINITY).is_none())
+            .count();
+        assert!((missed as f64 / count as f64 - 0.25).abs() < 0.03);
+    }
+}
@@ -0,0 +1,186 @@
+use crate::{
+    hittable::HitRecord,
+    image::Image,
+    ray::Ray,
+    vec3::{Color, Vec3},
+};
+
+// Auxiliary passes rendered next to the beauty image.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Aov {
+    // distance from the camera to the first hit
+    Depth,
+    // world space outward normal at the first hit
+    Normal,
+    Albedo,
+    MaterialId,
+    ObjectId,
+    // per channel variance of the radiance samples in the pixel
+    Variance,
+}
+
+impl Aov {
+    pub fn all() -> Vec<Aov> {
+        return vec![
+            Aov::Depth,
+            Aov::Normal,
+            Aov::Albedo,
+            Aov::MaterialId,
+            Aov::ObjectId,
+            Aov::Variance,
+        ];
+    }
+
+    pub fn parse(name: &str) -> Result<Aov, String> {
+        return Aov::all()
+            .into_iter()
+            .find(|aov| aov.name() == name)
+            .ok_or_else(|| format!("unknown aov {}", name));
+    }
+
+    pub fn name(&self) -> &'static str {
+        return match self {
+            Aov::Depth => "depth",
+            Aov::Normal => "normal",
+            Aov::Albedo => "albedo",
+            Aov::MaterialId => "material_id",
+            Aov::ObjectId => "object_id",
+            Aov::Variance => "variance",
+        };
+    }
+}
+
+// Per pixel averages of first hit values. Ids are written as false colors, so averaging
+// them gives anti-aliased mattes.
+pub struct AovBuffers {
+    width: u32,
+    height: u32,
+    counts: Vec<u32>,
+    depth: Vec<f64>,
+    normal: Vec<Vec3>,
+    albedo: Vec<Color>,
+    material_id: Vec<Color>,
+    object_id: Vec<Color>,
+    sum: Vec<Color>,
+    sum_squared: Vec<Color>,
+}
+
+impl AovBuffers {
+    pub fn new(width: u32, height: u32) -> AovBuffers {
+        let size = (width * height) as usize;
+        return AovBuffers {
+            width,
+            height,
+            counts: vec![0; size],
+            depth: vec![0.0; size],
+            normal: vec![Vec3::default(); size],
+            albedo: vec![Color::default(); size],
+            material_id: vec![Color::default(); size],
+            object_id: vec![Color::default(); size],
+            sum: vec![Color::default(); size],
+            sum_squared: vec![Color::default(); size],
+        };
+    }
+
+    pub fn add_sample(
+        &mut self,
+        x: u32,
+        y: u32,
+        ray: &Ray,
+        record: Option<&HitRecord>,
+        color: Color,
+    ) {
+        let i = (y * self.width + x) as usize;
+        self.counts[i] += 1;
+        self.sum[i] += color;
+        self.sum_squared[i] += color * color;
+        if let Some(record) = record {
+            self.depth[i] += record.t * ray.dir.length();
+            self.normal[i] += HitRecord::get_normal(record.front_face, record.normal);
+            self.albedo[i] += record.material.albedo(record);
+            self.material_id[i] += id_color(record.material_id);
+            self.object_id[i] += id_color(record.object_id);
+        }
+    }
+
+    pub fn to_image(&self, aov: Aov) -> Image {
+        let mut image = Image::new(self.width, self.height);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let i = (y * self.width + x) as usize;
+                let n = self.counts[i] as f64;
+                if n == 0.0 {
+                    continue;
+                }
+                let value = match aov {
+                    Aov::Depth => {
+                        let d = self.depth[i] / n;
+                        Color::new(d, d, d)
+                    }
+                    Aov::Normal => self.normal[i] / n,
+                    Aov::Albedo => self.albedo[i] / n,
+                    Aov::MaterialId => self.material_id[i] / n,
+                    Aov::ObjectId => self.object_id[i] / n,
+                    Aov::Variance => {
+                        if n < 2.0 {
+                            Color::default()
+                        } else {
+                            let mean = self.sum[i] / n;
+                            (self.sum_squared[i] - n * mean * mean) / (n - 1.0)
+                        }
+                    }
+                };
+                image.set(x, y, value);
+            }
+        }
+        return image;
+    }
+}
+
+// stable pseudo random color for an id
+fn id_color(id: usize) -> Color {
+    let mut h = (id as u64).wrapping_mul(0x9E3779B97F4A7C15);
+    h ^= h >> 29;
+    h = h.wrapping_mul(0xBF58476D1CE4E5B9);
+    h ^= h >> 32;
+    let channel = |shift: u32| ((h >> shift) & 0xFF) as f64 / 255.0;
+    return Color::new(channel(0), channel(8), channel(16));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lambertian::Lambertian, vec3::Point};
+
+    const EPSILON: f64 = 0.000001;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(Aov::parse("object_id"), Ok(Aov::ObjectId));
+        assert!(Aov::parse("beauty").is_err());
+    }
+
+    #[test]
+    fn test_buffers() {
+        let material = Lambertian::new(Color::new(0.2, 0.4, 0.6));
+        let record = HitRecord::new(
+            Point::new(0.0, 0.0, -2.0),
+            Vec3::new(0.0, 0.0, 1.0),
+            1.0,
+            true,
+            &material,
+        );
+        let ray = Ray::new(Point::default(), Vec3::new(0.0, 0.0, -2.0));
+        let mut buffers = AovBuffers::new(2, 1);
+        buffers.add_sample(0, 0, &ray, Some(&record), Color::new(1.0, 1.0, 1.0));
+        buffers.add_sample(0, 0, &ray, None, Color::new(3.0, 3.0, 3.0));
+
+        let depth = buffers.to_image(Aov::Depth).get(0, 0);
+        assert!((depth.x - 1.0).abs() <= EPSILON);
+        let albedo = buffers.to_image(Aov::Albedo).get(0, 0);
+        assert!((albedo.y - 0.2).abs() <= EPSILON);
+        let variance = buffers.to_image(Aov::Variance).get(0, 0);
+        assert!((variance.x - 2.0).abs() <= EPSILON);
+        assert_eq!(buffers.to_image(Aov::Normal).get(1, 0), Vec3::default());
+    }
+}
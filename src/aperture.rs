@@ -0,0 +1,113 @@
+use std::f64::consts::PI;
+
+use crate::{
+    distribution::Distribution2D,
+    image::{luminance, Image},
+    sampler::{random, random_index},
+    vec3::Vec3,
+};
+
+// Shape of the lens opening, which is also the shape of out of focus highlights (bokeh).
+pub trait Aperture {
+    // point on the opening, scaled to fit the unit disk, with z = 0
+    fn sample(&self) -> Vec3;
+}
+
+pub struct CircularAperture {}
+
+impl Aperture for CircularAperture {
+    fn sample(&self) -> Vec3 {
+        return Vec3::random_in_unit_disk();
+    }
+}
+
+// Regular polygon as formed by straight diaphragm blades.
+pub struct PolygonAperture {
+    blades: u32,
+    rotation: f64,
+}
+
+impl PolygonAperture {
+    pub fn new(blades: u32, rotation: f64) -> PolygonAperture {
+        return PolygonAperture {
+            blades: blades.max(3),
+            rotation,
+        };
+    }
+}
+
+impl Aperture for PolygonAperture {
+    fn sample(&self) -> Vec3 {
+        // the polygon is a fan of equal triangles around the center
+        let blade = random_index(self.blades as usize) as f64;
+        let step = 2.0 * PI / self.blades as f64;
+        let a0 = self.rotation + blade * step;
+        let a1 = a0 + step;
+        let mut s: f64 = random();
+        let mut t: f64 = random();
+        if s + t > 1.0 {
+            s = 1.0 - s;
+            t = 1.0 - t;
+        }
+        return Vec3::new(
+            s * a0.cos() + t * a1.cos(),
+            s * a0.sin() + t * a1.sin(),
+            0.0,
+        );
+    }
+}
+
+// Opening given by an image, brighter pixels let more light through.
+pub struct ImageAperture {
+    distribution: Distribution2D,
+}
+
+impl ImageAperture {
+    pub fn new(image: &Image) -> ImageAperture {
+        let mut func = Vec::with_capacity((image.width * image.height) as usize);
+        for y in 0..image.height {
+            for x in 0..image.width {
+                func.push(luminance(image.get(x, y)));
+            }
+        }
+        return ImageAperture {
+            distribution: Distribution2D::new(&func, image.width as usize, image.height as usize),
+        };
+    }
+}
+
+impl Aperture for ImageAperture {
+    fn sample(&self) -> Vec3 {
+        let ((u, v), _) = self.distribution.sample_continuous(random(), random());
+        // image corners touch the unit circle
+        let scale = std::f64::consts::FRAC_1_SQRT_2;
+        return Vec3::new(scale * (2.0 * u - 1.0), scale * (1.0 - 2.0 * v), 0.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vec3::Color;
+
+    #[test]
+    fn test_polygon_inside_unit_disk() {
+        let aperture = PolygonAperture::new(5, 0.3);
+        for _ in 0..200 {
+            let p = aperture.sample();
+            assert!(p.length() <= 1.0);
+        }
+    }
+
+    #[test]
+    fn test_image_follows_bright_pixels() {
+        let mut image = Image::new(4, 4);
+        image.set(3, 0, Color::new(1.0, 1.0, 1.0));
+        let aperture = ImageAperture::new(&image);
+        for _ in 0..50 {
+            let p = aperture.sample();
+            // top right cell
+            assert!(p.x > 0.0 && p.y > 0.0);
+        }
+    }
+}
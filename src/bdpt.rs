@@ -0,0 +1,651 @@
+use crate::{
+    camera::Camera,
+    film::Film,
+    hittable::{HitRecord, Hittable},
+    integrator::{power_heuristic, project, random_walk, Integrator},
+    light::Light,
+    ray::Ray,
+    sampler::{random, random_index},
+    scene::Scene,
+    spectrum::spectral_to_rgb,
+    subsurface::Medium,
+    vec3::{Color, Point, Vec3},
+};
+
+const SHADOW_EPSILON: f64 = 0.0001;
+// vertices a subpath gets before russian roulette may end it
+const MIN_BOUNCES: usize = 3;
+
+// Bidirectional path tracing. A subpath from the camera and one from a point or spot
+// light are connected at every pair of vertices, and the light subpath is also connected
+// straight to the lens, which finds caustics of small lights seen through glass. Each
+// connection is weighted with the power heuristic against the other ways of sampling
+// the same path. Lights at infinity and the environment are gathered along the camera
+// subpath like the path tracer does, and walks through subsurface media are left to it.
+pub struct BidirectionalIntegrator {
+    max_depth: u32,
+}
+
+impl BidirectionalIntegrator {
+    pub fn new(max_depth: u32) -> BidirectionalIntegrator {
+        return BidirectionalIntegrator { max_depth };
+    }
+}
+
+// A vertex of a subpath, the camera and light ends have no hit record.
+#[derive(Clone, Copy)]
+struct Vertex<'a> {
+    p: Point,
+    record: Option<HitRecord<'a>>,
+    // throughput of the subpath up to the vertex
+    throughput: Color,
+    // area densities with which the own subpath and the opposite one sample the vertex
+    pdf_fwd: f64,
+    pdf_rev: f64,
+    // specular vertices can't be connected to
+    delta: bool,
+}
+
+impl<'a> Vertex<'a> {
+    fn end(p: Point, throughput: Color, pdf_fwd: f64) -> Vertex<'a> {
+        return Vertex {
+            p,
+            record: None,
+            throughput,
+            pdf_fwd,
+            pdf_rev: 0.0,
+            delta: false,
+        };
+    }
+
+    // cosine between the surface and dir, 1 at the ends and inside volumes
+    fn cos(&self, dir: Vec3) -> f64 {
+        return match self.record {
+            Some(record) if record.material.has_surface() => {
+                Vec3::dot(record.normal, dir.unit_vector()).abs()
+            }
+            _ => 1.0,
+        };
+    }
+
+    // area density at next for a solid angle density of the direction towards it
+    fn area_pdf(&self, pdf: f64, next: &Vertex) -> f64 {
+        let d = next.p - self.p;
+        let distance_squared = d.length_double();
+        if distance_squared == 0.0 {
+            return 0.0;
+        }
+        return pdf * next.cos(d) / distance_squared;
+    }
+
+    // BSDF times cosine for light passing from `from` through the vertex to `to`
+    fn eval(&self, from: Point, to: Point) -> Color {
+        let record = self.record.unwrap();
+        return record
+            .material
+            .eval(&Ray::new(from, self.p - from), &record, to - self.p);
+    }
+
+    // solid angle density of scattering towards `to` for a ray arriving from `from`
+    fn pdf(&self, from: Point, to: Point) -> f64 {
+        let record = self.record.unwrap();
+        return record
+            .material
+            .pdf(&Ray::new(from, self.p - from), &record, to - self.p);
+    }
+}
+
+// How the camera subpath ended, for the light that the path tracer gathers past it.
+enum Exit<'a> {
+    // left the scene, with the density of the direction unless it was specular
+    Escaped(Ray, Color, Option<f64>),
+    // refracted into the medium of a subsurface material
+    Entered(Ray, Color, &'a Medium),
+    Ended,
+}
+
+// Extends a subpath along ray, which leaves its last vertex with throughput in a
+// direction sampled with the solid angle density pdf.
+fn walk<'a>(
+    scene: &'a Scene,
+    ray: Ray,
+    throughput: Color,
+    pdf: f64,
+    max_vertices: usize,
+    path: &mut Vec<Vertex<'a>>,
+) -> Exit<'a> {
+    let mut ray = ray;
+    let mut throughput = throughput;
+    let mut pdf = pdf;
+    let mut bsdf_pdf = None;
+    while path.len() < max_vertices {
+        let record = match scene.world.hit(&ray, 0.0001, f64::INFINITY) {
+            Some(record) => record,
+            None => return Exit::Escaped(ray, throughput, bsdf_pdf),
+        };
+        let previous = path.len() - 1;
+        let mut vertex = Vertex::end(record.p, throughput, 0.0);
+        vertex.record = Some(record);
+        vertex.pdf_fwd = path[previous].area_pdf(pdf, &vertex);
+        path.push(vertex);
+        if path.len() == max_vertices {
+            break;
+        }
+
+        let mut scattered = match record.material.scatter(&ray, &record) {
+            Some(scattered) => scattered,
+            None => break,
+        };
+        scattered.ray.wavelength = ray.wavelength;
+        let attenuation = project(scattered.attenuation, &ray);
+        let current = previous + 1;
+        pdf = record.material.pdf(&ray, &record, scattered.ray.dir);
+        path[current].delta = pdf == 0.0;
+        if let Some(medium) = record.material.medium() {
+            if Vec3::dot(scattered.ray.dir, record.normal) < 0.0 {
+                return Exit::Entered(scattered.ray, throughput * attenuation, medium);
+            }
+        }
+
+        // density of going the other way, as the subpath from the other end would
+        let next = record.p + scattered.ray.dir;
+        let pdf_rev = if pdf > 0.0 {
+            path[current].pdf(next, path[previous].p)
+        } else {
+            0.0
+        };
+        path[previous].pdf_rev = path[current].area_pdf(pdf_rev, &path[previous]);
+        bsdf_pdf = if pdf > 0.0 { Some(pdf) } else { None };
+
+        throughput = throughput * attenuation;
+        if current > MIN_BOUNCES {
+            let survive = attenuation.x.max(attenuation.y).max(attenuation.z).min(1.0);
+            if random() >= survive {
+                break;
+            }
+            throughput /= survive;
+        }
+        ray = scattered.ray;
+    }
+    return Exit::Ended;
+}
+
+fn visibility(scene: &Scene, p: Point, dir: Vec3, distance: f64) -> f64 {
+    let shadow_ray = Ray::new(p, dir);
+    return scene
+        .world
+        .transmittance(&shadow_ray, SHADOW_EPSILON, distance - SHADOW_EPSILON);
+}
+
+// Power heuristic weight for connecting the last vertices of the light and camera
+// subpaths, against every other split of the same path that can be sampled. light_pdf
+// is the solid angle density of the light emitting towards the camera vertex when the
+// light subpath is just the light, camera_pdf that of the camera looking at the light
+// vertex when the camera subpath is just the lens.
+fn mis_weight(
+    light: &[Vertex],
+    camera: &[Vertex],
+    light_pdf: f64,
+    camera_pdf: f64,
+    light_tracing: bool,
+) -> f64 {
+    let (s, t) = (light.len(), camera.len());
+    // the path from the light to the camera as densities from the light side and from
+    // the camera side, and whether the vertex is specular
+    let mut nodes: Vec<(f64, f64, bool)> = light
+        .iter()
+        .map(|v| (v.pdf_fwd, v.pdf_rev, v.delta))
+        .chain(camera.iter().rev().map(|v| (v.pdf_rev, v.pdf_fwd, v.delta)))
+        .collect();
+
+    // the vertices at the connection and their neighbours as sampled from across it
+    let (y, z) = (&light[s - 1], &camera[t - 1]);
+    nodes[s].0 = if s == 1 {
+        y.area_pdf(light_pdf, z)
+    } else {
+        y.area_pdf(y.pdf(light[s - 2].p, z.p), z)
+    };
+    if t > 1 {
+        nodes[s + 1].0 = z.area_pdf(z.pdf(y.p, camera[t - 2].p), &camera[t - 2]);
+    }
+    nodes[s - 1].1 = if t == 1 {
+        z.area_pdf(camera_pdf, y)
+    } else {
+        z.area_pdf(z.pdf(camera[t - 2].p, y.p), y)
+    };
+    if s > 1 {
+        nodes[s - 2].1 = y.area_pdf(y.pdf(z.p, light[s - 2].p), &light[s - 2]);
+    }
+
+    // specular vertices have no density, they stay out of the ratios
+    let remap = |pdf: f64| if pdf == 0.0 { 1.0 } else { pdf };
+    let n = nodes.len();
+    let mut sum = 1.0;
+    // fewer light vertices, down to sampling the light from the camera subpath
+    let mut ratio = 1.0;
+    for i in (1..s).rev() {
+        ratio *= remap(nodes[i].1) / remap(nodes[i].0);
+        if !nodes[i].2 && !nodes[i - 1].2 {
+            sum += ratio * ratio;
+        }
+    }
+    // more light vertices, up to connecting to the lens
+    ratio = 1.0;
+    for i in s..n - 1 {
+        ratio *= remap(nodes[i].0) / remap(nodes[i].1);
+        if !nodes[i].2 && !nodes[i + 1].2 && (i + 2 < n || light_tracing) {
+            sum += ratio * ratio;
+        }
+    }
+    return 1.0 / sum;
+}
+
+impl BidirectionalIntegrator {
+    // Light from the environment and lights at infinity along the camera subpath, with
+    // the same estimators as the path tracer, and the rest of walks into media.
+    fn gather(&self, ray: &Ray, scene: &Scene, camera_path: &[Vertex], exit: Exit) -> Color {
+        let mut color = Color::default();
+        for i in 1..camera_path.len() {
+            let v = &camera_path[i];
+            let record = v.record.unwrap();
+            let ray_in = Ray::new(camera_path[i - 1].p, v.p - camera_path[i - 1].p);
+            for light in scene.lights.iter().filter(|l| l.position().is_none()) {
+                if let Some(sample) = light.sample_li(v.p) {
+                    let f = record.material.eval(&ray_in, &record, sample.dir);
+                    if f.length_double() == 0.0 {
+                        continue;
+                    }
+                    let visibility = visibility(scene, v.p, sample.dir, sample.distance);
+                    color += v.throughput * project(f, ray) * project(sample.li, ray) * visibility;
+                }
+            }
+
+            if let Some(sample) = scene.environment.sample() {
+                let f = record.material.eval(&ray_in, &record, sample.dir);
+                if f.length_double() > 0.0 {
+                    let visibility = visibility(scene, v.p, sample.dir, f64::INFINITY);
+                    let material_pdf = record.material.pdf(&ray_in, &record, sample.dir);
+                    let weight = power_heuristic(sample.pdf, material_pdf);
+                    let radiance = project(f, ray) * project(sample.radiance, ray);
+                    color += v.throughput * radiance * (weight * visibility / sample.pdf);
+                }
+            }
+        }
+
+        match exit {
+            Exit::Escaped(escaped, throughput, bsdf_pdf) => {
+                let emitted = project(scene.environment.emitted(escaped.dir), ray);
+                let weight = match bsdf_pdf {
+                    Some(pdf) => power_heuristic(pdf, scene.environment.pdf(escaped.dir)),
+                    None => 1.0,
+                };
+                color += throughput * emitted * weight;
+            }
+            Exit::Entered(entered, throughput, medium) => {
+                let depth = self.max_depth.saturating_sub(camera_path.len() as u32 - 1);
+                color += throughput
+                    * random_walk(&entered, scene, medium, depth, &|_, _| Color::default());
+            }
+            Exit::Ended => {}
+        }
+        return color;
+    }
+}
+
+impl Integrator for BidirectionalIntegrator {
+    fn li(&mut self, ray: &Ray, scene: &Scene, camera: &dyn Camera, film: &mut Film) -> Color {
+        let max_vertices = self.max_depth as usize + 1;
+        let one = Color::new(1.0, 1.0, 1.0);
+
+        let mut camera_ray = Ray::new(ray.org, ray.dir);
+        camera_ray.wavelength = ray.wavelength;
+        let mut camera_path = vec![Vertex::end(ray.org, one, 1.0)];
+        let camera_pdf = camera.pdf(ray.dir);
+        let exit = walk(
+            scene,
+            camera_ray,
+            one,
+            camera_pdf,
+            max_vertices,
+            &mut camera_path,
+        );
+        let mut color = self.gather(ray, scene, &camera_path, exit);
+
+        let lights: Vec<&dyn Light> = scene
+            .lights
+            .iter()
+            .map(|light| light.as_ref())
+            .filter(|light| light.position().is_some())
+            .collect();
+        if lights.is_empty() {
+            return color;
+        }
+        let selection = 1.0 / lights.len() as f64;
+        let light = lights[random_index(lights.len())];
+        let mut light_path = vec![Vertex::end(light.position().unwrap(), one, selection)];
+        if let Some(emission) = light.sample_le() {
+            let mut light_ray = emission.ray;
+            light_ray.wavelength = ray.wavelength;
+            let throughput = project(emission.le, ray) / selection;
+            walk(
+                scene,
+                light_ray,
+                throughput,
+                emission.pdf,
+                max_vertices,
+                &mut light_path,
+            );
+        }
+
+        // cameras that can't be connected to leave out the strategies ending at the lens
+        let light_tracing = camera_pdf > 0.0;
+        for t in 1..=camera_path.len() {
+            for s in 1..=light_path.len() {
+                if s + t > max_vertices + 1 {
+                    continue;
+                }
+                if t == 1 {
+                    if s > 1 && light_tracing {
+                        connect_to_camera(ray, scene, camera, &light_path[..s], film);
+                    }
+                } else if s == 1 {
+                    let light = lights[random_index(lights.len())];
+                    let camera = &camera_path[..t];
+                    color += connect_to_light(ray, scene, light, selection, camera, light_tracing);
+                } else {
+                    let (light, camera) = (&light_path[..s], &camera_path[..t]);
+                    color += connect(ray, scene, light, camera, light_tracing);
+                }
+            }
+        }
+        return color;
+    }
+}
+
+// s = 1, a light sampled from the last camera vertex
+fn connect_to_light(
+    ray: &Ray,
+    scene: &Scene,
+    light: &dyn Light,
+    selection: f64,
+    camera: &[Vertex],
+    light_tracing: bool,
+) -> Color {
+    let t = camera.len();
+    let z = &camera[t - 1];
+    if z.delta {
+        return Color::default();
+    }
+    let sample = match light.sample_li(z.p) {
+        Some(sample) => sample,
+        None => return Color::default(),
+    };
+    let light_vertex = Vertex::end(
+        z.p + sample.distance * sample.dir,
+        Color::default(),
+        selection,
+    );
+    let f = z.eval(camera[t - 2].p, light_vertex.p);
+    if f.length_double() == 0.0 {
+        return Color::default();
+    }
+    let visibility = visibility(scene, z.p, sample.dir, sample.distance);
+    if visibility == 0.0 {
+        return Color::default();
+    }
+
+    let light_pdf = light.pdf_le(-1.0 * sample.dir);
+    let weight = mis_weight(&[light_vertex], camera, light_pdf, 0.0, light_tracing);
+    return z.throughput
+        * project(f, ray)
+        * project(sample.li, ray)
+        * (weight * visibility / selection);
+}
+
+// t = 1, the last light vertex seen through the lens, added where it lands on the film
+fn connect_to_camera(
+    ray: &Ray,
+    scene: &Scene,
+    camera: &dyn Camera,
+    light: &[Vertex],
+    film: &mut Film,
+) {
+    let s = light.len();
+    let y = &light[s - 1];
+    if y.delta {
+        return;
+    }
+    let connection = match camera.connect(y.p) {
+        Some(connection) => connection,
+        None => return,
+    };
+    let lens = y.p + connection.distance * connection.dir;
+    let f = y.eval(light[s - 2].p, lens);
+    if f.length_double() == 0.0 {
+        return;
+    }
+    let visibility = visibility(scene, y.p, connection.dir, connection.distance);
+    if visibility == 0.0 {
+        return;
+    }
+
+    let camera_vertex = Vertex::end(lens, Color::new(1.0, 1.0, 1.0), 1.0);
+    let weight = mis_weight(light, &[camera_vertex], 0.0, connection.pdf, true);
+    let radiance = y.throughput * project(f, ray) * (weight * connection.importance * visibility);
+    let color = match ray.wavelength {
+        Some(wavelength) => spectral_to_rgb(radiance.x, wavelength),
+        None => radiance,
+    };
+    let x = connection.s * f64::from(film.width);
+    let y = (1.0 - connection.t) * f64::from(film.height);
+    film.add_splat(x, y, color);
+}
+
+// s, t >= 2, an edge between the last vertices of both subpaths
+fn connect(
+    ray: &Ray,
+    scene: &Scene,
+    light: &[Vertex],
+    camera: &[Vertex],
+    light_tracing: bool,
+) -> Color {
+    let (s, t) = (light.len(), camera.len());
+    let (y, z) = (&light[s - 1], &camera[t - 1]);
+    if y.delta || z.delta {
+        return Color::default();
+    }
+    let f_camera = z.eval(camera[t - 2].p, y.p);
+    let f_light = y.eval(light[s - 2].p, z.p);
+    if f_camera.length_double() == 0.0 || f_light.length_double() == 0.0 {
+        return Color::default();
+    }
+    let d = y.p - z.p;
+    let distance = d.length();
+    let visibility = visibility(scene, z.p, d / distance, distance);
+    if visibility == 0.0 {
+        return Color::default();
+    }
+
+    let weight = mis_weight(light, camera, 0.0, 0.0, light_tracing);
+    return z.throughput
+        * y.throughput
+        * project(f_camera, ray)
+        * project(f_light, ray)
+        * (weight * visibility / (distance * distance));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        aabb::Aabb,
+        density_grid::DensityGrid,
+        dielectric::Dielectric,
+        environment::ConstantEnvironment,
+        hittable_list::HittableList,
+        lambertian::Lambertian,
+        math::degrees_to_radians,
+        options::Options,
+        perspective_camera::PerspectiveCamera,
+        point_light::PointLight,
+        renderer::render,
+        sphere::Sphere,
+        spot_light::SpotLight,
+        volume::{GridVolume, HenyeyGreenstein},
+    };
+
+    fn diffuse(r: f64, g: f64, b: f64) -> Box<Lambertian> {
+        return Box::new(Lambertian::new(Color::new(r, g, b)));
+    }
+
+    // average of the left and right halves of the image with each integrator
+    fn halves(scene: &Scene, integrator: &str) -> Vec<Color> {
+        let camera = PerspectiveCamera::new(
+            Point::new(0.0, 1.0, 4.0),
+            Point::new(0.0, 0.5, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            50.0,
+            1.5,
+            0.0,
+            4.0,
+        );
+        let mut options = Options::default();
+        options.width = 24;
+        options.samples_per_pixel = 128;
+        options.max_depth = 6;
+        options.integrator = String::from(integrator);
+        let film = render(scene, &camera, &options, 16).film;
+
+        let mut sums = [Color::default(); 2];
+        for y in 0..film.height {
+            for x in 0..film.width {
+                sums[(2 * x / film.width) as usize] += film.pixel(x, y);
+            }
+        }
+        let count = f64::from(film.width * film.height / 2);
+        return sums.iter().map(|sum| *sum / count).collect();
+    }
+
+    fn assert_matches_path_tracing(scene: &Scene) {
+        let reference = halves(scene, "path");
+        let bidirectional = halves(scene, "bdpt");
+        for (a, b) in reference.iter().zip(&bidirectional) {
+            assert!((*a - *b).length() < 0.05 * a.length(), "{:?} {:?}", a, b);
+        }
+    }
+
+    #[test]
+    fn test_diffuse_scene() {
+        // a sphere on the ground lit from the side by a point light and a spot light
+        let mut world = HittableList::default();
+        world.add(Box::new(Sphere::new(
+            Point::new(0.0, -100.0, 0.0),
+            100.0,
+            diffuse(0.6, 0.6, 0.6),
+        )));
+        world.add(Box::new(Sphere::new(
+            Point::new(0.0, 0.5, 0.0),
+            0.5,
+            diffuse(0.8, 0.3, 0.2),
+        )));
+        let environment = Box::new(ConstantEnvironment::new(Color::new(0.1, 0.1, 0.2)));
+        let mut scene = Scene::new(world, environment);
+        scene.add_light(Box::new(PointLight::new(
+            Point::new(-2.0, 2.0, 1.0),
+            Color::new(60.0, 60.0, 60.0),
+        )));
+        scene.add_light(Box::new(SpotLight::new(
+            Point::new(1.5, 2.5, 0.5),
+            Point::new(0.5, 0.0, 0.0),
+            Color::new(40.0, 30.0, 20.0),
+            degrees_to_radians(20.0),
+            degrees_to_radians(30.0),
+        )));
+        assert_matches_path_tracing(&scene);
+    }
+
+    #[test]
+    fn test_volume_scene() {
+        // fog in front of a wall, lit by a point light above it
+        let mut world = HittableList::default();
+        world.add(Box::new(Sphere::new(
+            Point::new(0.0, 0.0, -102.0),
+            100.0,
+            diffuse(0.7, 0.7, 0.7),
+        )));
+        let bounds = Aabb::new(Point::new(-1.5, -0.5, -1.5), Point::new(1.5, 2.0, 1.0));
+        let phase = HenyeyGreenstein::new(Color::new(0.9, 0.9, 0.9), 0.3);
+        world.add(Box::new(GridVolume::new(
+            bounds,
+            DensityGrid::constant(1.0),
+            0.4,
+            phase,
+        )));
+        let environment = Box::new(ConstantEnvironment::new(Color::default()));
+        let mut scene = Scene::new(world, environment);
+        scene.add_light(Box::new(PointLight::new(
+            Point::new(0.2, 2.8, 0.5),
+            Color::new(30.0, 30.0, 30.0),
+        )));
+        assert_matches_path_tracing(&scene);
+    }
+
+    #[test]
+    fn test_caustic() {
+        // a glass ball focusing a point light onto the ground, seen from the side
+        let mut world = HittableList::default();
+        world.add(Box::new(Sphere::new(
+            Point::new(0.0, -100.0, 0.0),
+            100.0,
+            diffuse(0.8, 0.8, 0.8),
+        )));
+        world.add(Box::new(Sphere::new(
+            Point::new(0.0, 1.0, 0.0),
+            0.5,
+            Box::new(Dielectric::new(1.5)),
+        )));
+        let environment = Box::new(ConstantEnvironment::new(Color::default()));
+        let mut scene = Scene::new(world, environment);
+        scene.add_light(Box::new(PointLight::new(
+            Point::new(0.0, 3.0, 0.0),
+            Color::new(20.0, 20.0, 20.0),
+        )));
+        let camera = PerspectiveCamera::new(
+            Point::new(2.5, 0.8, 0.0),
+            Point::default(),
+            Vec3::new(0.0, 1.0, 0.0),
+            10.0,
+            1.0,
+            0.0,
+            2.6,
+        );
+
+        // average over the middle of the image, where the focus is
+        let focus = |integrator: &str| {
+            let mut options = Options::default();
+            options.width = 16;
+            options.samples_per_pixel = 64;
+            options.max_depth = 6;
+            options.integrator = String::from(integrator);
+            let film = render(&scene, &camera, &options, 16).film;
+            let mut sum = Color::default();
+            for y in 6..10 {
+                for x in 6..10 {
+                    sum += film.pixel(x, y);
+                }
+            }
+            return sum / 16.0;
+        };
+        // paths from the camera can't find a point light through the glass, light
+        // tracing can
+        let path = focus("path");
+        let bidirectional = focus("bdpt");
+        assert!(bidirectional.x > 0.5, "{:?}", bidirectional);
+        assert!(
+            path.x < 0.1 * bidirectional.x,
+            "{:?} {:?}",
+            path,
+            bidirectional
+        );
+    }
+}
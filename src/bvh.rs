@@ -0,0 +1,156 @@
+use crate::{
+    aabb::{axis_of, Aabb},
+    hittable::{HitRecord, Hittable},
+    ray::Ray,
+};
+
+enum BvhNode {
+    Leaf {
+        bounds: Aabb,
+        objects: Vec<usize>,
+    },
+    Interior {
+        bounds: Aabb,
+        children: Box<(BvhNode, BvhNode)>,
+    },
+}
+
+impl BvhNode {
+    fn bounds(&self) -> &Aabb {
+        return match self {
+            BvhNode::Leaf { bounds, .. } => bounds,
+            BvhNode::Interior { bounds, .. } => bounds,
+        };
+    }
+}
+
+const MAX_LEAF_SIZE: usize = 4;
+
+// Bounding volume hierarchy over objects that all have bounding boxes. Built by median
+// splits along the longest axis of the centroids.
+pub struct Bvh {
+    objects: Vec<Box<dyn Hittable>>,
+    root: Option<BvhNode>,
+}
+
+impl Bvh {
+    pub fn new(objects: Vec<Box<dyn Hittable>>) -> Bvh {
+        let bounds: Vec<Aabb> = objects
+            .iter()
+            .map(|o| o.bounding_box().expect("bvh objects need bounding boxes"))
+            .collect();
+        let indices: Vec<usize> = (0..objects.len()).collect();
+        let root = if objects.is_empty() {
+            None
+        } else {
+            Some(build(&bounds, indices))
+        };
+        return Bvh { objects, root };
+    }
+
+    fn hit_node(&self, node: &BvhNode, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        if !node.bounds().hit(ray, t_min, t_max) {
+            return None;
+        }
+        match node {
+            BvhNode::Leaf { objects, .. } => {
+                let mut closest = t_max;
+                let mut record = None;
+                for i in objects {
+                    if let Some(v) = self.objects[*i].hit(ray, t_min, closest) {
+                        closest = v.t;
+                        record = Some(v);
+                    }
+                }
+                return record;
+            }
+            BvhNode::Interior { children, .. } => {
+                let left = self.hit_node(&children.0, ray, t_min, t_max);
+                let closest = left.as_ref().map_or(t_max, |r| r.t);
+                let right = self.hit_node(&children.1, ray, t_min, closest);
+                return right.or(left);
+            }
+        }
+    }
+}
+
+fn build(bounds: &[Aabb], indices: Vec<usize>) -> BvhNode {
+    let mut node_bounds = bounds[indices[0]];
+    let mut centroids = Aabb::new(node_bounds.centroid(), node_bounds.centroid());
+    for i in &indices {
+        node_bounds = node_bounds.union(&bounds[*i]);
+        centroids = centroids.include(bounds[*i].centroid());
+    }
+    if indices.len() <= MAX_LEAF_SIZE {
+        return BvhNode::Leaf {
+            bounds: node_bounds,
+            objects: indices,
+        };
+    }
+
+    let axis = centroids.longest_axis();
+    let mut indices = indices;
+    indices.sort_by(|a, b| {
+        let ca = axis_of(bounds[*a].centroid(), axis);
+        let cb = axis_of(bounds[*b].centroid(), axis);
+        return ca.total_cmp(&cb);
+    });
+    let right = indices.split_off(indices.len() / 2);
+    return BvhNode::Interior {
+        bounds: node_bounds,
+        children: Box::new((build(bounds, indices), build(bounds, right))),
+    };
+}
+
+impl Hittable for Bvh {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        return self.hit_node(self.root.as_ref()?, ray, t_min, t_max);
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        return self.root.as_ref().map(|node| *node.bounds());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        hittable_list::HittableList,
+        lambertian::Lambertian,
+        sphere::Sphere,
+        vec3::{Color, Point, Vec3},
+    };
+    use rand::Rng;
+
+    #[test]
+    fn test_matches_linear_list() {
+        let mut rng = rand::thread_rng();
+        let sphere = |center: Point, radius: f64| {
+            let material = Box::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+            return Box::new(Sphere::new(center, radius, material));
+        };
+        let mut list = HittableList::default();
+        let mut objects: Vec<Box<dyn Hittable>> = vec![];
+        for _ in 0..200 {
+            let center = Point::new(
+                rng.gen_range(-10.0..10.0),
+                rng.gen_range(-10.0..10.0),
+                rng.gen_range(-10.0..10.0),
+            );
+            let radius = rng.gen_range(0.1..1.0);
+            list.add(sphere(center, radius));
+            objects.push(sphere(center, radius));
+        }
+        let bvh = Bvh::new(objects);
+
+        for _ in 0..500 {
+            let origin = Point::new(rng.gen_range(-15.0..15.0), rng.gen_range(-15.0..15.0), 20.0);
+            let dir = Vec3::new(rng.gen_range(-0.5..0.5), rng.gen_range(-0.5..0.5), -1.0);
+            let ray = Ray::new(origin, dir);
+            let expected = list.hit(&ray, 0.001, f64::INFINITY).map(|r| r.t);
+            let actual = bvh.hit(&ray, 0.001, f64::INFINITY).map(|r| r.t);
+            assert_eq!(expected, actual);
+        }
+    }
+}
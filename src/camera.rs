@@ -1,61 +1,42 @@
 use crate::{
-    math::degrees_to_radians,
     ray::Ray,
     vec3::{Point, Vec3},
 };
 
-pub struct Camera {
-    origin: Point,
-    lower_left_corner: Point,
-    horizontal: Vec3,
-    vertical: Vec3,
-    u: Vec3,
-    v: Vec3,
-    lens_radius: f64,
+// A point connected to a position on the lens, for tracing light paths to the camera.
+pub struct CameraConnection {
+    // image position as taken by get_ray
+    pub s: f64,
+    pub t: f64,
+    // unit direction from the point towards the lens
+    pub dir: Vec3,
+    pub distance: f64,
+    // importance arriving at the point, divided by the density of the lens position
+    pub importance: f64,
+    // solid angle density with which get_ray picks the direction from the lens
+    pub pdf: f64,
 }
 
-impl Camera {
-    pub fn new(
-        lookfrom: Point,
-        lookat: Point,
-        vup: Vec3,
-        vfov: f64,
-        aspect_ratio: f64,
-        aperture: f64,
-        focus_dist: f64,
-    ) -> Camera {
-        let theta = degrees_to_radians(vfov);
-        let h = (theta * 0.5).tan();
-        let viewport_height = 2.0 * h;
-        let viewport_width = viewport_height * aspect_ratio;
+pub trait Camera {
+    // (s, t) in [0, 1], t = 0 is the bottom of the image
+    fn get_ray(&self, s: f64, t: f64) -> Ray;
 
-        let w = (lookfrom - lookat).unit_vector();
-        let u = Vec3::cross(vup, w).unit_vector();
-        let v = Vec3::cross(w, u);
-
-        let origin = lookfrom;
-        let horizontal = focus_dist * viewport_width * u;
-        let vertical = focus_dist * viewport_height * v;
-        let lower_left_corner = origin - horizontal / 2.0 - vertical / 2.0 - focus_dist * w;
-        let lens_radius = aperture * 0.5;
-
-        return Camera {
-            origin,
-            lower_left_corner,
-            horizontal,
-            vertical,
-            u,
-            v,
-            lens_radius,
-        };
+    // None when p is out of view, or for cameras that can't be connected to.
+    fn connect(&self, _p: Point) -> Option<CameraConnection> {
+        return None;
     }
 
-    pub fn get_ray(&self, s: f64, t: f64) -> Ray {
-        let rd = self.lens_radius * Vec3::random_in_unit_disk();
-        let offset = self.u * rd.x + self.v * rd.y;
-        return Ray::new(
-            self.origin + offset,
-            self.lower_left_corner + s * self.horizontal + t * self.vertical - self.origin - offset,
-        );
+    // Solid angle density of the direction of a ray from get_ray, 0 for cameras
+    // without connect.
+    fn pdf(&self, _dir: Vec3) -> f64 {
+        return 0.0;
     }
 }
+
+// Right handed basis looking from lookfrom towards lookat: u right, v up, w backwards.
+pub fn look_at_basis(lookfrom: Point, lookat: Point, vup: Vec3) -> (Vec3, Vec3, Vec3) {
+    let w = (lookfrom - lookat).unit_vector();
+    let u = Vec3::cross(vup, w).unit_vector();
+    let v = Vec3::cross(w, u);
+    return (u, v, w);
+}
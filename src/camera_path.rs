@@ -0,0 +1,215 @@
+use std::f64::consts::PI;
+
+use crate::vec3::{Point, Vec3};
+
+// Animated camera parameters. vfov and focus_dist are None when the camera keeps its own.
+#[derive(Clone, Copy, Debug)]
+pub struct CameraState {
+    pub lookfrom: Point,
+    pub lookat: Point,
+    pub vfov: Option<f64>,
+    pub focus_dist: Option<f64>,
+}
+
+pub trait CameraAnimation {
+    fn state_at(&self, frame: f64) -> CameraState;
+    // first and last frame, inclusive
+    fn frame_range(&self) -> (u32, u32);
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Interpolation {
+    Linear,
+    CatmullRom,
+}
+
+pub struct KeyframePath {
+    keyframes: Vec<(f64, CameraState)>,
+    interpolation: Interpolation,
+}
+
+impl KeyframePath {
+    pub fn new(keyframes: Vec<(f64, CameraState)>, interpolation: Interpolation) -> KeyframePath {
+        assert!(!keyframes.is_empty());
+        let mut keyframes = keyframes;
+        keyframes.sort_by(|a, b| a.0.total_cmp(&b.0));
+        return KeyframePath {
+            keyframes,
+            interpolation,
+        };
+    }
+}
+
+impl CameraAnimation for KeyframePath {
+    fn state_at(&self, frame: f64) -> CameraState {
+        let n = self.keyframes.len();
+        if frame <= self.keyframes[0].0 {
+            return self.keyframes[0].1;
+        }
+        if frame >= self.keyframes[n - 1].0 {
+            return self.keyframes[n - 1].1;
+        }
+
+        let i = self.keyframes.partition_point(|k| k.0 <= frame) - 1;
+        let (f1, k1) = self.keyframes[i];
+        let (f2, k2) = self.keyframes[i + 1];
+        let t = (frame - f1) / (f2 - f1);
+        match self.interpolation {
+            Interpolation::Linear => {
+                return CameraState {
+                    lookfrom: lerp(k1.lookfrom, k2.lookfrom, t),
+                    lookat: lerp(k1.lookat, k2.lookat, t),
+                    vfov: lerp_option(k1.vfov, k2.vfov, |a, b| a + (b - a) * t),
+                    focus_dist: lerp_option(k1.focus_dist, k2.focus_dist, |a, b| a + (b - a) * t),
+                };
+            }
+            Interpolation::CatmullRom => {
+                // end points are repeated so the curve stops at the first and last keys
+                let k0 = self.keyframes[i.saturating_sub(1)].1;
+                let k3 = self.keyframes[(i + 2).min(n - 1)].1;
+                let scalar = |a: Option<f64>, b: Option<f64>, c: Option<f64>, d: Option<f64>| match (
+                    a, b, c, d,
+                ) {
+                    (Some(a), Some(b), Some(c), Some(d)) => Some(catmull_rom(a, b, c, d, t)),
+                    _ => lerp_option(b, c, |b, c| b + (c - b) * t),
+                };
+                return CameraState {
+                    lookfrom: catmull_rom_vec3(
+                        k0.lookfrom,
+                        k1.lookfrom,
+                        k2.lookfrom,
+                        k3.lookfrom,
+                        t,
+                    ),
+                    lookat: catmull_rom_vec3(k0.lookat, k1.lookat, k2.lookat, k3.lookat, t),
+                    vfov: scalar(k0.vfov, k1.vfov, k2.vfov, k3.vfov),
+                    focus_dist: scalar(k0.focus_dist, k1.focus_dist, k2.focus_dist, k3.focus_dist),
+                };
+            }
+        }
+    }
+
+    fn frame_range(&self) -> (u32, u32) {
+        let first = self.keyframes[0].0.max(0.0) as u32;
+        let last = self.keyframes[self.keyframes.len() - 1].0.max(0.0) as u32;
+        return (first, last);
+    }
+}
+
+// Orbits lookfrom once around the vertical axis through lookat.
+pub struct Turntable {
+    base: CameraState,
+    frames: u32,
+}
+
+impl Turntable {
+    pub fn new(base: CameraState, frames: u32) -> Turntable {
+        return Turntable {
+            base,
+            frames: frames.max(1),
+        };
+    }
+}
+
+impl CameraAnimation for Turntable {
+    fn state_at(&self, frame: f64) -> CameraState {
+        let angle = 2.0 * PI * frame / self.frames as f64;
+        let offset = self.base.lookfrom - self.base.lookat;
+        let rotated = Vec3::new(
+            offset.x * angle.cos() + offset.z * angle.sin(),
+            offset.y,
+            -offset.x * angle.sin() + offset.z * angle.cos(),
+        );
+        return CameraState {
+            lookfrom: self.base.lookat + rotated,
+            ..self.base
+        };
+    }
+
+    fn frame_range(&self) -> (u32, u32) {
+        return (0, self.frames - 1);
+    }
+}
+
+fn lerp(a: Vec3, b: Vec3, t: f64) -> Vec3 {
+    return a + t * (b - a);
+}
+
+fn lerp_option(a: Option<f64>, b: Option<f64>, f: impl Fn(f64, f64) -> f64) -> Option<f64> {
+    return match (a, b) {
+        (Some(a), Some(b)) => Some(f(a, b)),
+        _ => a.or(b),
+    };
+}
+
+fn catmull_rom(p0: f64, p1: f64, p2: f64, p3: f64, t: f64) -> f64 {
+    let t2 = t * t;
+    let t3 = t2 * t;
+    return 0.5
+        * (2.0 * p1
+            + (-p0 + p2) * t
+            + (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3) * t2
+            + (-p0 + 3.0 * p1 - 3.0 * p2 + p3) * t3);
+}
+
+fn catmull_rom_vec3(p0: Vec3, p1: Vec3, p2: Vec3, p3: Vec3, t: f64) -> Vec3 {
+    return Vec3::new(
+        catmull_rom(p0.x, p1.x, p2.x, p3.x, t),
+        catmull_rom(p0.y, p1.y, p2.y, p3.y, t),
+        catmull_rom(p0.z, p1.z, p2.z, p3.z, t),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPSILON: f64 = 0.000001;
+
+    fn state(x: f64, vfov: f64) -> CameraState {
+        return CameraState {
+            lookfrom: Point::new(x, 0.0, 0.0),
+            lookat: Point::default(),
+            vfov: Some(vfov),
+            focus_dist: None,
+        };
+    }
+
+    #[test]
+    fn test_linear() {
+        let path = KeyframePath::new(
+            vec![(10.0, state(4.0, 40.0)), (0.0, state(0.0, 20.0))],
+            Interpolation::Linear,
+        );
+        let s = path.state_at(2.5);
+        assert!((s.lookfrom.x - 1.0).abs() <= EPSILON);
+        assert!((s.vfov.unwrap() - 25.0).abs() <= EPSILON);
+        assert!(s.focus_dist.is_none());
+        assert!((path.state_at(20.0).lookfrom.x - 4.0).abs() <= EPSILON);
+        assert_eq!(path.frame_range(), (0, 10));
+    }
+
+    #[test]
+    fn test_catmull_rom_passes_through_keys() {
+        let path = KeyframePath::new(
+            vec![
+                (0.0, state(0.0, 20.0)),
+                (10.0, state(1.0, 20.0)),
+                (20.0, state(5.0, 20.0)),
+            ],
+            Interpolation::CatmullRom,
+        );
+        assert!((path.state_at(10.0).lookfrom.x - 1.0).abs() <= EPSILON);
+        // the spline is smooth through the keys instead of following straight segments
+        assert!((path.state_at(15.0).lookfrom.x - 3.0).abs() > EPSILON);
+    }
+
+    #[test]
+    fn test_turntable() {
+        let turntable = Turntable::new(state(2.0, 20.0), 4);
+        let quarter = turntable.state_at(1.0);
+        assert!(quarter.lookfrom.x.abs() <= EPSILON);
+        assert!((quarter.lookfrom.z.abs() - 2.0).abs() <= EPSILON);
+        assert_eq!(turntable.frame_range(), (0, 3));
+    }
+}
@@ -0,0 +1,186 @@
+use crate::{
+    aabb::Aabb,
+    hittable::{HitInterval, HitRecord, Hittable},
+    ray::Ray,
+};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum CsgOperation {
+    Union,
+    Intersection,
+    Difference,
+}
+
+impl CsgOperation {
+    pub fn parse(name: &str) -> Result<CsgOperation, String> {
+        return match name {
+            "union" => Ok(CsgOperation::Union),
+            "intersection" => Ok(CsgOperation::Intersection),
+            "difference" => Ok(CsgOperation::Difference),
+            other => Err(format!("unknown csg operation {}", other)),
+        };
+    }
+
+    fn inside(&self, in_a: bool, in_b: bool) -> bool {
+        return match self {
+            CsgOperation::Union => in_a || in_b,
+            CsgOperation::Intersection => in_a && in_b,
+            CsgOperation::Difference => in_a && !in_b,
+        };
+    }
+}
+
+// Boolean combination of two closed objects. Each side keeps its own materials, so the
+// surface carved out of a by b in a difference shows the material of b.
+pub struct Csg {
+    operation: CsgOperation,
+    a: Box<dyn Hittable>,
+    b: Box<dyn Hittable>,
+}
+
+impl Csg {
+    pub fn new(operation: CsgOperation, a: Box<dyn Hittable>, b: Box<dyn Hittable>) -> Csg {
+        return Csg { operation, a, b };
+    }
+}
+
+impl Hittable for Csg {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        for interval in self.intervals(ray) {
+            if t_min < interval.enter.t && interval.enter.t < t_max {
+                return Some(interval.enter);
+            }
+            if t_min < interval.exit.t && interval.exit.t < t_max {
+                return Some(interval.exit);
+            }
+        }
+        return None;
+    }
+
+    // Sweeps the boundaries of both children in order and keeps the ones where being
+    // inside the combination changes.
+    fn intervals(&self, ray: &Ray) -> Vec<HitInterval<'_>> {
+        let mut events: Vec<(HitRecord, bool)> = vec![];
+        for interval in self.a.intervals(ray) {
+            events.push((interval.enter, true));
+            events.push((interval.exit, true));
+        }
+        for interval in self.b.intervals(ray) {
+            events.push((interval.enter, false));
+            events.push((interval.exit, false));
+        }
+        events.sort_by(|x, y| x.0.t.total_cmp(&y.0.t));
+
+        let mut intervals = vec![];
+        let mut in_a = false;
+        let mut in_b = false;
+        let mut enter: Option<HitRecord> = None;
+        for (record, from_a) in events {
+            let was_inside = self.operation.inside(in_a, in_b);
+            if from_a {
+                in_a = record.front_face;
+            } else {
+                in_b = record.front_face;
+            }
+            let inside = self.operation.inside(in_a, in_b);
+            if inside == was_inside {
+                continue;
+            }
+
+            // the normal already faces the ray, only the side changes, e.g. leaving b
+            // enters a difference
+            let mut record = record;
+            record.front_face = inside;
+            if inside {
+                enter = Some(record);
+            } else if let Some(enter) = enter.take() {
+                intervals.push(HitInterval {
+                    enter,
+                    exit: record,
+                });
+            }
+        }
+        return intervals;
+    }
+
+    // what remains of a difference or intersection lies inside a
+    fn bounding_box(&self) -> Option<Aabb> {
+        let a = self.a.bounding_box()?;
+        if self.operation != CsgOperation::Union {
+            return Some(a);
+        }
+        return Some(a.union(&self.b.bounding_box()?));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        lambertian::Lambertian,
+        sphere::Sphere,
+        vec3::{Color, Point, Vec3},
+    };
+
+    const EPSILON: f64 = 0.000001;
+
+    fn sphere(x: f64, radius: f64) -> Box<dyn Hittable> {
+        return Box::new(Sphere::new(
+            Point::new(x, 0.0, 0.0),
+            radius,
+            Box::new(Lambertian::new(Color::new(0.5, 0.5, 0.5))),
+        ));
+    }
+
+    fn ray() -> Ray {
+        // along +x from x = -10
+        return Ray::new(Point::new(-10.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn test_sphere_intervals() {
+        let sphere = sphere(0.0, 1.0);
+        let intervals = sphere.intervals(&ray());
+        assert_eq!(intervals.len(), 1);
+        assert!((intervals[0].enter.t - 9.0).abs() <= EPSILON);
+        assert!((intervals[0].exit.t - 11.0).abs() <= EPSILON);
+    }
+
+    #[test]
+    fn test_union_and_intersection() {
+        let union = Csg::new(CsgOperation::Union, sphere(0.0, 1.0), sphere(1.5, 1.0));
+        let intervals = union.intervals(&ray());
+        assert_eq!(intervals.len(), 1);
+        assert!((intervals[0].enter.t - 9.0).abs() <= EPSILON);
+        assert!((intervals[0].exit.t - 12.5).abs() <= EPSILON);
+
+        // a lens shaped overlap
+        let lens = Csg::new(
+            CsgOperation::Intersection,
+            sphere(0.0, 1.0),
+            sphere(1.5, 1.0),
+        );
+        let record = lens.hit(&ray(), 0.001, f64::INFINITY).unwrap();
+        assert!((record.t - 10.5).abs() <= EPSILON);
+        assert!(record.front_face);
+    }
+
+    #[test]
+    fn test_difference() {
+        // hollow shell, the ray passes through the wall, the cavity and the wall again
+        let shell = Csg::new(CsgOperation::Difference, sphere(0.0, 2.0), sphere(0.0, 1.0));
+        let intervals = shell.intervals(&ray());
+        assert_eq!(intervals.len(), 2);
+        assert!((intervals[0].exit.t - 9.0).abs() <= EPSILON);
+        assert!(!intervals[0].exit.front_face);
+        assert!((intervals[1].enter.t - 11.0).abs() <= EPSILON);
+        assert!(intervals[1].enter.front_face);
+
+        // starting inside the cavity the first surface is the inner wall, entered
+        let inside = Ray::new(Point::default(), Vec3::new(1.0, 0.0, 0.0));
+        let record = shell.hit(&inside, 0.001, f64::INFINITY).unwrap();
+        assert!((record.t - 1.0).abs() <= EPSILON);
+        assert!(record.front_face);
+        assert!(Vec3::dot(record.normal, inside.dir) < 0.0);
+    }
+}
@@ -0,0 +1,288 @@
+use std::rc::Rc;
+
+use crate::{
+    aabb::Aabb,
+    bvh::Bvh,
+    hittable::{HitRecord, Hittable},
+    material::Material,
+    onb::Onb,
+    ray::Ray,
+    sampler::random_range,
+    vec3::{Point, Vec3},
+};
+
+// Cubic Bezier curve swept by a flat ribbon that always faces the ray, with a width
+// that changes linearly from the root to the tip. Shaded like a thin cylinder.
+pub struct Curve {
+    cp: [Point; 4],
+    width: (f64, f64),
+    material: Rc<dyn Material>,
+}
+
+impl Curve {
+    pub fn new(cp: [Point; 4], width: (f64, f64), material: Rc<dyn Material>) -> Curve {
+        return Curve {
+            cp,
+            width,
+            material,
+        };
+    }
+
+    fn max_width(&self) -> f64 {
+        return self.width.0.max(self.width.1);
+    }
+
+    // Recursively splits the curve until the pieces are close to straight, then tests
+    // the segment between their end points. cp is in ray space, where the ray starts
+    // at the origin and runs along z.
+    fn recurse(
+        &self,
+        cp: &[Point; 4],
+        u0: f64,
+        u1: f64,
+        depth: u32,
+        z_min: f64,
+        z_max: f64,
+    ) -> Option<(f64, f64, f64)> {
+        let half = 0.5 * self.max_width();
+        let bounds = Aabb::from_points(cp).expand(half);
+        if bounds.min.x > 0.0 || bounds.max.x < 0.0 || bounds.min.y > 0.0 || bounds.max.y < 0.0 {
+            return None;
+        }
+        if bounds.max.z < z_min || bounds.min.z > z_max {
+            return None;
+        }
+
+        if depth > 0 {
+            let (a, b) = split_bezier(cp);
+            let mid = 0.5 * (u0 + u1);
+            let first = self.recurse(&a, u0, mid, depth - 1, z_min, z_max);
+            let z_max = first.map_or(z_max, |(z, _, _)| z);
+            return self.recurse(&b, mid, u1, depth - 1, z_min, z_max).or(first);
+        }
+
+        // the closest point of the segment to the ray, rejected past the end points so
+        // neighbouring segments do not both report it
+        let (p0, p3) = (cp[0], cp[3]);
+        let d = Vec3::new(p3.x - p0.x, p3.y - p0.y, 0.0);
+        let length_squared = d.length_double();
+        if length_squared == 0.0 {
+            return None;
+        }
+        let w = -(p0.x * d.x + p0.y * d.y) / length_squared;
+        if !(0.0..=1.0).contains(&w) {
+            return None;
+        }
+        let p = eval_bezier(cp, w);
+        let u = u0 + w * (u1 - u0);
+        let half_width = 0.5 * (self.width.0 + u * (self.width.1 - self.width.0));
+        let distance_squared = p.x * p.x + p.y * p.y;
+        if distance_squared > half_width * half_width {
+            return None;
+        }
+        if p.z <= z_min || p.z >= z_max {
+            return None;
+        }
+        // distance from the middle of the ribbon, 0 to 1
+        return Some((p.z, u, distance_squared.sqrt() / half_width));
+    }
+}
+
+impl Hittable for Curve {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        let length = ray.dir.length();
+        let onb = Onb::new(ray.dir);
+        let to_ray_space = |p: Point| {
+            let p = p - ray.org;
+            return Vec3::new(
+                Vec3::dot(p, onb.u),
+                Vec3::dot(p, onb.v),
+                Vec3::dot(p, onb.w),
+            );
+        };
+        let cp = self.cp.map(to_ray_space);
+
+        // enough splits to keep the segments within a fraction of the width, as in pbrt
+        let mut l0: f64 = 0.0;
+        for i in 0..2 {
+            let d = cp[i] - 2.0 * cp[i + 1] + cp[i + 2];
+            l0 = l0.max(d.x.abs()).max(d.y.abs()).max(d.z.abs());
+        }
+        let epsilon = 0.05 * self.max_width();
+        let depth = if l0 > 0.0 {
+            let r0 = (std::f64::consts::SQRT_2 * 6.0 * l0 / (8.0 * epsilon)).log2() / 2.0;
+            r0.clamp(0.0, 10.0) as u32
+        } else {
+            0
+        };
+
+        // t_min is applied while recursing, so a hit before it can't hide a later one
+        let z_min = (t_min * length).max(0.0);
+        let z_max = t_max * length;
+        let (z, u, offset) = self.recurse(&cp, 0.0, 1.0, depth, z_min, z_max)?;
+        let t = z / length;
+
+        // the normal turns from facing the ray to the sides across the ribbon
+        let p = ray.at(t);
+        let tangent = bezier_derivative(&self.cp, u).unit_vector();
+        let view = -1.0 * ray.dir.unit_vector();
+        let facing = view - Vec3::dot(view, tangent) * tangent;
+        if facing.length_double() == 0.0 {
+            return None;
+        }
+        let facing = facing.unit_vector();
+        let across = Vec3::cross(tangent, facing);
+        let offset = if Vec3::dot(p - eval_bezier(&self.cp, u), across) < 0.0 {
+            -offset
+        } else {
+            offset
+        };
+        let normal = (1.0 - offset * offset).max(0.0).sqrt() * facing + offset * across;
+
+        let mut record = HitRecord::new(p, normal.unit_vector(), t, true, &*self.material);
+        record.u = u;
+        record.v = 0.5 * (offset + 1.0);
+        record.dpdu = bezier_derivative(&self.cp, u);
+        record.dpdv = (self.width.0 + u * (self.width.1 - self.width.0)) * across;
+        return Some(record);
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        // the curve lies in the convex hull of its control points
+        return Some(Aabb::from_points(&self.cp).expand(0.5 * self.max_width()));
+    }
+}
+
+pub fn eval_bezier(cp: &[Point; 4], u: f64) -> Point {
+    let s = 1.0 - u;
+    return s * s * s * cp[0]
+        + 3.0 * s * s * u * cp[1]
+        + 3.0 * s * u * u * cp[2]
+        + u * u * u * cp[3];
+}
+
+pub fn bezier_derivative(cp: &[Point; 4], u: f64) -> Vec3 {
+    let s = 1.0 - u;
+    return 3.0
+        * (s * s * (cp[1] - cp[0]) + 2.0 * s * u * (cp[2] - cp[1]) + u * u * (cp[3] - cp[2]));
+}
+
+// de Casteljau split at the middle
+fn split_bezier(cp: &[Point; 4]) -> ([Point; 4], [Point; 4]) {
+    let m01 = 0.5 * (cp[0] + cp[1]);
+    let m12 = 0.5 * (cp[1] + cp[2]);
+    let m23 = 0.5 * (cp[2] + cp[3]);
+    let m012 = 0.5 * (m01 + m12);
+    let m123 = 0.5 * (m12 + m23);
+    let mid = 0.5 * (m012 + m123);
+    return ([cp[0], m01, m012, mid], [mid, m123, m23, cp[3]]);
+}
+
+// Hairs growing out of a sphere and drooping under their own weight, in a bvh.
+pub fn fur(
+    center: Point,
+    radius: f64,
+    count: usize,
+    length: f64,
+    width: f64,
+    material: Box<dyn Material>,
+) -> Bvh {
+    let material: Rc<dyn Material> = Rc::from(material);
+    let down = Vec3::new(0.0, -1.0, 0.0);
+    let mut hairs: Vec<Box<dyn Hittable>> = vec![];
+    for _ in 0..count {
+        let n = Vec3::random_unit_vector();
+        let length = length * random_range(0.7, 1.0);
+        let droop = random_range(0.1, 0.35) * length;
+        let root = center + (radius - 0.1 * width) * n;
+        let cp = [
+            root,
+            root + length / 3.0 * n,
+            root + 2.0 * length / 3.0 * n + 0.5 * droop * down,
+            root + length * n + droop * down + 0.1 * length * Vec3::random_in_unit_sphere(),
+        ];
+        hairs.push(Box::new(Curve::new(
+            cp,
+            (width, 0.2 * width),
+            material.clone(),
+        )));
+    }
+    return Bvh::new(hairs);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lambertian::Lambertian, vec3::Color};
+
+    const EPSILON: f64 = 0.000001;
+
+    fn curve() -> Curve {
+        // a straight curve along x with width 0.2
+        let cp = [
+            Point::new(-1.0, 0.0, 0.0),
+            Point::new(-1.0 / 3.0, 0.0, 0.0),
+            Point::new(1.0 / 3.0, 0.0, 0.0),
+            Point::new(1.0, 0.0, 0.0),
+        ];
+        let material = Rc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        return Curve::new(cp, (0.2, 0.2), material);
+    }
+
+    #[test]
+    fn test_hit() {
+        let curve = curve();
+        let ray = Ray::new(Point::new(0.5, 0.05, 4.0), Vec3::new(0.0, 0.0, -2.0));
+        let record = curve.hit(&ray, 0.001, f64::INFINITY).unwrap();
+        assert!((record.t - 2.0).abs() <= EPSILON);
+        assert!((record.u - 0.75).abs() <= EPSILON);
+        assert!((record.v - 0.25).abs() <= EPSILON);
+        // half way to the edge the normal leans towards it by 30 degrees
+        assert!((record.normal.y - 0.5).abs() <= EPSILON);
+        assert!(record.normal.z > 0.0);
+
+        assert!(curve.hit(&ray, 0.001, 1.5).is_none());
+        let miss = Ray::new(Point::new(0.5, 0.15, 4.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(curve.hit(&miss, 0.001, f64::INFINITY).is_none());
+        let past_end = Ray::new(Point::new(1.05, 0.0, 4.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(curve.hit(&past_end, 0.001, f64::INFINITY).is_none());
+    }
+
+    #[test]
+    fn test_bent_curve() {
+        let cp = [
+            Point::new(0.0, 0.0, 0.0),
+            Point::new(0.0, 1.0, 0.0),
+            Point::new(1.0, 1.0, 0.0),
+            Point::new(1.0, 0.0, 0.0),
+        ];
+        let material = Rc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        let curve = Curve::new(cp, (0.02, 0.02), material);
+        // the top of the arch is at y = 0.75
+        let ray = Ray::new(Point::new(0.5, 0.75, 3.0), Vec3::new(0.0, 0.0, -1.0));
+        let record = curve.hit(&ray, 0.001, f64::INFINITY).unwrap();
+        assert!((record.t - 3.0).abs() <= EPSILON);
+        assert!((record.u - 0.5).abs() <= 0.01);
+        let inside = Ray::new(Point::new(0.5, 0.5, 3.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(curve.hit(&inside, 0.001, f64::INFINITY).is_none());
+    }
+
+    #[test]
+    fn test_hit_past_t_min() {
+        // a hairpin the ray crosses twice, once in each half of the curve
+        let cp = [
+            Point::new(-1.0, 0.0, -1.0),
+            Point::new(3.0, 0.0, -1.0),
+            Point::new(3.0, 0.0, -3.0),
+            Point::new(-1.0, 0.0, -3.0),
+        ];
+        let material = Rc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        let curve = Curve::new(cp, (0.1, 0.1), material);
+        let ray = Ray::new(Point::default(), Vec3::new(0.0, 0.0, -1.0));
+        let near = curve.hit(&ray, 0.001, f64::INFINITY).unwrap();
+        assert!(near.t < 1.5);
+        let far = curve.hit(&ray, near.t + 0.1, f64::INFINITY).unwrap();
+        assert!(far.t > 2.5);
+        assert!(far.u > 0.5);
+    }
+}
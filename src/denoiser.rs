@@ -0,0 +1,202 @@
+use crate::{
+    image::Image,
+    vec3::{Color, Vec3},
+};
+
+// B3 spline taps of the a-trous wavelet transform
+const KERNEL: [f64; 5] = [1.0 / 16.0, 1.0 / 4.0, 3.0 / 8.0, 1.0 / 4.0, 1.0 / 16.0];
+
+// Edge-avoiding a-trous wavelet filter (Dammertz et al. 2010) guided by the normal and
+// albedo passes. The albedo is divided out before filtering so texture detail survives,
+// and multiplied back in afterwards.
+pub struct Denoiser {
+    pub iterations: u32,
+    pub sigma_color: f64,
+    pub sigma_normal: f64,
+    pub sigma_albedo: f64,
+}
+
+impl Denoiser {
+    pub fn default() -> Denoiser {
+        return Denoiser {
+            iterations: 5,
+            sigma_color: 0.6,
+            sigma_normal: 0.1,
+            sigma_albedo: 0.1,
+        };
+    }
+
+    pub fn denoise(&self, color: &Image, normal: &Image, albedo: &Image) -> Image {
+        let width = color.width;
+        let height = color.height;
+
+        let mut illumination = Image::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                illumination.set(x, y, color.get(x, y) / safe_albedo(albedo.get(x, y)));
+            }
+        }
+
+        for i in 0..self.iterations {
+            let step = 1 << i;
+            // later passes see smoother input, so the color edge stopping is tightened
+            let sigma_color = self.sigma_color / f64::from(1 << i);
+            illumination = self.filter_pass(&illumination, normal, albedo, step, sigma_color);
+        }
+
+        let mut result = Image::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                result.set(x, y, illumination.get(x, y) * safe_albedo(albedo.get(x, y)));
+            }
+        }
+        return result;
+    }
+
+    fn filter_pass(
+        &self,
+        input: &Image,
+        normal: &Image,
+        albedo: &Image,
+        step: i64,
+        sigma_color: f64,
+    ) -> Image {
+        let width = input.width as i64;
+        let height = input.height as i64;
+        let mut output = Image::new(input.width, input.height);
+
+        for y in 0..height {
+            for x in 0..width {
+                let c0 = input.get(x as u32, y as u32);
+                let n0 = normal.get(x as u32, y as u32);
+                let a0 = albedo.get(x as u32, y as u32);
+                let mut sum = Color::default();
+                let mut weight_sum = 0.0;
+
+                for (ky, ty) in KERNEL.iter().enumerate() {
+                    for (kx, tx) in KERNEL.iter().enumerate() {
+                        let sx = (x + (kx as i64 - 2) * step).clamp(0, width - 1) as u32;
+                        let sy = (y + (ky as i64 - 2) * step).clamp(0, height - 1) as u32;
+                        let c = input.get(sx, sy);
+                        let n = normal.get(sx, sy);
+                        let a = albedo.get(sx, sy);
+
+                        let w_color = gaussian((c - c0).length_double(), sigma_color);
+                        let w_normal = gaussian((n - n0).length_double(), self.sigma_normal);
+                        let w_albedo = gaussian((a - a0).length_double(), self.sigma_albedo);
+                        let weight = tx * ty * w_color * w_normal * w_albedo;
+
+                        sum += weight * c;
+                        weight_sum += weight;
+                    }
+                }
+                output.set(x as u32, y as u32, sum / weight_sum);
+            }
+        }
+        return output;
+    }
+}
+
+fn gaussian(distance_squared: f64, sigma: f64) -> f64 {
+    return (-distance_squared / (sigma * sigma)).exp();
+}
+
+fn safe_albedo(albedo: Color) -> Vec3 {
+    return Vec3::new(albedo.x.max(0.01), albedo.y.max(0.01), albedo.z.max(0.01));
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use super::*;
+    use crate::{
+        aov::Aov,
+        environment::GradientEnvironment,
+        hittable_list::HittableList,
+        lambertian::Lambertian,
+        metal::Metal,
+        options::Options,
+        perspective_camera::PerspectiveCamera,
+        renderer::render,
+        sampler::{with_sampler, SeededSampler},
+        scene::Scene,
+        sphere::Sphere,
+        vec3::Point,
+    };
+
+    fn rmse(a: &Image, b: &Image) -> f64 {
+        let mut sum = 0.0;
+        for y in 0..a.height {
+            for x in 0..a.width {
+                sum += (a.get(x, y) - b.get(x, y)).length_double();
+            }
+        }
+        return (sum / (3.0 * (a.width * a.height) as f64)).sqrt();
+    }
+
+    fn test_scene() -> Scene {
+        let mut world = HittableList::default();
+        world.add(Box::new(Sphere::new(
+            Point::new(0.0, -100.5, -1.0),
+            100.0,
+            Box::new(Lambertian::new(Color::new(0.8, 0.8, 0.0))),
+        )));
+        world.add(Box::new(Sphere::new(
+            Point::new(0.0, 0.0, -1.0),
+            0.5,
+            Box::new(Lambertian::new(Color::new(0.1, 0.2, 0.5))),
+        )));
+        world.add(Box::new(Sphere::new(
+            Point::new(1.0, 0.0, -1.0),
+            0.5,
+            Box::new(Metal::new(Color::new(0.8, 0.6, 0.2), 0.3)),
+        )));
+        let environment =
+            GradientEnvironment::new(Color::new(1.0, 1.0, 1.0), Color::new(0.5, 0.7, 1.0));
+        return Scene::new(world, Box::new(environment));
+    }
+
+    #[test]
+    fn test_denoised_is_closer_to_reference() {
+        let scene = test_scene();
+        let camera = PerspectiveCamera::new(
+            Point::new(0.0, 0.0, 1.0),
+            Point::new(0.0, 0.0, -1.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            60.0,
+            2.0,
+            0.0,
+            2.0,
+        );
+        let mut options = Options::default();
+        options.width = 96;
+        options.max_depth = 8;
+        options.aovs = vec![Aov::Normal, Aov::Albedo];
+
+        // seeded, so the noise and with it the errors are the same on every run
+        let seeded = |seed| Rc::new(RefCell::new(SeededSampler::new(seed)));
+        options.samples_per_pixel = 4;
+        let noisy = with_sampler(seeded(1), || render(&scene, &camera, &options, 48));
+        options.samples_per_pixel = 256;
+        let reference = with_sampler(seeded(2), || render(&scene, &camera, &options, 48));
+        let reference = reference.film.to_image();
+
+        let aovs = noisy.aovs.unwrap();
+        let noisy_image = noisy.film.to_image();
+        let denoised = Denoiser::default().denoise(
+            &noisy_image,
+            &aovs.to_image(Aov::Normal),
+            &aovs.to_image(Aov::Albedo),
+        );
+
+        let noisy_error = rmse(&noisy_image, &reference);
+        let denoised_error = rmse(&denoised, &reference);
+        assert!(
+            denoised_error < 0.8 * noisy_error,
+            "denoised {} noisy {}",
+            denoised_error,
+            noisy_error
+        );
+    }
+}
@@ -0,0 +1,246 @@
+use crate::{
+    aabb::Aabb,
+    vec3::{Point, Vec3},
+};
+
+// Densities on a regular grid stretched over the unit cube, stored x fastest and looked
+// up with trilinear interpolation between the voxel centers.
+pub struct DensityGrid {
+    nx: usize,
+    ny: usize,
+    nz: usize,
+    values: Vec<f64>,
+    max: f64,
+}
+
+impl DensityGrid {
+    pub fn new(nx: usize, ny: usize, nz: usize, values: Vec<f64>) -> DensityGrid {
+        assert_eq!(values.len(), nx * ny * nz);
+        let max = values.iter().fold(0.0, |m: f64, v| m.max(*v));
+        return DensityGrid {
+            nx,
+            ny,
+            nz,
+            values,
+            max,
+        };
+    }
+
+    pub fn constant(value: f64) -> DensityGrid {
+        return DensityGrid::new(1, 1, 1, vec![value]);
+    }
+
+    // Fractal value noise in [0, 1], thinned out below threshold. With ball the noise
+    // fades towards the faces of the cube, which gives a cloud.
+    pub fn noise(
+        resolution: usize,
+        frequency: f64,
+        octaves: u32,
+        threshold: f64,
+        ball: bool,
+        seed: u32,
+    ) -> DensityGrid {
+        let mut values = Vec::with_capacity(resolution * resolution * resolution);
+        for z in 0..resolution {
+            for y in 0..resolution {
+                for x in 0..resolution {
+                    let p = Point::new(x as f64 + 0.5, y as f64 + 0.5, z as f64 + 0.5)
+                        / resolution as f64;
+                    let mut value = fractal_noise(frequency * p, octaves, seed);
+                    value = ((value - threshold) / (1.0 - threshold)).clamp(0.0, 1.0);
+                    if ball {
+                        let r = 2.0 * (p - Point::new(0.5, 0.5, 0.5)).length();
+                        value *= smoothstep(1.0 - r.min(1.0));
+                    }
+                    values.push(value);
+                }
+            }
+        }
+        return DensityGrid::new(resolution, resolution, resolution, values);
+    }
+
+    pub fn max(&self) -> f64 {
+        return self.max;
+    }
+
+    // p in [0, 1] on each axis, clamped at the faces
+    pub fn lookup(&self, p: Point) -> f64 {
+        let cell = |v: f64, n: usize| {
+            let x = (v * n as f64 - 0.5).clamp(0.0, (n - 1) as f64);
+            let i = (x.floor() as usize).min(n.saturating_sub(2));
+            return (i, x - i as f64);
+        };
+        let (x, fx) = cell(p.x, self.nx);
+        let (y, fy) = cell(p.y, self.ny);
+        let (z, fz) = cell(p.z, self.nz);
+        let get = |dx: usize, dy: usize, dz: usize| {
+            let (i, j, k) = (
+                (x + dx).min(self.nx - 1),
+                (y + dy).min(self.ny - 1),
+                (z + dz).min(self.nz - 1),
+            );
+            return self.values[(k * self.ny + j) * self.nx + i];
+        };
+        let lerp = |a: f64, b: f64, t: f64| a + (b - a) * t;
+        let face = |dz: usize| {
+            let bottom = lerp(get(0, 0, dz), get(1, 0, dz), fx);
+            let top = lerp(get(0, 1, dz), get(1, 1, dz), fx);
+            return lerp(bottom, top, fy);
+        };
+        return lerp(face(0), face(1), fz);
+    }
+}
+
+fn smoothstep(x: f64) -> f64 {
+    let x = x.clamp(0.0, 1.0);
+    return x * x * (3.0 - 2.0 * x);
+}
+
+// pseudo random value in [0, 1] for a lattice point
+fn lattice(x: i64, y: i64, z: i64, seed: u32) -> f64 {
+    let mut h = (x as u64).wrapping_mul(0x9e3779b97f4a7c15)
+        ^ (y as u64).wrapping_mul(0xc2b2ae3d27d4eb4f)
+        ^ (z as u64).wrapping_mul(0x165667b19e3779f9)
+        ^ (seed as u64).wrapping_mul(0x27d4eb2f165667c5);
+    h ^= h >> 33;
+    h = h.wrapping_mul(0xff51afd7ed558ccd);
+    h ^= h >> 33;
+    return (h >> 11) as f64 / (1u64 << 53) as f64;
+}
+
+fn value_noise(p: Point, seed: u32) -> f64 {
+    let (x0, y0, z0) = (p.x.floor(), p.y.floor(), p.z.floor());
+    let (fx, fy, fz) = (
+        smoothstep(p.x - x0),
+        smoothstep(p.y - y0),
+        smoothstep(p.z - z0),
+    );
+    let (x, y, z) = (x0 as i64, y0 as i64, z0 as i64);
+    let lerp = |a: f64, b: f64, t: f64| a + (b - a) * t;
+    let face = |dz: i64| {
+        let bottom = lerp(
+            lattice(x, y, z + dz, seed),
+            lattice(x + 1, y, z + dz, seed),
+            fx,
+        );
+        let top = lerp(
+            lattice(x, y + 1, z + dz, seed),
+            lattice(x + 1, y + 1, z + dz, seed),
+            fx,
+        );
+        return lerp(bottom, top, fy);
+    };
+    return lerp(face(0), face(1), fz);
+}
+
+// octaves of value noise at doubling frequencies and halving amplitudes, in [0, 1]
+fn fractal_noise(p: Point, octaves: u32, seed: u32) -> f64 {
+    let mut sum = 0.0;
+    let mut total = 0.0;
+    let mut amplitude = 1.0;
+    let mut p = p;
+    for octave in 0..octaves.max(1) {
+        sum += amplitude * value_noise(p, seed.wrapping_add(octave));
+        total += amplitude;
+        amplitude *= 0.5;
+        p = 2.0 * p + Vec3::new(17.0, 31.0, 47.0);
+    }
+    return sum / total;
+}
+
+// Reader for the single channel .vol grids of Mitsuba: "VOL" and version 3, the
+// encoding (1 for float32, 3 for uint8), the resolution, the channel count and the
+// bounding box, followed by the values with x running fastest. Returns the grid and
+// its box.
+pub fn read_vol(path: &str) -> Result<(DensityGrid, Aabb), String> {
+    let data = std::fs::read(path).map_err(|e| format!("{}: {}", path, e))?;
+    return parse_vol(&data).map_err(|e| format!("{}: {}", path, e));
+}
+
+pub fn parse_vol(data: &[u8]) -> Result<(DensityGrid, Aabb), String> {
+    if data.len() < 48 || &data[0..3] != b"VOL" || data[3] != 3 {
+        return Err(String::from("not a version 3 vol file"));
+    }
+    let word = |i: usize| [data[i], data[i + 1], data[i + 2], data[i + 3]];
+    let int = |i: usize| i32::from_le_bytes(word(i));
+    let float = |i: usize| f32::from_le_bytes(word(i)) as f64;
+
+    let encoding = int(4);
+    let (nx, ny, nz) = (int(8), int(12), int(16));
+    if nx <= 0 || ny <= 0 || nz <= 0 {
+        return Err(String::from("invalid resolution"));
+    }
+    if int(20) != 1 {
+        return Err(format!(
+            "{} channels, only density grids are supported",
+            int(20)
+        ));
+    }
+    let bounds = Aabb::new(
+        Point::new(float(24), float(28), float(32)),
+        Point::new(float(36), float(40), float(44)),
+    );
+
+    let count = nx as usize * ny as usize * nz as usize;
+    let body = &data[48..];
+    let values = match encoding {
+        1 if body.len() >= 4 * count => (0..count).map(|i| float(48 + 4 * i)).collect(),
+        3 if body.len() >= count => body[..count].iter().map(|v| *v as f64 / 255.0).collect(),
+        1 | 3 => return Err(String::from("unexpected end of data")),
+        other => return Err(format!("unsupported encoding {}", other)),
+    };
+    return Ok((
+        DensityGrid::new(nx as usize, ny as usize, nz as usize, values),
+        bounds,
+    ));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPSILON: f64 = 0.000001;
+
+    #[test]
+    fn test_lookup() {
+        // 2x1x1 grid from 0 to 1 along x
+        let grid = DensityGrid::new(2, 1, 1, vec![0.0, 1.0]);
+        assert!((grid.lookup(Point::new(0.5, 0.5, 0.5)) - 0.5).abs() <= EPSILON);
+        assert!((grid.lookup(Point::new(0.375, 0.1, 0.9)) - 0.25).abs() <= EPSILON);
+        // constant beyond the outer voxel centers
+        assert!((grid.lookup(Point::new(0.1, 0.5, 0.5))).abs() <= EPSILON);
+        assert!((grid.lookup(Point::new(1.0, 0.5, 0.5)) - 1.0).abs() <= EPSILON);
+        assert!((grid.max() - 1.0).abs() <= EPSILON);
+
+        let constant = DensityGrid::constant(0.7);
+        assert!((constant.lookup(Point::new(0.2, 0.9, 0.4)) - 0.7).abs() <= EPSILON);
+    }
+
+    #[test]
+    fn test_noise() {
+        let cloud = DensityGrid::noise(16, 4.0, 4, 0.3, true, 7);
+        assert!(cloud.max() > 0.0 && cloud.max() <= 1.0);
+        // the ball fades out towards the corners
+        assert!(cloud.lookup(Point::new(0.0, 0.0, 0.0)).abs() <= EPSILON);
+        let again = DensityGrid::noise(16, 4.0, 4, 0.3, true, 7);
+        assert_eq!(cloud.values, again.values);
+    }
+
+    #[test]
+    fn test_parse_vol() {
+        let mut data = b"VOL\x03".to_vec();
+        for v in [1i32, 2, 1, 1, 1] {
+            data.extend_from_slice(&v.to_le_bytes());
+        }
+        for v in [0.0f32, 0.0, 0.0, 2.0, 1.0, 1.0, 0.25, 0.75] {
+            data.extend_from_slice(&v.to_le_bytes());
+        }
+        let (grid, bounds) = parse_vol(&data).unwrap();
+        assert!((bounds.max.x - 2.0).abs() <= EPSILON);
+        assert!((grid.lookup(Point::new(0.5, 0.5, 0.5)) - 0.5).abs() <= EPSILON);
+
+        data.truncate(data.len() - 1);
+        assert!(parse_vol(&data).is_err());
+        assert!(parse_vol(b"VOL\x02").is_err());
+    }
+}
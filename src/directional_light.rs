@@ -0,0 +1,38 @@
+use crate::{
+    light::{Light, LightSample},
+    vec3::{Color, Point, Vec3},
+};
+
+// Infinitely distant light arriving from a single direction, irradiance in W/m^2.
+pub struct DirectionalLight {
+    dir: Vec3,
+    irradiance: Color,
+}
+
+impl DirectionalLight {
+    // dir points from the scene towards the light
+    pub fn new(dir: Vec3, irradiance: Color) -> DirectionalLight {
+        return DirectionalLight {
+            dir: dir.unit_vector(),
+            irradiance,
+        };
+    }
+
+    // A direction new() can normalise.
+    pub fn check(dir: Vec3) -> Result<(), String> {
+        if dir.length_double() == 0.0 {
+            return Err(String::from("directional light direction is zero"));
+        }
+        return Ok(());
+    }
+}
+
+impl Light for DirectionalLight {
+    fn sample_li(&self, _: Point) -> Option<LightSample> {
+        return Some(LightSample {
+            dir: self.dir,
+            distance: f64::INFINITY,
+            li: self.irradiance,
+        });
+    }
+}
@@ -0,0 +1,128 @@
+// Piecewise-constant distributions used for importance sampling tabulated functions.
+pub struct Distribution1D {
+    func: Vec<f64>,
+    cdf: Vec<f64>,
+    integral: f64,
+}
+
+impl Distribution1D {
+    pub fn new(func: Vec<f64>) -> Distribution1D {
+        let n = func.len();
+        let mut cdf = vec![0.0; n + 1];
+        for i in 0..n {
+            cdf[i + 1] = cdf[i] + func[i].abs() / n as f64;
+        }
+        let integral = cdf[n];
+        for (i, c) in cdf.iter_mut().enumerate() {
+            // fall back to uniform when the function is zero everywhere
+            *c = if integral > 0.0 {
+                *c / integral
+            } else {
+                i as f64 / n as f64
+            };
+        }
+        return Distribution1D {
+            func,
+            cdf,
+            integral,
+        };
+    }
+
+    pub fn count(&self) -> usize {
+        return self.func.len();
+    }
+
+    pub fn integral(&self) -> f64 {
+        return self.integral;
+    }
+
+    // Returns the sampled position in [0, 1), its density and the bucket index.
+    pub fn sample_continuous(&self, u: f64) -> (f64, f64, usize) {
+        let n = self.count();
+        // last index whose cdf is <= u
+        let index = self.cdf.partition_point(|&c| c <= u).clamp(1, n) - 1;
+        let mut du = u - self.cdf[index];
+        let width = self.cdf[index + 1] - self.cdf[index];
+        if width > 0.0 {
+            du /= width;
+        }
+        let pdf = self.pdf(index);
+        return ((index as f64 + du) / n as f64, pdf, index);
+    }
+
+    pub fn pdf(&self, index: usize) -> f64 {
+        if self.integral > 0.0 {
+            self.func[index].abs() / self.integral
+        } else {
+            1.0
+        }
+    }
+}
+
+pub struct Distribution2D {
+    conditional: Vec<Distribution1D>,
+    marginal: Distribution1D,
+}
+
+impl Distribution2D {
+    // func is laid out row by row, rows are indexed by v
+    pub fn new(func: &[f64], width: usize, height: usize) -> Distribution2D {
+        let conditional: Vec<Distribution1D> = (0..height)
+            .map(|v| Distribution1D::new(func[v * width..(v + 1) * width].to_vec()))
+            .collect();
+        let marginal = Distribution1D::new(conditional.iter().map(|d| d.integral()).collect());
+        return Distribution2D {
+            conditional,
+            marginal,
+        };
+    }
+
+    pub fn sample_continuous(&self, u0: f64, u1: f64) -> ((f64, f64), f64) {
+        let (v, pdf_v, row) = self.marginal.sample_continuous(u1);
+        let (u, pdf_u, _) = self.conditional[row].sample_continuous(u0);
+        return ((u, v), pdf_u * pdf_v);
+    }
+
+    pub fn pdf(&self, u: f64, v: f64) -> f64 {
+        let row = ((v * self.marginal.count() as f64) as usize).min(self.marginal.count() - 1);
+        let conditional = &self.conditional[row];
+        let column = ((u * conditional.count() as f64) as usize).min(conditional.count() - 1);
+        if self.marginal.integral() <= 0.0 {
+            return 1.0;
+        }
+        return conditional.func[column].abs() / self.marginal.integral();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPSILON: f64 = 0.000001;
+
+    #[test]
+    fn test_sample_1d() {
+        let d = Distribution1D::new(vec![1.0, 3.0]);
+
+        let (x, pdf, index) = d.sample_continuous(0.1);
+        assert_eq!(index, 0);
+        assert!((x - 0.2).abs() <= EPSILON);
+        assert!((pdf - 0.5).abs() <= EPSILON);
+
+        let (x, pdf, index) = d.sample_continuous(0.625);
+        assert_eq!(index, 1);
+        assert!((x - 0.75).abs() <= EPSILON);
+        assert!((pdf - 1.5).abs() <= EPSILON);
+    }
+
+    #[test]
+    fn test_pdf_2d_matches_sample() {
+        let func = [0.0, 1.0, 2.0, 5.0, 0.5, 1.5];
+        let d = Distribution2D::new(&func, 3, 2);
+
+        for &(u0, u1) in &[(0.1, 0.2), (0.7, 0.9), (0.5, 0.5)] {
+            let ((u, v), pdf) = d.sample_continuous(u0, u1);
+            assert!((d.pdf(u, v) - pdf).abs() <= EPSILON);
+        }
+    }
+}
@@ -0,0 +1,56 @@
+use crate::vec3::{Color, Vec3};
+
+pub struct EnvironmentSample {
+    pub dir: Vec3,
+    pub radiance: Color,
+    pub pdf: f64,
+}
+
+// Radiance arriving from infinitely far away, seen by rays that escape the scene.
+pub trait Environment {
+    fn emitted(&self, dir: Vec3) -> Color;
+
+    // Environments that can be importance sampled return a direction with its solid angle pdf.
+    fn sample(&self) -> Option<EnvironmentSample> {
+        return None;
+    }
+
+    fn pdf(&self, _dir: Vec3) -> f64 {
+        return 0.0;
+    }
+}
+
+pub struct GradientEnvironment {
+    bottom: Color,
+    top: Color,
+}
+
+impl GradientEnvironment {
+    pub fn new(bottom: Color, top: Color) -> GradientEnvironment {
+        return GradientEnvironment { bottom, top };
+    }
+}
+
+impl Environment for GradientEnvironment {
+    fn emitted(&self, dir: Vec3) -> Color {
+        let unit_dir = dir.unit_vector();
+        let t = 0.5 * (unit_dir.y + 1.0);
+        return (1.0 - t) * self.bottom + t * self.top;
+    }
+}
+
+pub struct ConstantEnvironment {
+    color: Color,
+}
+
+impl ConstantEnvironment {
+    pub fn new(color: Color) -> ConstantEnvironment {
+        return ConstantEnvironment { color };
+    }
+}
+
+impl Environment for ConstantEnvironment {
+    fn emitted(&self, _: Vec3) -> Color {
+        return self.color;
+    }
+}
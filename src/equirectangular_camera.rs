@@ -0,0 +1,66 @@
+use std::f64::consts::PI;
+
+use crate::{
+    camera::{look_at_basis, Camera},
+    ray::Ray,
+    vec3::{Point, Vec3},
+};
+
+// Full 360 x 180 degree panorama, meant for a 2:1 image.
+// The image center looks at lookat, the left and right edges look backwards.
+pub struct EquirectangularCamera {
+    origin: Point,
+    u: Vec3,
+    v: Vec3,
+    w: Vec3,
+}
+
+impl EquirectangularCamera {
+    pub fn new(lookfrom: Point, lookat: Point, vup: Vec3) -> EquirectangularCamera {
+        let (u, v, w) = look_at_basis(lookfrom, lookat, vup);
+        return EquirectangularCamera {
+            origin: lookfrom,
+            u,
+            v,
+            w,
+        };
+    }
+}
+
+impl Camera for EquirectangularCamera {
+    fn get_ray(&self, s: f64, t: f64) -> Ray {
+        let phi = (s - 0.5) * 2.0 * PI;
+        let elevation = (t - 0.5) * PI;
+        let dir = elevation.cos() * phi.sin() * self.u + elevation.sin() * self.v
+            - elevation.cos() * phi.cos() * self.w;
+        return Ray::new(self.origin, dir);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPSILON: f64 = 0.000001;
+
+    #[test]
+    fn test_directions() {
+        let camera = EquirectangularCamera::new(
+            Point::new(0.0, 0.0, 0.0),
+            Point::new(0.0, 0.0, -1.0),
+            Vec3::new(0.0, 1.0, 0.0),
+        );
+
+        let forward = camera.get_ray(0.5, 0.5).dir;
+        assert!((forward.z + 1.0).abs() <= EPSILON);
+
+        let right = camera.get_ray(0.75, 0.5).dir;
+        assert!((right.x - 1.0).abs() <= EPSILON);
+
+        let back = camera.get_ray(0.0, 0.5).dir;
+        assert!((back.z - 1.0).abs() <= EPSILON);
+
+        let up = camera.get_ray(0.3, 1.0).dir;
+        assert!((up.y - 1.0).abs() <= EPSILON);
+    }
+}
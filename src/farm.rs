@@ -0,0 +1,879 @@
+use std::{
+    collections::VecDeque,
+    io::{BufReader, BufWriter, Read, Write},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpListener, TcpStream},
+    path::Path,
+    process::{Child, Command, Stdio},
+    sync::{Arc, Condvar, Mutex},
+    thread,
+    time::Duration,
+};
+
+use crate::{
+    camera::Camera,
+    film::{Film, PixelSums},
+    options::Options,
+    renderer::{render_tile, Tile},
+    scene_file::SceneDescription,
+    vec3::Color,
+};
+
+// Rendering spread over worker processes on this machine or others. The coordinator
+// sends every worker the scene and its command line, hands out tiles one at a time as
+// workers become free and adds the sums they send back into its film. Files the scene
+// refers to are read by the workers from the directory of the coordinator, so workers
+// on other machines need to see the same files under the same path.
+//
+// Anything that connects is sent the job, with the working directory, command line and
+// scene of the coordinator, so the coordinator should only listen where the workers
+// are trusted.
+
+// What a worker needs to load the scene as the coordinator did.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Job {
+    // working directory of the coordinator
+    pub directory: String,
+    pub args: Vec<String>,
+    // text of the scene file
+    pub scene: String,
+    pub base_dir: String,
+    // for the procedural geometry of the scene
+    pub seed: u64,
+}
+
+impl Job {
+    pub fn new(
+        args: Vec<String>,
+        scene: String,
+        base_dir: &Path,
+        seed: u64,
+    ) -> Result<Job, String> {
+        let directory = std::env::current_dir().map_err(|e| e.to_string())?;
+        return Ok(Job {
+            directory: directory.to_string_lossy().into_owned(),
+            args,
+            scene,
+            base_dir: base_dir.to_string_lossy().into_owned(),
+            seed,
+        });
+    }
+}
+
+#[derive(PartialEq, Debug)]
+enum Message {
+    Job(Job),
+    // the worker loaded the job
+    Ready,
+    // a worker that can't render the job
+    Error(String),
+    Tile {
+        frame: Option<u32>,
+        height: u32,
+        tile: Tile,
+    },
+    // the pixels a tile added to, by index
+    Sums(Vec<(u32, PixelSums)>),
+    Done,
+}
+
+struct Encoder {
+    bytes: Vec<u8>,
+}
+
+impl Encoder {
+    fn u8(&mut self, value: u8) {
+        self.bytes.push(value);
+    }
+
+    fn u32(&mut self, value: u32) {
+        self.bytes.extend(value.to_le_bytes());
+    }
+
+    fn u64(&mut self, value: u64) {
+        self.bytes.extend(value.to_le_bytes());
+    }
+
+    fn f64(&mut self, value: f64) {
+        self.bytes.extend(value.to_le_bytes());
+    }
+
+    fn color(&mut self, color: Color) {
+        self.f64(color.x);
+        self.f64(color.y);
+        self.f64(color.z);
+    }
+
+    fn string(&mut self, value: &str) {
+        self.u32(value.len() as u32);
+        self.bytes.extend(value.as_bytes());
+    }
+}
+
+struct Decoder<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Decoder<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], String> {
+        if self.position + n > self.bytes.len() {
+            return Err(String::from("message ends early"));
+        }
+        self.position += n;
+        return Ok(&self.bytes[self.position - n..self.position]);
+    }
+
+    fn u8(&mut self) -> Result<u8, String> {
+        return Ok(self.take(1)?[0]);
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        return Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()));
+    }
+
+    fn u64(&mut self) -> Result<u64, String> {
+        return Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()));
+    }
+
+    fn f64(&mut self) -> Result<f64, String> {
+        return Ok(f64::from_le_bytes(self.take(8)?.try_into().unwrap()));
+    }
+
+    fn color(&mut self) -> Result<Color, String> {
+        return Ok(Color::new(self.f64()?, self.f64()?, self.f64()?));
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        let length = self.u32()? as usize;
+        let bytes = self.take(length)?.to_vec();
+        return String::from_utf8(bytes).map_err(|e| e.to_string());
+    }
+}
+
+// Messages are a tag byte and their fields, little endian.
+impl Message {
+    fn encode(&self) -> Vec<u8> {
+        let mut e = Encoder { bytes: vec![] };
+        match self {
+            Message::Job(job) => {
+                e.u8(0);
+                e.string(&job.directory);
+                e.u32(job.args.len() as u32);
+                for arg in &job.args {
+                    e.string(arg);
+                }
+                e.string(&job.scene);
+                e.string(&job.base_dir);
+                e.u64(job.seed);
+            }
+            Message::Ready => e.u8(1),
+            Message::Error(error) => {
+                e.u8(2);
+                e.string(error);
+            }
+            Message::Tile {
+                frame,
+                height,
+                tile,
+            } => {
+                e.u8(3);
+                match frame {
+                    Some(frame) => {
+                        e.u8(1);
+                        e.u32(*frame);
+                    }
+                    None => e.u8(0),
+                }
+                e.u32(*height);
+                for value in [tile.x0, tile.y0, tile.x1, tile.y1] {
+                    e.u32(value);
+                }
+            }
+            Message::Sums(sums) => {
+                e.u8(4);
+                e.u32(sums.len() as u32);
+                for (index, sums) in sums {
+                    e.u32(*index);
+                    e.color(sums.color);
+                    e.f64(sums.weight);
+                    e.color(sums.splat);
+                }
+            }
+            Message::Done => e.u8(5),
+        }
+        return e.bytes;
+    }
+
+    fn decode(bytes: &[u8]) -> Result<Message, String> {
+        let mut d = Decoder { bytes, position: 0 };
+        let message = match d.u8()? {
+            0 => {
+                let directory = d.string()?;
+                let count = d.u32()?;
+                let args = (0..count)
+                    .map(|_| d.string())
+                    .collect::<Result<Vec<String>, String>>()?;
+                Message::Job(Job {
+                    directory,
+                    args,
+                    scene: d.string()?,
+                    base_dir: d.string()?,
+                    seed: d.u64()?,
+                })
+            }
+            1 => Message::Ready,
+            2 => Message::Error(d.string()?),
+            3 => {
+                let frame = match d.u8()? {
+                    0 => None,
+                    _ => Some(d.u32()?),
+                };
+                Message::Tile {
+                    frame,
+                    height: d.u32()?,
+                    tile: Tile {
+                        x0: d.u32()?,
+                        y0: d.u32()?,
+                        x1: d.u32()?,
+                        y1: d.u32()?,
+                    },
+                }
+            }
+            4 => {
+                let count = d.u32()?;
+                let mut sums = vec![];
+                for _ in 0..count {
+                    let index = d.u32()?;
+                    let pixel = PixelSums {
+                        color: d.color()?,
+                        weight: d.f64()?,
+                        splat: d.color()?,
+                    };
+                    sums.push((index, pixel));
+                }
+                Message::Sums(sums)
+            }
+            5 => Message::Done,
+            tag => return Err(format!("unknown message {}", tag)),
+        };
+        if d.position != bytes.len() {
+            return Err(String::from("message too long"));
+        }
+        return Ok(message);
+    }
+}
+
+// A message goes over the connection after its length.
+fn send(stream: &mut impl Write, message: &Message) -> Result<(), String> {
+    let bytes = message.encode();
+    stream
+        .write_all(&(bytes.len() as u32).to_le_bytes())
+        .and_then(|_| stream.write_all(&bytes))
+        .and_then(|_| stream.flush())
+        .map_err(|e| e.to_string())?;
+    return Ok(());
+}
+
+// enough for the sums of a tile of a few million pixels
+const MAX_MESSAGE: u32 = 1 << 28;
+
+fn receive(stream: &mut impl Read) -> Result<Message, String> {
+    let mut length = [0; 4];
+    stream.read_exact(&mut length).map_err(|e| e.to_string())?;
+    let length = u32::from_le_bytes(length);
+    if length > MAX_MESSAGE {
+        return Err(format!("message of {} bytes is too long", length));
+    }
+    let mut bytes = vec![0; length as usize];
+    stream.read_exact(&mut bytes).map_err(|e| e.to_string())?;
+    return Message::decode(&bytes);
+}
+
+// a tile that failed on this many workers fails the render
+const MAX_FAILURES: u32 = 3;
+
+// how often a render waiting for tiles checks on the worker processes
+const POLL: Duration = Duration::from_millis(100);
+
+// A tile still to render, with the image height and the workers it failed on.
+struct Task {
+    frame: Option<u32>,
+    height: u32,
+    tile: Tile,
+    failures: u32,
+}
+
+// Shared by the coordinator and the threads talking to the workers.
+struct Queue {
+    tiles: VecDeque<Task>,
+    sums: Vec<Vec<(u32, PixelSums)>>,
+    error: Option<String>,
+    // workers that loaded the job and haven't gone away
+    alive: usize,
+    finished: bool,
+}
+
+type Shared = Arc<(Mutex<Queue>, Condvar)>;
+
+pub struct Farm {
+    // where workers on this machine connect to
+    address: SocketAddr,
+    shared: Shared,
+    // watched while rendering, since one that dies before connecting is never heard from
+    workers: Mutex<Vec<Child>>,
+}
+
+impl Farm {
+    // Listens for workers on address and starts the given number of worker processes
+    // on this machine. Port 0 picks a free port.
+    pub fn start(address: &str, workers: usize, job: Job) -> Result<Farm, String> {
+        let executable = std::env::current_exe().map_err(|e| e.to_string())?;
+        return Farm::start_with(address, workers, job, |address| {
+            let mut command = Command::new(&executable);
+            command.arg("--worker").arg(address.to_string());
+            return command;
+        });
+    }
+
+    // As start, with worker processes run by the command for the address.
+    fn start_with(
+        address: &str,
+        workers: usize,
+        job: Job,
+        worker: impl Fn(&SocketAddr) -> Command,
+    ) -> Result<Farm, String> {
+        let listener = TcpListener::bind(address).map_err(|e| format!("{}: {}", address, e))?;
+        let mut address = listener.local_addr().map_err(|e| e.to_string())?;
+        if address.ip().is_unspecified() {
+            address.set_ip(match address.ip() {
+                IpAddr::V4(_) => IpAddr::V4(Ipv4Addr::LOCALHOST),
+                IpAddr::V6(_) => IpAddr::V6(Ipv6Addr::LOCALHOST),
+            });
+        }
+        let shared = Arc::new((
+            Mutex::new(Queue {
+                tiles: VecDeque::new(),
+                sums: vec![],
+                error: None,
+                alive: 0,
+                finished: false,
+            }),
+            Condvar::new(),
+        ));
+
+        let accepting = shared.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                if accepting.0.lock().unwrap().finished {
+                    return;
+                }
+                if let Ok(stream) = stream {
+                    let shared = accepting.clone();
+                    let job = job.clone();
+                    thread::spawn(move || serve(stream, &job, &shared));
+                }
+            }
+        });
+
+        let mut children = vec![];
+        for _ in 0..workers {
+            // errors come back over the connection, progress isn't wanted
+            let child = worker(&address)
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .spawn()
+                .map_err(|e| e.to_string())?;
+            children.push(child);
+        }
+        return Ok(Farm {
+            address,
+            shared,
+            workers: Mutex::new(children),
+        });
+    }
+
+    pub fn address(&self) -> SocketAddr {
+        return self.address;
+    }
+
+    // Renders a frame of the animation, or the still image for None, on the workers.
+    pub fn render(
+        &self,
+        frame: Option<u32>,
+        options: &Options,
+        height: u32,
+    ) -> Result<Film, String> {
+        let width = options.width;
+        let mut film = Film::new(width, height, options.make_filter()?);
+        film.set_splat_scale(1.0 / f64::from(options.samples_per_pixel));
+        let tiles = Tile::split(width, height, options.tile_size);
+        let (queue, condvar) = &*self.shared;
+        let tasks = tiles.iter().map(|tile| Task {
+            frame,
+            height,
+            tile: *tile,
+            failures: 0,
+        });
+        queue.lock().unwrap().tiles.extend(tasks);
+        condvar.notify_all();
+
+        let mut remaining = tiles.len();
+        while remaining > 0 {
+            let finished = {
+                let mut queue = queue.lock().unwrap();
+                while queue.sums.is_empty() && queue.error.is_none() {
+                    queue = condvar.wait_timeout(queue, POLL).unwrap().0;
+                    if queue.alive == 0 && self.workers_exited() {
+                        queue.error = Some(String::from("no workers left"));
+                    }
+                }
+                if let Some(error) = queue.error.take() {
+                    return Err(error);
+                }
+                std::mem::take(&mut queue.sums)
+            };
+            for sums in finished {
+                for (index, sums) in sums {
+                    if index >= width * height {
+                        return Err(format!("worker sent pixel {} outside the image", index));
+                    }
+                    film.add_sums(index % width, index / width, &sums);
+                }
+                remaining -= 1;
+            }
+            eprint!("\rremain {:3} tiles", remaining);
+        }
+        return Ok(film);
+    }
+
+    // Whether the processes started here have all exited. Without any there may still
+    // be workers on other machines to come.
+    fn workers_exited(&self) -> bool {
+        let mut workers = self.workers.lock().unwrap();
+        return !workers.is_empty()
+            && workers
+                .iter_mut()
+                .all(|worker| matches!(worker.try_wait(), Ok(Some(_))));
+    }
+}
+
+impl Drop for Farm {
+    fn drop(&mut self) {
+        let (queue, condvar) = &*self.shared;
+        queue.lock().unwrap().finished = true;
+        condvar.notify_all();
+        // wakes the thread waiting for connections to see it
+        let _ = TcpStream::connect(self.address);
+        for worker in self.workers.get_mut().unwrap() {
+            let _ = worker.wait();
+        }
+    }
+}
+
+// Talks to one worker: sends the job, then tiles for as long as there are any.
+fn serve(stream: TcpStream, job: &Job, shared: &Shared) {
+    let (queue, condvar) = &**shared;
+    let mut reader = match stream.try_clone() {
+        Ok(stream) => BufReader::new(stream),
+        Err(_) => return,
+    };
+    let mut writer = BufWriter::new(stream);
+    let ready = send(&mut writer, &Message::Job(job.clone())).and_then(|_| receive(&mut reader));
+    match ready {
+        Ok(Message::Ready) => queue.lock().unwrap().alive += 1,
+        Ok(Message::Error(error)) => {
+            queue.lock().unwrap().error = Some(error);
+            condvar.notify_all();
+            return;
+        }
+        _ => return,
+    }
+
+    loop {
+        let task = {
+            let mut queue = queue.lock().unwrap();
+            loop {
+                if queue.finished {
+                    break None;
+                }
+                if let Some(task) = queue.tiles.pop_front() {
+                    break Some(task);
+                }
+                queue = condvar.wait(queue).unwrap();
+            }
+        };
+        let mut task = match task {
+            Some(task) => task,
+            None => {
+                let _ = send(&mut writer, &Message::Done);
+                return;
+            }
+        };
+
+        let message = Message::Tile {
+            frame: task.frame,
+            height: task.height,
+            tile: task.tile,
+        };
+        let reply = send(&mut writer, &message).and_then(|_| receive(&mut reader));
+        let mut queue = queue.lock().unwrap();
+        match reply {
+            Ok(Message::Sums(sums)) => queue.sums.push(sums),
+            // a worker that went away leaves its tile to the others, unless the tile
+            // keeps failing or there are no others
+            _ => {
+                queue.alive -= 1;
+                task.failures += 1;
+                let tile = task.tile;
+                if task.failures >= MAX_FAILURES {
+                    queue.error = Some(format!(
+                        "tile at {},{} failed on {} workers",
+                        tile.x0, tile.y0, task.failures
+                    ));
+                } else if queue.alive == 0 {
+                    queue.error = Some(String::from("no workers left"));
+                } else {
+                    queue.tiles.push_front(task);
+                }
+                condvar.notify_all();
+                return;
+            }
+        }
+        condvar.notify_all();
+    }
+}
+
+fn load_job(job: &Job) -> Result<(Options, SceneDescription), String> {
+    // relative paths on the command line and in the scene are the coordinator's
+    let directory = Path::new(&job.directory);
+    if std::env::current_dir().ok().as_deref() != Some(directory) {
+        std::env::set_current_dir(directory).map_err(|e| format!("{}: {}", job.directory, e))?;
+    }
+    let options = Options::parse(job.args.iter().cloned())?;
+    let description = options.make_scene(&job.scene, Path::new(&job.base_dir), job.seed)?;
+    return Ok((options, description));
+}
+
+// Renders tiles for the coordinator at address until it has no more.
+pub fn work(address: &str) -> Result<(), String> {
+    let stream = TcpStream::connect(address).map_err(|e| format!("{}: {}", address, e))?;
+    let mut reader = BufReader::new(stream.try_clone().map_err(|e| e.to_string())?);
+    let mut writer = BufWriter::new(stream);
+    let job = match receive(&mut reader)? {
+        Message::Job(job) => job,
+        _ => return Err(String::from("expected a job from the coordinator")),
+    };
+    let (options, description) = match load_job(&job) {
+        Ok(loaded) => loaded,
+        Err(error) => {
+            send(&mut writer, &Message::Error(error.clone()))?;
+            return Err(error);
+        }
+    };
+    send(&mut writer, &Message::Ready)?;
+
+    loop {
+        let (frame, height, tile) = match receive(&mut reader)? {
+            Message::Tile {
+                frame,
+                height,
+                tile,
+            } => (frame, height, tile),
+            Message::Done => return Ok(()),
+            _ => return Err(String::from("unexpected message from the coordinator")),
+        };
+        let animated;
+        let camera: &dyn Camera = match frame {
+            Some(frame) => {
+                animated = description.camera_at(f64::from(frame))?;
+                &*animated
+            }
+            None => &*description.camera,
+        };
+
+        let film = render_tile(&description.scene, camera, &options, height, &tile).film;
+        let mut sums = vec![];
+        for y in 0..film.height {
+            for x in 0..film.width {
+                let pixel = film.sums(x, y);
+                let empty = Color::default();
+                if pixel.weight != 0.0 || pixel.color != empty || pixel.splat != empty {
+                    sums.push((y * film.width + x, pixel));
+                }
+            }
+        }
+        send(&mut writer, &Message::Sums(sums))?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{renderer::render, scene_file::DEFAULT_SCENE};
+
+    const EPSILON: f64 = 0.000001;
+
+    const SCENE: &str = "camera type=perspective lookfrom=0,0,1 lookat=0,0,0 vup=0,1,0 vfov=40\n";
+
+    fn job(args: &[&str], scene: &str) -> (Job, Options) {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        let options = Options::parse(args.iter().cloned()).unwrap();
+        let job = Job::new(args, String::from(scene), Path::new("."), 7).unwrap();
+        return (job, options);
+    }
+
+    #[test]
+    fn test_message_round_trip() {
+        let (job, _) = job(&["--width", "24"], SCENE);
+        let messages = [
+            Message::Job(job),
+            Message::Ready,
+            Message::Error(String::from("no scene")),
+            Message::Tile {
+                frame: Some(3),
+                height: 16,
+                tile: Tile {
+                    x0: 0,
+                    y0: 8,
+                    x1: 8,
+                    y1: 16,
+                },
+            },
+            Message::Tile {
+                frame: None,
+                height: 16,
+                tile: Tile {
+                    x0: 8,
+                    y0: 0,
+                    x1: 16,
+                    y1: 8,
+                },
+            },
+            Message::Sums(vec![(
+                5,
+                PixelSums {
+                    color: Color::new(0.1, 0.2, 0.3),
+                    weight: 4.0,
+                    splat: Color::new(1.0, 2.0, 3.0),
+                },
+            )]),
+            Message::Done,
+        ];
+        for message in messages {
+            let mut stream = vec![];
+            send(&mut stream, &message).unwrap();
+            assert_eq!(receive(&mut stream.as_slice()).unwrap(), message);
+        }
+        assert!(Message::decode(&[3, 1]).is_err());
+        assert!(Message::decode(&[9]).is_err());
+        // a length past the limit is rejected before reading the message
+        let length = (MAX_MESSAGE + 1).to_le_bytes();
+        assert!(receive(&mut length.as_slice()).is_err());
+    }
+
+    #[test]
+    fn test_local_workers() {
+        // a constant environment alone shows the same in every sample
+        let args = [
+            "--width",
+            "24",
+            "--samples",
+            "4",
+            "--env",
+            "0.2,0.4,0.6",
+            "--tile-size",
+            "5",
+        ];
+        let (job, options) = job(&args, SCENE);
+        let farm = Farm::start("127.0.0.1:0", 0, job).unwrap();
+        let address = farm.address().to_string();
+        let workers: Vec<_> = (0..3)
+            .map(|_| {
+                let address = address.clone();
+                return thread::spawn(move || work(&address));
+            })
+            .collect();
+
+        let film = farm.render(None, &options, 16).unwrap();
+        drop(farm);
+        for worker in workers {
+            assert!(worker.join().unwrap().is_ok());
+        }
+        // every pixel was rendered by exactly one worker
+        for y in 0..16 {
+            for x in 0..24 {
+                assert!((film.sums(x, y).weight - 4.0).abs() <= EPSILON);
+                let color = film.pixel(x, y);
+                assert!((color - Color::new(0.2, 0.4, 0.6)).length() <= EPSILON);
+            }
+        }
+    }
+
+    #[test]
+    fn test_default_scene_seams() {
+        // under a white sky every random sphere shows in its own color
+        let args = [
+            "--width",
+            "48",
+            "--samples",
+            "16",
+            "--depth",
+            "2",
+            "--env",
+            "1,1,1",
+            "--tile-size",
+            "8",
+        ];
+        let (job, options) = job(&args, DEFAULT_SCENE);
+        let description = options
+            .make_scene(DEFAULT_SCENE, Path::new("."), 7)
+            .unwrap();
+        let height = (48.0 / description.aspect_ratio) as u32;
+        let farm = Farm::start("127.0.0.1:0", 0, job).unwrap();
+        let address = farm.address().to_string();
+        let workers: Vec<_> = (0..3)
+            .map(|_| {
+                let address = address.clone();
+                return thread::spawn(move || work(&address));
+            })
+            .collect();
+        let film = farm.render(None, &options, height).unwrap();
+        drop(farm);
+        for worker in workers {
+            assert!(worker.join().unwrap().is_ok());
+        }
+
+        // tiles from workers with spheres of their own would disagree along the edges
+        let local = render(&description.scene, &*description.camera, &options, height).film;
+        let mut different = 0;
+        for y in 0..height {
+            for x in 0..48 {
+                if (film.pixel(x, y) - local.pixel(x, y)).length() > 0.5 {
+                    different += 1;
+                }
+            }
+        }
+        assert!(different * 100 < 48 * height, "{} pixels differ", different);
+    }
+
+    // a worker that goes away with the first tile it gets, or when there are none
+    fn broken_worker(address: &str) {
+        let stream = TcpStream::connect(address).unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut writer = BufWriter::new(stream);
+        assert!(matches!(receive(&mut reader), Ok(Message::Job(_))));
+        send(&mut writer, &Message::Ready).unwrap();
+        let message = receive(&mut reader);
+        assert!(matches!(message, Ok(Message::Tile { .. } | Message::Done)));
+    }
+
+    fn wait_for_workers(farm: &Farm, count: usize) {
+        while farm.shared.0.lock().unwrap().alive < count {
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn test_lost_workers() {
+        let (job, options) = job(&["--width", "8", "--samples", "1"], SCENE);
+
+        // the last worker going away ends the render
+        let farm = Farm::start("127.0.0.1:0", 0, job.clone()).unwrap();
+        let address = farm.address().to_string();
+        let worker = thread::spawn(move || broken_worker(&address));
+        wait_for_workers(&farm, 1);
+        assert_eq!(
+            farm.render(None, &options, 8).err().unwrap(),
+            "no workers left"
+        );
+        worker.join().unwrap();
+
+        // and so does a tile that fails on too many workers
+        let farm = Farm::start("127.0.0.1:0", 0, job).unwrap();
+        let workers: Vec<_> = (0..4)
+            .map(|_| {
+                let address = farm.address().to_string();
+                return thread::spawn(move || broken_worker(&address));
+            })
+            .collect();
+        wait_for_workers(&farm, 4);
+        let error = farm.render(None, &options, 8).err().unwrap();
+        assert_eq!(error, "tile at 0,0 failed on 3 workers");
+        drop(farm);
+        for worker in workers {
+            worker.join().unwrap();
+        }
+    }
+
+    // Runs as a worker in the processes test_worker_processes starts, and does nothing
+    // in the test run itself.
+    #[test]
+    fn worker_process() {
+        if let Ok(address) = std::env::var("FARM_WORKER") {
+            work(&address).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_worker_processes() {
+        let args = [
+            "--width",
+            "24",
+            "--samples",
+            "4",
+            "--env",
+            "0.2,0.4,0.6",
+            "--tile-size",
+            "5",
+        ];
+        let (job, options) = job(&args, SCENE);
+        let executable = std::env::current_exe().unwrap();
+        let farm = Farm::start_with("127.0.0.1:0", 2, job, |address| {
+            let mut command = Command::new(&executable);
+            command
+                .args(["farm::tests::worker_process", "--exact"])
+                .env("FARM_WORKER", address.to_string());
+            return command;
+        })
+        .unwrap();
+        let film = farm.render(None, &options, 16).unwrap();
+        // the next frame goes to the same processes
+        let next = farm.render(Some(1), &options, 16).unwrap();
+        drop(farm);
+        for film in [film, next] {
+            for y in 0..16 {
+                for x in 0..24 {
+                    assert!((film.sums(x, y).weight - 4.0).abs() <= EPSILON);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_exited_workers() {
+        // processes that exit without connecting end the render
+        let (job, options) = job(&["--width", "8"], SCENE);
+        let executable = std::env::current_exe().unwrap();
+        let farm = Farm::start_with("127.0.0.1:0", 2, job, |_| {
+            let mut command = Command::new(&executable);
+            command.args(["farm::tests::no_such_test", "--exact"]);
+            return command;
+        })
+        .unwrap();
+        assert_eq!(
+            farm.render(None, &options, 8).err().unwrap(),
+            "no workers left"
+        );
+    }
+
+    #[test]
+    fn test_worker_error() {
+        let (job, options) = job(&["--width", "8"], "no_such_directive\n");
+        let farm = Farm::start("127.0.0.1:0", 0, job).unwrap();
+        let address = farm.address().to_string();
+        let worker = thread::spawn(move || work(&address));
+        let error = farm.render(None, &options, 8).err().unwrap();
+        assert!(error.contains("no_such_directive"), "{}", error);
+        assert!(worker.join().unwrap().is_err());
+    }
+}
@@ -0,0 +1,174 @@
+use crate::{filter::Filter, image::Image, vec3::Color};
+
+// What the samples and splats landing in a pixel add up to, for merging films rendered
+// apart, like the tiles of a render farm.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct PixelSums {
+    pub color: Color,
+    pub weight: f64,
+    pub splat: Color,
+}
+
+pub struct Film {
+    pub width: u32,
+    pub height: u32,
+    pixels: Vec<Color>,
+    weights: Vec<f64>,
+    // light arriving from paths traced from the lights, added unfiltered
+    splats: Vec<Color>,
+    splat_scale: f64,
+    filter: Box<dyn Filter>,
+}
+
+impl Film {
+    pub fn new(width: u32, height: u32, filter: Box<dyn Filter>) -> Film {
+        let size = (width * height) as usize;
+        return Film {
+            width,
+            height,
+            pixels: vec![Color::default(); size],
+            weights: vec![0.0; size],
+            splats: vec![Color::default(); size],
+            splat_scale: 1.0,
+            filter,
+        };
+    }
+
+    // (x, y) is a position in raster space, y = 0 is the top row.
+    // The sample is splatted into every pixel whose center lies inside the filter radius.
+    pub fn add_sample(&mut self, x: f64, y: f64, color: Color) {
+        let radius = self.filter.radius();
+        let x0 = f64::max((x - 0.5 - radius).ceil(), 0.0) as i64;
+        let x1 = f64::min((x - 0.5 + radius).floor(), (self.width - 1) as f64) as i64;
+        let y0 = f64::max((y - 0.5 - radius).ceil(), 0.0) as i64;
+        let y1 = f64::min((y - 0.5 + radius).floor(), (self.height - 1) as f64) as i64;
+
+        for py in y0..=y1 {
+            for px in x0..=x1 {
+                let weight = self
+                    .filter
+                    .evaluate(px as f64 + 0.5 - x, py as f64 + 0.5 - y);
+                if weight == 0.0 {
+                    continue;
+                }
+                let index = (py as usize) * (self.width as usize) + (px as usize);
+                self.pixels[index] += weight * color;
+                self.weights[index] += weight;
+            }
+        }
+    }
+
+    // Adds to the pixel containing (x, y). Splats are sums over all paths, set_splat_scale
+    // turns them into the average per camera sample.
+    pub fn add_splat(&mut self, x: f64, y: f64, color: Color) {
+        let px = (x.max(0.0) as u32).min(self.width - 1);
+        let py = (y.max(0.0) as u32).min(self.height - 1);
+        self.splats[(py * self.width + px) as usize] += color;
+    }
+
+    pub fn set_splat_scale(&mut self, scale: f64) {
+        self.splat_scale = scale;
+    }
+
+    pub fn sums(&self, x: u32, y: u32) -> PixelSums {
+        let index = (y * self.width + x) as usize;
+        return PixelSums {
+            color: self.pixels[index],
+            weight: self.weights[index],
+            splat: self.splats[index],
+        };
+    }
+
+    pub fn add_sums(&mut self, x: u32, y: u32, sums: &PixelSums) {
+        let index = (y * self.width + x) as usize;
+        self.pixels[index] += sums.color;
+        self.weights[index] += sums.weight;
+        self.splats[index] += sums.splat;
+    }
+
+    pub fn pixel(&self, x: u32, y: u32) -> Color {
+        let index = (y * self.width + x) as usize;
+        let weight = self.weights[index];
+        let mut c = self.splat_scale * self.splats[index];
+        if weight.abs() >= 1e-12 {
+            c += self.pixels[index] / weight;
+        }
+        // negative lobes (Mitchell) can undershoot below zero
+        return Color::new(c.x.max(0.0), c.y.max(0.0), c.z.max(0.0));
+    }
+
+    pub fn to_image(&self) -> Image {
+        let mut image = Image::new(self.width, self.height);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                image.set(x, y, self.pixel(x, y));
+            }
+        }
+        return image;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filter::{BoxFilter, TentFilter};
+
+    const EPSILON: f64 = 0.000001;
+
+    #[test]
+    fn test_box_filter_stays_in_pixel() {
+        let mut film = Film::new(3, 3, Box::new(BoxFilter::new(0.5)));
+        film.add_sample(1.3, 1.7, Color::new(1.0, 2.0, 3.0));
+
+        let center = film.pixel(1, 1);
+        assert!((center.x - 1.0).abs() <= EPSILON);
+        assert!((center.z - 3.0).abs() <= EPSILON);
+        assert!(film.pixel(0, 1).x.abs() <= EPSILON);
+        assert!(film.pixel(1, 2).x.abs() <= EPSILON);
+    }
+
+    #[test]
+    fn test_wide_filter_splats_into_neighbours() {
+        let mut film = Film::new(3, 3, Box::new(TentFilter::new(1.5)));
+        film.add_sample(1.5, 1.5, Color::new(1.0, 1.0, 1.0));
+
+        for y in 0..3 {
+            for x in 0..3 {
+                assert!((film.pixel(x, y).x - 1.0).abs() <= EPSILON);
+            }
+        }
+    }
+
+    #[test]
+    fn test_splats() {
+        let mut film = Film::new(2, 2, Box::new(BoxFilter::new(0.5)));
+        film.add_sample(0.5, 0.5, Color::new(1.0, 1.0, 1.0));
+        film.add_splat(0.7, 0.2, Color::new(2.0, 2.0, 2.0));
+        film.add_splat(1.9, 1.9, Color::new(3.0, 3.0, 3.0));
+        film.set_splat_scale(0.5);
+
+        assert!((film.pixel(0, 0).x - 2.0).abs() <= EPSILON);
+        assert!((film.pixel(1, 1).x - 1.5).abs() <= EPSILON);
+        assert!(film.pixel(1, 0).x.abs() <= EPSILON);
+    }
+
+    #[test]
+    fn test_add_sums() {
+        // two halves of an image with a wide filter add up to the whole
+        let mut whole = Film::new(4, 1, Box::new(TentFilter::new(1.5)));
+        let mut left = Film::new(4, 1, Box::new(TentFilter::new(1.5)));
+        let mut right = Film::new(4, 1, Box::new(TentFilter::new(1.5)));
+        for (x, color) in [(0.5, 1.0), (1.5, 2.0), (2.5, 3.0), (3.5, 4.0)] {
+            whole.add_sample(x, 0.5, Color::new(color, color, color));
+            let half = if x < 2.0 { &mut left } else { &mut right };
+            half.add_sample(x, 0.5, Color::new(color, color, color));
+        }
+        right.add_splat(0.5, 0.5, Color::new(1.0, 1.0, 1.0));
+        whole.add_splat(0.5, 0.5, Color::new(1.0, 1.0, 1.0));
+
+        for x in 0..4 {
+            left.add_sums(x, 0, &right.sums(x, 0));
+            assert!((left.pixel(x, 0) - whole.pixel(x, 0)).length() <= EPSILON);
+        }
+    }
+}
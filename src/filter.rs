@@ -0,0 +1,166 @@
+pub trait Filter {
+    fn radius(&self) -> f64;
+    fn evaluate(&self, x: f64, y: f64) -> f64;
+}
+
+pub struct BoxFilter {
+    radius: f64,
+}
+
+impl BoxFilter {
+    pub fn new(radius: f64) -> BoxFilter {
+        return BoxFilter { radius };
+    }
+}
+
+impl Filter for BoxFilter {
+    fn radius(&self) -> f64 {
+        return self.radius;
+    }
+
+    fn evaluate(&self, x: f64, y: f64) -> f64 {
+        if x.abs() <= self.radius && y.abs() <= self.radius {
+            1.0
+        } else {
+            0.0
+        }
+    }
+}
+
+pub struct TentFilter {
+    radius: f64,
+}
+
+impl TentFilter {
+    pub fn new(radius: f64) -> TentFilter {
+        return TentFilter { radius };
+    }
+
+    fn tent(&self, x: f64) -> f64 {
+        return f64::max(0.0, self.radius - x.abs());
+    }
+}
+
+impl Filter for TentFilter {
+    fn radius(&self) -> f64 {
+        return self.radius;
+    }
+
+    fn evaluate(&self, x: f64, y: f64) -> f64 {
+        return self.tent(x) * self.tent(y);
+    }
+}
+
+pub struct GaussianFilter {
+    radius: f64,
+    alpha: f64,
+    edge: f64,
+}
+
+impl GaussianFilter {
+    pub fn new(radius: f64, alpha: f64) -> GaussianFilter {
+        // subtract the value at the radius so the filter falls to zero at its edge
+        let edge = (-alpha * radius * radius).exp();
+        return GaussianFilter {
+            radius,
+            alpha,
+            edge,
+        };
+    }
+
+    fn gaussian(&self, x: f64) -> f64 {
+        return f64::max(0.0, (-self.alpha * x * x).exp() - self.edge);
+    }
+}
+
+impl Filter for GaussianFilter {
+    fn radius(&self) -> f64 {
+        return self.radius;
+    }
+
+    fn evaluate(&self, x: f64, y: f64) -> f64 {
+        return self.gaussian(x) * self.gaussian(y);
+    }
+}
+
+pub struct MitchellFilter {
+    radius: f64,
+    b: f64,
+    c: f64,
+}
+
+impl MitchellFilter {
+    pub fn new(radius: f64, b: f64, c: f64) -> MitchellFilter {
+        return MitchellFilter { radius, b, c };
+    }
+
+    // Mitchell-Netravali cubic defined over [-2, 2]
+    fn mitchell(&self, x: f64) -> f64 {
+        let x = (2.0 * x / self.radius).abs();
+        let b = self.b;
+        let c = self.c;
+        if x < 1.0 {
+            return ((12.0 - 9.0 * b - 6.0 * c) * x * x * x
+                + (-18.0 + 12.0 * b + 6.0 * c) * x * x
+                + (6.0 - 2.0 * b))
+                / 6.0;
+        } else if x < 2.0 {
+            return ((-b - 6.0 * c) * x * x * x
+                + (6.0 * b + 30.0 * c) * x * x
+                + (-12.0 * b - 48.0 * c) * x
+                + (8.0 * b + 24.0 * c))
+                / 6.0;
+        }
+        return 0.0;
+    }
+}
+
+impl Filter for MitchellFilter {
+    fn radius(&self) -> f64 {
+        return self.radius;
+    }
+
+    fn evaluate(&self, x: f64, y: f64) -> f64 {
+        return self.mitchell(x) * self.mitchell(y);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPSILON: f64 = 0.000001;
+
+    #[test]
+    fn test_box() {
+        let f = BoxFilter::new(0.5);
+        assert!((f.evaluate(0.2, -0.4) - 1.0).abs() <= EPSILON);
+        assert!(f.evaluate(0.6, 0.0).abs() <= EPSILON);
+    }
+
+    #[test]
+    fn test_tent() {
+        let f = TentFilter::new(1.0);
+        assert!((f.evaluate(0.0, 0.0) - 1.0).abs() <= EPSILON);
+        assert!((f.evaluate(0.5, 0.0) - 0.5).abs() <= EPSILON);
+        assert!(f.evaluate(1.0, 0.0).abs() <= EPSILON);
+    }
+
+    #[test]
+    fn test_gaussian_falls_to_zero_at_radius() {
+        let f = GaussianFilter::new(1.5, 2.0);
+        assert!(f.evaluate(1.5, 0.0).abs() <= EPSILON);
+        assert!(f.evaluate(0.0, 0.0) > f.evaluate(0.5, 0.0));
+    }
+
+    #[test]
+    fn test_mitchell() {
+        let f = MitchellFilter::new(2.0, 1.0 / 3.0, 1.0 / 3.0);
+        // center value is (6 - 2B) / 6 per axis
+        let center = (6.0 - 2.0 / 3.0) / 6.0;
+        assert!((f.evaluate(0.0, 0.0) - center * center).abs() <= EPSILON);
+        assert!(f.evaluate(2.0, 0.0).abs() <= EPSILON);
+        // negative lobe
+        assert!(f.evaluate(1.5, 0.0) < 0.0);
+    }
+}
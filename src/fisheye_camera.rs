@@ -0,0 +1,83 @@
+use crate::{
+    camera::{look_at_basis, Camera},
+    math::degrees_to_radians,
+    ray::Ray,
+    vec3::{Point, Vec3},
+};
+
+// Equidistant fisheye: the angle from the view axis grows linearly with the distance
+// from the image center, reaching fov / 2 at the left and right edges.
+pub struct FisheyeCamera {
+    origin: Point,
+    u: Vec3,
+    v: Vec3,
+    w: Vec3,
+    half_fov: f64,
+    aspect_ratio: f64,
+}
+
+impl FisheyeCamera {
+    pub fn new(
+        lookfrom: Point,
+        lookat: Point,
+        vup: Vec3,
+        fov: f64,
+        aspect_ratio: f64,
+    ) -> FisheyeCamera {
+        let (u, v, w) = look_at_basis(lookfrom, lookat, vup);
+        return FisheyeCamera {
+            origin: lookfrom,
+            u,
+            v,
+            w,
+            half_fov: degrees_to_radians(fov) * 0.5,
+            aspect_ratio,
+        };
+    }
+}
+
+impl Camera for FisheyeCamera {
+    fn get_ray(&self, s: f64, t: f64) -> Ray {
+        let x = 2.0 * s - 1.0;
+        let y = (2.0 * t - 1.0) / self.aspect_ratio;
+        let r = (x * x + y * y).sqrt();
+        let theta = f64::min(r * self.half_fov, std::f64::consts::PI);
+        let phi = f64::atan2(y, x);
+        let dir = theta.sin() * phi.cos() * self.u + theta.sin() * phi.sin() * self.v
+            - theta.cos() * self.w;
+        return Ray::new(self.origin, dir);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPSILON: f64 = 0.000001;
+
+    #[test]
+    fn test_get_ray() {
+        let camera = FisheyeCamera::new(
+            Point::new(0.0, 0.0, 0.0),
+            Point::new(0.0, 0.0, -1.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            180.0,
+            2.0,
+        );
+
+        let center = camera.get_ray(0.5, 0.5);
+        assert!((center.org - Point::new(0.0, 0.0, 0.0)).length() <= EPSILON);
+        assert!((center.dir - Vec3::new(0.0, 0.0, -1.0)).length() <= EPSILON);
+
+        // the left and right edges are fov / 2 off the axis
+        let right = camera.get_ray(1.0, 0.5).dir;
+        assert!((right - Vec3::new(1.0, 0.0, 0.0)).length() <= EPSILON);
+        let left = camera.get_ray(0.0, 0.5).dir;
+        assert!((left - Vec3::new(-1.0, 0.0, 0.0)).length() <= EPSILON);
+
+        // the top edge is half as far from the center in a 2:1 image
+        let top = camera.get_ray(0.5, 1.0).dir;
+        let s = 0.5_f64.sqrt();
+        assert!((top - Vec3::new(0.0, s, -s)).length() <= EPSILON);
+    }
+}
@@ -0,0 +1,964 @@
+use std::path::Path;
+
+use crate::{
+    alpha_mask::AlphaMask,
+    dielectric::Dielectric,
+    hittable::Hittable,
+    json::Json,
+    lambertian::Lambertian,
+    material::Material,
+    mesh::Mesh,
+    metal::Metal,
+    texture::SolidColor,
+    vec3::{Color, Point, Vec3},
+};
+
+// Importer for glTF 2.0 scenes in .gltf (with external or embedded buffers) or .glb
+// files. Triangle meshes are placed by the node hierarchy of the default scene and
+// metallic-roughness materials are approximated by our materials. What cannot be
+// represented is skipped and listed in the warnings.
+
+// elements of an accessor without data, far more than the meshes we render
+const MAX_ZEROS: usize = 1 << 24;
+
+const SUPPORTED_EXTENSIONS: [&str; 3] = [
+    "KHR_materials_transmission",
+    "KHR_materials_ior",
+    "KHR_materials_emissive_strength",
+];
+
+pub enum Projection {
+    // vertical field of view in degrees
+    Perspective { vfov: f64 },
+    Orthographic { height: f64 },
+}
+
+pub struct GltfCamera {
+    pub lookfrom: Point,
+    pub lookat: Point,
+    pub vup: Vec3,
+    pub projection: Projection,
+}
+
+pub struct Gltf {
+    // one object per mesh primitive
+    pub objects: Vec<Box<dyn Hittable>>,
+    // index of the material of each object in the file, None for the default material
+    pub object_materials: Vec<Option<usize>>,
+    // in the order the nodes are visited
+    pub cameras: Vec<GltfCamera>,
+    pub warnings: Vec<String>,
+}
+
+pub fn load_gltf(path: &Path) -> Result<Gltf, String> {
+    let name = path.to_string_lossy();
+    let data = std::fs::read(path).map_err(|e| format!("{}: {}", name, e))?;
+    let base_dir = path.parent().unwrap_or(Path::new("."));
+    return parse_gltf(&data, base_dir).map_err(|e| format!("{}: {}", name, e));
+}
+
+pub fn parse_gltf(data: &[u8], base_dir: &Path) -> Result<Gltf, String> {
+    let (doc, binary) = if data.starts_with(b"glTF") {
+        parse_glb(data)?
+    } else {
+        let text = std::str::from_utf8(data).map_err(|e| format!("gltf: {}", e))?;
+        (Json::parse(text)?, None)
+    };
+    let version = doc
+        .get("asset")
+        .and_then(|a| a.get("version"))
+        .and_then(|v| v.as_str());
+    if !version.is_some_and(|v| v.starts_with("2.")) {
+        return Err(format!(
+            "gltf: unsupported version {}",
+            version.unwrap_or("none")
+        ));
+    }
+
+    let mut importer = Importer {
+        doc: &doc,
+        buffers: vec![],
+        gltf: Gltf {
+            objects: vec![],
+            object_materials: vec![],
+            cameras: vec![],
+            warnings: vec![],
+        },
+    };
+    for extension in doc
+        .get("extensionsRequired")
+        .map_or(&[][..], |e| e.elements())
+    {
+        let name = extension.as_str().unwrap_or("");
+        if !SUPPORTED_EXTENSIONS.contains(&name) {
+            return Err(format!(
+                "gltf: required extension {} is not supported",
+                name
+            ));
+        }
+    }
+    for extension in doc.get("extensionsUsed").map_or(&[][..], |e| e.elements()) {
+        let name = extension.as_str().unwrap_or("");
+        if !SUPPORTED_EXTENSIONS.contains(&name) {
+            importer.warn(format!("extension {} is ignored", name));
+        }
+    }
+    if !importer.list("animations").is_empty() {
+        importer.warn(String::from("animations are ignored"));
+    }
+
+    for (i, buffer) in importer.list("buffers").iter().enumerate() {
+        let bytes = match buffer.get("uri").and_then(|u| u.as_str()) {
+            Some(uri) => load_uri(uri, base_dir)?,
+            None if i == 0 => binary.clone().ok_or("gltf: buffer 0 has no data")?,
+            None => return Err(format!("gltf: buffer {} has no data", i)),
+        };
+        importer.buffers.push(bytes);
+    }
+
+    let scenes = importer.list("scenes");
+    let scene = doc.get("scene").and_then(|s| s.as_usize()).unwrap_or(0);
+    if let Some(scene) = scenes.get(scene) {
+        for node in scene.get("nodes").map_or(&[][..], |n| n.elements()) {
+            let node = node.as_usize().ok_or("gltf: invalid node index")?;
+            importer.visit_node(node, IDENTITY, 0)?;
+        }
+    } else {
+        importer.warn(String::from("no scene to import"));
+    }
+    return Ok(importer.gltf);
+}
+
+// JSON chunk and the optional binary chunk of a .glb file
+fn parse_glb(data: &[u8]) -> Result<(Json, Option<Vec<u8>>), String> {
+    let read_u32 = |pos: usize| -> Result<u32, String> {
+        let bytes = data
+            .get(pos..pos + 4)
+            .ok_or("glb: unexpected end of file")?;
+        return Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]));
+    };
+    if read_u32(4)? != 2 {
+        return Err(format!(
+            "glb: unsupported container version {}",
+            read_u32(4)?
+        ));
+    }
+    let length = (read_u32(8)? as usize).min(data.len());
+    let mut pos = 12;
+    let mut json = None;
+    let mut binary = None;
+    while pos + 8 <= length {
+        let chunk_length = read_u32(pos)? as usize;
+        let chunk_type = read_u32(pos + 4)?;
+        let chunk = data
+            .get(pos + 8..pos + 8 + chunk_length)
+            .ok_or("glb: chunk runs past the end of the file")?;
+        match chunk_type {
+            // "JSON"
+            0x4e4f534a => {
+                let text = std::str::from_utf8(chunk).map_err(|e| format!("glb: {}", e))?;
+                json = Some(Json::parse(text)?);
+            }
+            // "BIN\0"
+            0x004e4942 => binary = Some(chunk.to_vec()),
+            _ => {}
+        }
+        // chunks are padded to four bytes
+        pos += 8 + chunk_length.div_ceil(4) * 4;
+    }
+    return Ok((json.ok_or("glb: missing JSON chunk")?, binary));
+}
+
+fn load_uri(uri: &str, base_dir: &Path) -> Result<Vec<u8>, String> {
+    if let Some(data) = uri.strip_prefix("data:") {
+        let (_, encoded) = data
+            .split_once(";base64,")
+            .ok_or("gltf: only base64 data uris are supported")?;
+        return decode_base64(encoded);
+    }
+    let path = base_dir.join(decode_percent(uri));
+    return std::fs::read(&path).map_err(|e| format!("{}: {}", path.to_string_lossy(), e));
+}
+
+fn decode_base64(text: &str) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::with_capacity(text.len() * 3 / 4);
+    let mut bits: u32 = 0;
+    let mut count = 0;
+    for c in text.bytes() {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' | b'-' => 62,
+            b'/' | b'_' => 63,
+            b'=' => break,
+            _ => return Err(format!("gltf: invalid base64 character {}", c as char)),
+        };
+        bits = (bits << 6) | value as u32;
+        count += 6;
+        if count >= 8 {
+            count -= 8;
+            bytes.push((bits >> count) as u8);
+        }
+    }
+    return Ok(bytes);
+}
+
+// relative uris may escape spaces and other characters as %xx
+fn decode_percent(uri: &str) -> String {
+    let bytes = uri.as_bytes();
+    let mut decoded = vec![];
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|h| std::str::from_utf8(h).ok());
+        match hex.and_then(|h| u8::from_str_radix(h, 16).ok()) {
+            Some(v) if bytes[i] == b'%' => {
+                decoded.push(v);
+                i += 3;
+            }
+            _ => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    return String::from_utf8_lossy(&decoded).to_string();
+}
+
+// Column major 4x4 matrix, as stored in glTF.
+type Matrix = [f64; 16];
+
+const IDENTITY: Matrix = [
+    1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0,
+];
+
+fn multiply(a: &Matrix, b: &Matrix) -> Matrix {
+    let mut m = [0.0; 16];
+    for column in 0..4 {
+        for row in 0..4 {
+            for k in 0..4 {
+                m[column * 4 + row] += a[k * 4 + row] * b[column * 4 + k];
+            }
+        }
+    }
+    return m;
+}
+
+fn from_trs(t: [f64; 3], r: [f64; 4], s: [f64; 3]) -> Matrix {
+    let [x, y, z, w] = r;
+    let rotation = [
+        1.0 - 2.0 * (y * y + z * z),
+        2.0 * (x * y + z * w),
+        2.0 * (x * z - y * w),
+        2.0 * (x * y - z * w),
+        1.0 - 2.0 * (x * x + z * z),
+        2.0 * (y * z + x * w),
+        2.0 * (x * z + y * w),
+        2.0 * (y * z - x * w),
+        1.0 - 2.0 * (x * x + y * y),
+    ];
+    let mut m = IDENTITY;
+    for column in 0..3 {
+        for row in 0..3 {
+            m[column * 4 + row] = rotation[column * 3 + row] * s[column];
+        }
+    }
+    m[12] = t[0];
+    m[13] = t[1];
+    m[14] = t[2];
+    return m;
+}
+
+fn transform_point(m: &Matrix, p: Point) -> Point {
+    return Point::new(
+        m[0] * p.x + m[4] * p.y + m[8] * p.z + m[12],
+        m[1] * p.x + m[5] * p.y + m[9] * p.z + m[13],
+        m[2] * p.x + m[6] * p.y + m[10] * p.z + m[14],
+    );
+}
+
+fn transform_vector(m: &Matrix, v: Vec3) -> Vec3 {
+    return Vec3::new(
+        m[0] * v.x + m[4] * v.y + m[8] * v.z,
+        m[1] * v.x + m[5] * v.y + m[9] * v.z,
+        m[2] * v.x + m[6] * v.y + m[10] * v.z,
+    );
+}
+
+// Columns of the upper 3x3 block.
+fn axes(m: &Matrix) -> [Vec3; 3] {
+    return [
+        Vec3::new(m[0], m[1], m[2]),
+        Vec3::new(m[4], m[5], m[6]),
+        Vec3::new(m[8], m[9], m[10]),
+    ];
+}
+
+fn determinant(m: &Matrix) -> f64 {
+    let [a, b, c] = axes(m);
+    return Vec3::dot(a, Vec3::cross(b, c));
+}
+
+// Normals transform by the inverse transpose, which is the cofactor matrix up to scale.
+fn transform_normal(m: &Matrix, n: Vec3) -> Vec3 {
+    let [a, b, c] = axes(m);
+    let cofactor = n.x * Vec3::cross(b, c) + n.y * Vec3::cross(c, a) + n.z * Vec3::cross(a, b);
+    if determinant(m) < 0.0 {
+        return -1.0 * cofactor;
+    }
+    return cofactor;
+}
+
+struct Importer<'a> {
+    doc: &'a Json,
+    buffers: Vec<Vec<u8>>,
+    gltf: Gltf,
+}
+
+impl<'a> Importer<'a> {
+    fn warn(&mut self, warning: String) {
+        if !self.gltf.warnings.contains(&warning) {
+            self.gltf.warnings.push(warning);
+        }
+    }
+
+    fn list(&self, key: &str) -> &'a [Json] {
+        return self.doc.get(key).map_or(&[][..], |v| v.elements());
+    }
+
+    fn item(&self, key: &str, index: usize) -> Result<&'a Json, String> {
+        return self
+            .list(key)
+            .get(index)
+            .ok_or_else(|| format!("gltf: {} {} does not exist", key, index));
+    }
+
+    fn visit_node(&mut self, index: usize, parent: Matrix, depth: usize) -> Result<(), String> {
+        // the hierarchy must be a tree, a cycle would never end
+        if depth > 128 {
+            return Err(String::from(
+                "gltf: node hierarchy is too deep or has a cycle",
+            ));
+        }
+        let node = self.item("nodes", index)?;
+        let local = if let Some(matrix) = node.get("matrix") {
+            let values = numbers(matrix);
+            if values.len() != 16 {
+                return Err(format!("gltf: node {} has an invalid matrix", index));
+            }
+            let mut m = IDENTITY;
+            m.copy_from_slice(&values);
+            m
+        } else {
+            let t = numbers_or(node.get("translation"), &[0.0, 0.0, 0.0]);
+            let r = numbers_or(node.get("rotation"), &[0.0, 0.0, 0.0, 1.0]);
+            let s = numbers_or(node.get("scale"), &[1.0, 1.0, 1.0]);
+            from_trs(
+                [t[0], t[1], t[2]],
+                [r[0], r[1], r[2], r[3]],
+                [s[0], s[1], s[2]],
+            )
+        };
+        let world = multiply(&parent, &local);
+
+        if node.get("skin").is_some() || node.get("weights").is_some() {
+            self.warn(String::from(
+                "skins and morph targets are ignored, meshes are in their rest pose",
+            ));
+        }
+        if node
+            .get("extensions")
+            .and_then(|e| e.get("KHR_lights_punctual"))
+            .is_some()
+        {
+            self.warn(String::from(
+                "lights are ignored, add them to the scene file",
+            ));
+        }
+        if let Some(mesh) = node.get("mesh").and_then(|m| m.as_usize()) {
+            self.add_mesh(mesh, &world)?;
+        }
+        if let Some(camera) = node.get("camera").and_then(|c| c.as_usize()) {
+            self.add_camera(camera, &world)?;
+        }
+        for child in node.get("children").map_or(&[][..], |c| c.elements()) {
+            let child = child.as_usize().ok_or("gltf: invalid child index")?;
+            self.visit_node(child, world, depth + 1)?;
+        }
+        return Ok(());
+    }
+
+    fn add_camera(&mut self, index: usize, world: &Matrix) -> Result<(), String> {
+        let camera = self.item("cameras", index)?;
+        let projection = match camera.get("type").and_then(|t| t.as_str()) {
+            Some("perspective") => {
+                let yfov = camera
+                    .get("perspective")
+                    .and_then(|p| p.get("yfov"))
+                    .and_then(|y| y.as_f64())
+                    .ok_or_else(|| format!("gltf: camera {} has no yfov", index))?;
+                Projection::Perspective {
+                    vfov: yfov.to_degrees(),
+                }
+            }
+            Some("orthographic") => {
+                let ymag = camera
+                    .get("orthographic")
+                    .and_then(|o| o.get("ymag"))
+                    .and_then(|y| y.as_f64())
+                    .ok_or_else(|| format!("gltf: camera {} has no ymag", index))?;
+                Projection::Orthographic { height: 2.0 * ymag }
+            }
+            other => {
+                return Err(format!(
+                    "gltf: camera {} has unknown type {}",
+                    index,
+                    other.unwrap_or("none")
+                ))
+            }
+        };
+        // cameras look down their local -z with +y up
+        let lookfrom = transform_point(world, Point::default());
+        let forward = transform_vector(world, Vec3::new(0.0, 0.0, -1.0)).unit_vector();
+        self.gltf.cameras.push(GltfCamera {
+            lookfrom,
+            lookat: lookfrom + forward,
+            vup: transform_vector(world, Vec3::new(0.0, 1.0, 0.0)).unit_vector(),
+            projection,
+        });
+        return Ok(());
+    }
+
+    fn add_mesh(&mut self, index: usize, world: &Matrix) -> Result<(), String> {
+        let mesh = self.item("meshes", index)?;
+        for primitive in mesh.get("primitives").map_or(&[][..], |p| p.elements()) {
+            let mode = primitive
+                .get("mode")
+                .and_then(|m| m.as_usize())
+                .unwrap_or(4);
+            if mode != 4 {
+                self.warn(format!(
+                    "primitives with mode {} are skipped, only triangles are supported",
+                    mode
+                ));
+                continue;
+            }
+            let attributes = primitive.get("attributes");
+            let attribute = |name: &str| {
+                attributes
+                    .and_then(|a| a.get(name))
+                    .and_then(|a| a.as_usize())
+            };
+            let positions = match attribute("POSITION") {
+                Some(accessor) => self.read_accessor(accessor)?,
+                None => {
+                    self.warn(String::from("primitives without positions are skipped"));
+                    continue;
+                }
+            };
+            let normals = match attribute("NORMAL") {
+                Some(accessor) => Some(self.read_accessor(accessor)?),
+                None => None,
+            };
+            let uvs = match attribute("TEXCOORD_0") {
+                Some(accessor) => Some(self.read_accessor(accessor)?),
+                None => None,
+            };
+            // other accessor types would be indexed past their components
+            let check = |values: &[Vec<f64>], name: &str, expected: usize| {
+                let found = values.first().map_or(expected, |value| value.len());
+                if found != expected {
+                    return Err(format!(
+                        "gltf: mesh {} has a {} {}, expected {}",
+                        index,
+                        accessor_type(found),
+                        name,
+                        accessor_type(expected)
+                    ));
+                }
+                return Ok(());
+            };
+            check(&positions, "POSITION", 3)?;
+            if let Some(normals) = &normals {
+                check(normals, "NORMAL", 3)?;
+            }
+            if let Some(uvs) = &uvs {
+                check(uvs, "TEXCOORD_0", 2)?;
+            }
+            let indices: Vec<usize> = match primitive.get("indices").and_then(|i| i.as_usize()) {
+                Some(accessor) => self
+                    .read_accessor(accessor)?
+                    .iter()
+                    .map(|i| i[0] as usize)
+                    .collect(),
+                None => (0..positions.len()).collect(),
+            };
+
+            // mirroring transforms turn the winding inside out
+            let flip = determinant(world) < 0.0;
+            let mut faces = vec![];
+            for triangle in indices.chunks_exact(3) {
+                if triangle.iter().any(|i| *i >= positions.len()) {
+                    return Err(format!("gltf: mesh {} has an index out of range", index));
+                }
+                if flip {
+                    faces.push(vec![triangle[0], triangle[2], triangle[1]]);
+                } else {
+                    faces.push(triangle.to_vec());
+                }
+            }
+            let vec3 = |v: &Vec<f64>| Vec3::new(v[0], v[1], v[2]);
+            let mut result = Mesh::new(
+                positions
+                    .iter()
+                    .map(|p| transform_point(world, vec3(p)))
+                    .collect(),
+                faces,
+            );
+            result.normals = normals.map(|normals| {
+                return normals
+                    .iter()
+                    .map(|n| transform_normal(world, vec3(n)).unit_vector())
+                    .collect();
+            });
+            result.uvs = uvs.map(|uvs| uvs.iter().map(|uv| (uv[0], uv[1])).collect());
+            if result
+                .normals
+                .as_ref()
+                .is_some_and(|n| n.len() != result.positions.len())
+                || result
+                    .uvs
+                    .as_ref()
+                    .is_some_and(|uv| uv.len() != result.positions.len())
+            {
+                return Err(format!(
+                    "gltf: mesh {} has attributes of different lengths",
+                    index
+                ));
+            }
+
+            let material_index = primitive.get("material").and_then(|m| m.as_usize());
+            let (material, opacity) = match material_index {
+                Some(material) => self.make_material(material)?,
+                None => (
+                    Box::new(Lambertian::new(Color::new(0.8, 0.8, 0.8))) as Box<dyn Material>,
+                    1.0,
+                ),
+            };
+            let object: Box<dyn Hittable> = Box::new(result.into_bvh(material));
+            self.gltf.object_materials.push(material_index);
+            if opacity < 1.0 {
+                let opacity = Box::new(SolidColor::new(Color::new(opacity, opacity, opacity)));
+                self.gltf
+                    .objects
+                    .push(Box::new(AlphaMask::new(object, opacity)));
+            } else {
+                self.gltf.objects.push(object);
+            }
+        }
+        return Ok(());
+    }
+
+    // Metallic-roughness materials become dielectrics when they transmit, metals when
+    // mostly metallic and lambertians otherwise. The opacity comes from the alpha of the
+    // base color: cut at alphaCutoff for MASK and kept as is for BLEND.
+    fn make_material(&mut self, index: usize) -> Result<(Box<dyn Material>, f64), String> {
+        let material = self.item("materials", index)?;
+        let name = material
+            .get("name")
+            .and_then(|n| n.as_str())
+            .map_or(format!("{}", index), |n| n.to_string());
+        let pbr = material.get("pbrMetallicRoughness");
+        let factor = |key: &str, default: f64| {
+            return pbr
+                .and_then(|p| p.get(key))
+                .and_then(|f| f.as_f64())
+                .unwrap_or(default);
+        };
+        let base = numbers_or(
+            pbr.and_then(|p| p.get("baseColorFactor")),
+            &[1.0, 1.0, 1.0, 1.0],
+        );
+        let albedo = Color::new(base[0], base[1], base[2]);
+        let metallic = factor("metallicFactor", 1.0);
+        let roughness = factor("roughnessFactor", 1.0);
+        let extension = |name: &str, key: &str| {
+            return material
+                .get("extensions")
+                .and_then(|e| e.get(name))
+                .and_then(|e| e.get(key))
+                .and_then(|v| v.as_f64());
+        };
+        let transmission =
+            extension("KHR_materials_transmission", "transmissionFactor").unwrap_or(0.0);
+        let ior = extension("KHR_materials_ior", "ior").unwrap_or(1.5);
+
+        let textures = ["baseColorTexture", "metallicRoughnessTexture"];
+        if textures
+            .iter()
+            .any(|t| pbr.and_then(|p| p.get(t)).is_some())
+            || ["normalTexture", "occlusionTexture", "emissiveTexture"]
+                .iter()
+                .any(|t| material.get(t).is_some())
+        {
+            self.warn(format!(
+                "material {}: textures are ignored, using the constant factors",
+                name
+            ));
+        }
+        let emissive = numbers_or(material.get("emissiveFactor"), &[0.0, 0.0, 0.0]);
+        if emissive.iter().any(|e| *e > 0.0) {
+            self.warn(format!("material {}: emission is ignored", name));
+        }
+        let opacity = match material.get("alphaMode").and_then(|m| m.as_str()) {
+            Some("MASK") => {
+                let cutoff = material
+                    .get("alphaCutoff")
+                    .and_then(|c| c.as_f64())
+                    .unwrap_or(0.5);
+                if base[3] >= cutoff {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            Some("BLEND") => base[3].clamp(0.0, 1.0),
+            _ => 1.0,
+        };
+        if transmission < 0.5 && metallic > 0.05 && metallic < 0.95 {
+            self.warn(format!(
+                "material {}: metallic {} is rounded to {}",
+                name,
+                metallic,
+                metallic.round()
+            ));
+        }
+
+        if transmission >= 0.5 {
+            return Ok((Box::new(Dielectric::new(ior)), opacity));
+        }
+        if metallic >= 0.5 {
+            return Ok((Box::new(Metal::new(albedo, roughness)), opacity));
+        }
+        return Ok((Box::new(Lambertian::new(albedo)), opacity));
+    }
+
+    // Elements of an accessor as f64, normalized integers scaled to [0, 1] or [-1, 1].
+    fn read_accessor(&mut self, index: usize) -> Result<Vec<Vec<f64>>, String> {
+        let accessor = self.item("accessors", index)?;
+        let count = accessor
+            .get("count")
+            .and_then(|c| c.as_usize())
+            .unwrap_or(0);
+        let components = match accessor.get("type").and_then(|t| t.as_str()) {
+            Some("SCALAR") => 1,
+            Some("VEC2") => 2,
+            Some("VEC3") => 3,
+            Some("VEC4") => 4,
+            Some("MAT4") => 16,
+            other => {
+                return Err(format!(
+                    "gltf: accessor {} has unsupported type {}",
+                    index,
+                    other.unwrap_or("none")
+                ))
+            }
+        };
+        let component_type = accessor
+            .get("componentType")
+            .and_then(|c| c.as_usize())
+            .unwrap_or(0);
+        let size = match component_type {
+            5120 | 5121 => 1,
+            5122 | 5123 => 2,
+            5125 | 5126 => 4,
+            other => {
+                return Err(format!(
+                    "gltf: accessor {} has unsupported component type {}",
+                    index, other
+                ))
+            }
+        };
+        let normalized = accessor.get("normalized") == Some(&Json::Bool(true));
+        if accessor.get("sparse").is_some() {
+            self.warn(String::from(
+                "sparse accessors are ignored, using the base values",
+            ));
+        }
+
+        // accessors without a buffer view are all zeros
+        let view = match accessor.get("bufferView").and_then(|v| v.as_usize()) {
+            Some(view) => self.item("bufferViews", view)?,
+            None if count > MAX_ZEROS => {
+                return Err(format!("gltf: accessor {} has too many elements", index));
+            }
+            None => return Ok(vec![vec![0.0; components]; count]),
+        };
+        let buffer = view.get("buffer").and_then(|b| b.as_usize()).unwrap_or(0);
+        let data = self
+            .buffers
+            .get(buffer)
+            .ok_or_else(|| format!("gltf: buffer {} does not exist", buffer))?;
+        let offset = view
+            .get("byteOffset")
+            .and_then(|o| o.as_usize())
+            .unwrap_or(0)
+            .saturating_add(
+                accessor
+                    .get("byteOffset")
+                    .and_then(|o| o.as_usize())
+                    .unwrap_or(0),
+            );
+        let stride = view
+            .get("byteStride")
+            .and_then(|s| s.as_usize())
+            .unwrap_or(components * size);
+
+        // the count comes from the file, the buffer has to hold all of it before allocating
+        let fits = match count {
+            0 => true,
+            _ => (count - 1)
+                .checked_mul(stride)
+                .and_then(|last| last.checked_add(offset))
+                .and_then(|last| last.checked_add(components * size))
+                .is_some_and(|end| end <= data.len()),
+        };
+        if !fits {
+            return Err(format!(
+                "gltf: accessor {} runs past the end of its buffer",
+                index
+            ));
+        }
+        let mut elements = Vec::with_capacity(count);
+        for i in 0..count {
+            let start = offset + i * stride;
+            let bytes = data.get(start..start + components * size).ok_or_else(|| {
+                format!("gltf: accessor {} runs past the end of its buffer", index)
+            })?;
+            let element = bytes
+                .chunks_exact(size)
+                .map(|b| {
+                    return match component_type {
+                        5120 if normalized => (b[0] as i8 as f64 / 127.0).max(-1.0),
+                        5120 => b[0] as i8 as f64,
+                        5121 if normalized => b[0] as f64 / 255.0,
+                        5121 => b[0] as f64,
+                        5122 if normalized => {
+                            (i16::from_le_bytes([b[0], b[1]]) as f64 / 32767.0).max(-1.0)
+                        }
+                        5122 => i16::from_le_bytes([b[0], b[1]]) as f64,
+                        5123 if normalized => u16::from_le_bytes([b[0], b[1]]) as f64 / 65535.0,
+                        5123 => u16::from_le_bytes([b[0], b[1]]) as f64,
+                        5125 => u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+                        _ => f32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+                    };
+                })
+                .collect();
+            elements.push(element);
+        }
+        return Ok(elements);
+    }
+}
+
+// name of the accessor type with this many components
+fn accessor_type(components: usize) -> &'static str {
+    return match components {
+        1 => "SCALAR",
+        2 => "VEC2",
+        3 => "VEC3",
+        4 => "VEC4",
+        _ => "MAT4",
+    };
+}
+
+fn numbers(value: &Json) -> Vec<f64> {
+    return value.elements().iter().filter_map(|v| v.as_f64()).collect();
+}
+
+// the numbers of an array, or the default when missing or too short
+fn numbers_or(value: Option<&Json>, default: &[f64]) -> Vec<f64> {
+    let values = value.map(numbers).unwrap_or_default();
+    if values.len() < default.len() {
+        return default.to_vec();
+    }
+    return values;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ray::Ray;
+
+    const EPSILON: f64 = 0.000001;
+
+    // a unit quad in the xy plane, two triangles with u16 indices
+    fn quad_buffer() -> Vec<u8> {
+        let mut data = vec![];
+        for v in [
+            0.0f32, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 1.0, 0.0, 0.0, 1.0, 0.0,
+        ] {
+            data.extend_from_slice(&v.to_le_bytes());
+        }
+        for i in [0u16, 1, 2, 0, 2, 3] {
+            data.extend_from_slice(&i.to_le_bytes());
+        }
+        return data;
+    }
+
+    const QUAD_JSON: &str = r#"{
+        "asset": {"version": "2.0"},
+        "scene": 0,
+        "scenes": [{"nodes": [0, 2]}],
+        "nodes": [
+            {"translation": [0, 0, -2], "children": [1]},
+            {"mesh": 0, "scale": [2, 2, 2]},
+            {"camera": 0, "translation": [0.5, 0.5, 3], "rotation": [0, 0.7071068, 0, 0.7071068]}
+        ],
+        "cameras": [{"type": "perspective", "perspective": {"yfov": 0.5, "znear": 0.1}}],
+        "meshes": [{"primitives": [{"attributes": {"POSITION": 0}, "indices": 1, "material": 0}]}],
+        "materials": [{"name": "gold", "emissiveFactor": [1, 0, 0],
+            "pbrMetallicRoughness": {"baseColorFactor": [1, 0.8, 0.3, 1], "roughnessFactor": 0.2}}],
+        "accessors": [
+            {"bufferView": 0, "componentType": 5126, "count": 4, "type": "VEC3"},
+            {"bufferView": 1, "componentType": 5123, "count": 6, "type": "SCALAR"}
+        ],
+        "bufferViews": [
+            {"buffer": 0, "byteOffset": 0, "byteLength": 48},
+            {"buffer": 0, "byteOffset": 48, "byteLength": 12}
+        ],
+        "buffers": [{"byteLength": 60}]
+    }"#;
+
+    fn glb(json: &str, binary: &[u8]) -> Vec<u8> {
+        let mut json = json.as_bytes().to_vec();
+        while !json.len().is_multiple_of(4) {
+            json.push(b' ');
+        }
+        let mut binary = binary.to_vec();
+        while !binary.len().is_multiple_of(4) {
+            binary.push(0);
+        }
+        let mut data = b"glTF".to_vec();
+        data.extend_from_slice(&2u32.to_le_bytes());
+        data.extend_from_slice(&((28 + json.len() + binary.len()) as u32).to_le_bytes());
+        data.extend_from_slice(&(json.len() as u32).to_le_bytes());
+        data.extend_from_slice(b"JSON");
+        data.extend_from_slice(&json);
+        data.extend_from_slice(&(binary.len() as u32).to_le_bytes());
+        data.extend_from_slice(b"BIN\0");
+        data.extend_from_slice(&binary);
+        return data;
+    }
+
+    #[test]
+    fn test_glb() {
+        let gltf = parse_gltf(&glb(QUAD_JSON, &quad_buffer()), Path::new(".")).unwrap();
+        assert_eq!(gltf.objects.len(), 1);
+        assert_eq!(gltf.warnings, vec!["material gold: emission is ignored"]);
+
+        // the quad is scaled to 2x2 by its node and moved to z = -2 by the parent
+        let ray = Ray::new(Point::new(1.5, 1.5, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        let record = gltf.objects[0].hit(&ray, 0.001, f64::INFINITY).unwrap();
+        assert!((record.t - 7.0).abs() <= EPSILON);
+        assert!(record.front_face);
+        let miss = Ray::new(Point::new(2.5, 1.5, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(gltf.objects[0].hit(&miss, 0.001, f64::INFINITY).is_none());
+
+        // masked out by an alpha below the cutoff
+        let masked = QUAD_JSON.replace(
+            r#""name": "gold","#,
+            r#""name": "gold", "alphaMode": "MASK", "alphaCutoff": 0.5,"#,
+        );
+        let masked = masked.replace("[1, 0.8, 0.3, 1]", "[1, 0.8, 0.3, 0.4]");
+        let gltf_masked = parse_gltf(&glb(&masked, &quad_buffer()), Path::new(".")).unwrap();
+        assert!(gltf_masked.objects[0]
+            .hit(&ray, 0.001, f64::INFINITY)
+            .is_none());
+
+        // turned 90 degrees around y, the camera looks down -x
+        let camera = &gltf.cameras[0];
+        assert!((camera.lookfrom - Point::new(0.5, 0.5, 3.0)).length() <= EPSILON);
+        assert!((camera.lookat - Point::new(-0.5, 0.5, 3.0)).length() <= EPSILON);
+        assert!((camera.vup - Vec3::new(0.0, 1.0, 0.0)).length() <= EPSILON);
+        match camera.projection {
+            Projection::Perspective { vfov } => {
+                assert!((vfov - 0.5_f64.to_degrees()).abs() <= EPSILON)
+            }
+            Projection::Orthographic { .. } => panic!("expected a perspective camera"),
+        }
+    }
+
+    #[test]
+    fn test_embedded_buffer_and_errors() {
+        assert_eq!(decode_base64("AAEC/w==").unwrap(), vec![0, 1, 2, 255]);
+        assert_eq!(decode_percent("my%20model.bin"), "my model.bin");
+
+        let encoded: String = {
+            // base64 of the quad buffer
+            let table = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+            quad_buffer()
+                .chunks(3)
+                .flat_map(|c| {
+                    let n = (c[0] as u32) << 16
+                        | (*c.get(1).unwrap_or(&0) as u32) << 8
+                        | *c.get(2).unwrap_or(&0) as u32;
+                    let mut out = vec![];
+                    for i in 0..=c.len() {
+                        out.push(table[(n >> (18 - 6 * i) & 63) as usize] as char);
+                    }
+                    return out;
+                })
+                .collect()
+        };
+        let json = QUAD_JSON.replace(
+            r#"{"byteLength": 60}"#,
+            &format!(
+                r#"{{"byteLength": 60, "uri": "data:application/octet-stream;base64,{}"}}"#,
+                encoded
+            ),
+        );
+        let gltf = parse_gltf(json.as_bytes(), Path::new(".")).unwrap();
+        let ray = Ray::new(Point::new(0.5, 1.5, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(gltf.objects[0].hit(&ray, 0.001, f64::INFINITY).is_some());
+
+        let required = QUAD_JSON.replace(
+            r#""scene": 0,"#,
+            r#""scene": 0, "extensionsRequired": ["KHR_draco_mesh_compression"],"#,
+        );
+        let error = parse_gltf(required.as_bytes(), Path::new("."))
+            .err()
+            .unwrap();
+        assert!(error.contains("KHR_draco_mesh_compression"));
+        let old = QUAD_JSON.replace(r#""version": "2.0""#, r#""version": "1.0""#);
+        assert!(parse_gltf(old.as_bytes(), Path::new(".")).is_err());
+
+        let flat = QUAD_JSON.replace(
+            r#""count": 4, "type": "VEC3""#,
+            r#""count": 4, "type": "VEC2""#,
+        );
+        let error = parse_gltf(&glb(&flat, &quad_buffer()), Path::new("."))
+            .err()
+            .unwrap();
+        assert_eq!(error, "gltf: mesh 0 has a VEC2 POSITION, expected VEC3");
+        let scalar_uvs =
+            QUAD_JSON.replace(r#""POSITION": 0}"#, r#""POSITION": 0, "TEXCOORD_0": 1}"#);
+        let error = parse_gltf(&glb(&scalar_uvs, &quad_buffer()), Path::new("."))
+            .err()
+            .unwrap();
+        assert_eq!(error, "gltf: mesh 0 has a SCALAR TEXCOORD_0, expected VEC2");
+
+        // counts the data can't back are errors, not allocations
+        let huge = QUAD_JSON.replace(r#""count": 4"#, r#""count": 1e15"#);
+        let error = parse_gltf(&glb(&huge, &quad_buffer()), Path::new("."))
+            .err()
+            .unwrap();
+        assert_eq!(error, "gltf: accessor 0 runs past the end of its buffer");
+        let zeros = huge.replace(r#"{"bufferView": 0, "#, "{");
+        let error = parse_gltf(&glb(&zeros, &quad_buffer()), Path::new("."))
+            .err()
+            .unwrap();
+        assert_eq!(error, "gltf: accessor 0 has too many elements");
+    }
+}
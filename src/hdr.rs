@@ -0,0 +1,172 @@
+use crate::{image::Image, vec3::Color};
+
+// Reader for Radiance RGBE (.hdr) images, supporting flat and new-style RLE scanlines.
+pub fn read_hdr(path: &str) -> Result<Image, String> {
+    let data = std::fs::read(path).map_err(|e| format!("{}: {}", path, e))?;
+    return parse_hdr(&data).map_err(|e| format!("{}: {}", path, e));
+}
+
+pub fn parse_hdr(data: &[u8]) -> Result<Image, String> {
+    let mut pos = 0;
+    let mut first = true;
+    // header lines end with an empty line
+    loop {
+        let line = read_line(data, &mut pos).ok_or("unexpected end of header")?;
+        if first {
+            if !line.starts_with("#?") {
+                return Err(String::from("not a radiance file"));
+            }
+            first = false;
+            continue;
+        }
+        if line.is_empty() {
+            break;
+        }
+        if let Some(format) = line.strip_prefix("FORMAT=") {
+            if format != "32-bit_rle_rgbe" {
+                return Err(format!("unsupported format {}", format));
+            }
+        }
+    }
+
+    let resolution = read_line(data, &mut pos).ok_or("missing resolution")?;
+    let tokens: Vec<&str> = resolution.split_whitespace().collect();
+    if tokens.len() != 4 || tokens[0] != "-Y" || tokens[2] != "+X" {
+        return Err(format!("unsupported orientation {}", resolution));
+    }
+    let height: u32 = tokens[1].parse().map_err(|_| "invalid height")?;
+    let width: u32 = tokens[3].parse().map_err(|_| "invalid width")?;
+
+    let mut image = Image::new(width, height);
+    let mut scanline = vec![[0u8; 4]; width as usize];
+    for y in 0..height {
+        read_scanline(data, &mut pos, &mut scanline)?;
+        for (x, rgbe) in scanline.iter().enumerate() {
+            image.set(x as u32, y, rgbe_to_color(*rgbe));
+        }
+    }
+    return Ok(image);
+}
+
+fn read_line(data: &[u8], pos: &mut usize) -> Option<String> {
+    if *pos >= data.len() {
+        return None;
+    }
+    let start = *pos;
+    while *pos < data.len() && data[*pos] != b'\n' {
+        *pos += 1;
+    }
+    let line = String::from_utf8_lossy(&data[start..*pos])
+        .trim()
+        .to_string();
+    *pos += 1;
+    return Some(line);
+}
+
+fn next_byte(data: &[u8], pos: &mut usize) -> Result<u8, String> {
+    let b = *data.get(*pos).ok_or("unexpected end of pixel data")?;
+    *pos += 1;
+    return Ok(b);
+}
+
+fn read_scanline(data: &[u8], pos: &mut usize, scanline: &mut [[u8; 4]]) -> Result<(), String> {
+    let width = scanline.len();
+    let is_rle = (8..32768).contains(&width)
+        && data.len() >= *pos + 4
+        && data[*pos] == 2
+        && data[*pos + 1] == 2
+        && data[*pos + 2] & 0x80 == 0;
+
+    if !is_rle {
+        for pixel in scanline.iter_mut() {
+            for c in pixel.iter_mut() {
+                *c = next_byte(data, pos)?;
+            }
+        }
+        return Ok(());
+    }
+
+    let encoded_width = ((data[*pos + 2] as usize) << 8) | data[*pos + 3] as usize;
+    if encoded_width != width {
+        return Err(String::from("scanline width mismatch"));
+    }
+    *pos += 4;
+
+    // each of the four components is run-length encoded separately
+    for channel in 0..4 {
+        let mut x = 0;
+        while x < width {
+            let count = next_byte(data, pos)? as usize;
+            if count > 128 {
+                let run = count - 128;
+                let value = next_byte(data, pos)?;
+                if x + run > width {
+                    return Err(String::from("bad run length"));
+                }
+                for pixel in &mut scanline[x..x + run] {
+                    pixel[channel] = value;
+                }
+                x += run;
+            } else {
+                if count == 0 || x + count > width {
+                    return Err(String::from("bad run length"));
+                }
+                for pixel in &mut scanline[x..x + count] {
+                    pixel[channel] = next_byte(data, pos)?;
+                }
+                x += count;
+            }
+        }
+    }
+    return Ok(());
+}
+
+fn rgbe_to_color(rgbe: [u8; 4]) -> Color {
+    if rgbe[3] == 0 {
+        return Color::default();
+    }
+    let scale = 2f64.powi(rgbe[3] as i32 - 136);
+    return Color::new(
+        rgbe[0] as f64 * scale,
+        rgbe[1] as f64 * scale,
+        rgbe[2] as f64 * scale,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPSILON: f64 = 0.000001;
+
+    #[test]
+    fn test_parse_flat() {
+        let mut data = b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y 1 +X 2\n".to_vec();
+        data.extend_from_slice(&[128, 64, 0, 129, 0, 0, 0, 0]);
+        let image = parse_hdr(&data).unwrap();
+
+        let c = image.get(0, 0);
+        assert!((c.x - 1.0).abs() <= EPSILON);
+        assert!((c.y - 0.5).abs() <= EPSILON);
+        assert!(c.z.abs() <= EPSILON);
+        assert!(image.get(1, 0).x.abs() <= EPSILON);
+    }
+
+    #[test]
+    fn test_parse_rle() {
+        let mut data = b"#?RADIANCE\n\n-Y 1 +X 8\n".to_vec();
+        data.extend_from_slice(&[2, 2, 0, 8]);
+        // red: run of 8, green: 8 literals, blue: run of 8, exponent: run of 8
+        data.extend_from_slice(&[136, 128]);
+        data.extend_from_slice(&[8, 0, 16, 32, 48, 64, 80, 96, 112]);
+        data.extend_from_slice(&[136, 0]);
+        data.extend_from_slice(&[136, 128]);
+        let image = parse_hdr(&data).unwrap();
+
+        assert_eq!(image.width, 8);
+        let c = image.get(2, 0);
+        assert!((c.x - 0.5).abs() <= EPSILON);
+        assert!((c.y - 0.125).abs() <= EPSILON);
+        assert!(c.z.abs() <= EPSILON);
+    }
+}
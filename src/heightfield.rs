@@ -0,0 +1,296 @@
+use crate::{
+    aabb::Aabb,
+    hittable::{HitRecord, Hittable},
+    image::{luminance, Image},
+    material::Material,
+    ray::Ray,
+    triangle::intersect_triangle,
+    vec3::{Point, Vec3},
+};
+
+// Terrain from a grayscale image. Each pixel is a vertex, its luminance in [0, 1] scaled
+// by size.y is the height. The image spans size.x along x and size.z along z from origin,
+// the top row at origin.z. Cells are split into two triangles with smooth normals.
+pub struct Heightfield {
+    nx: usize,
+    nz: usize,
+    heights: Vec<f64>,
+    normals: Vec<Vec3>,
+    origin: Point,
+    size: Vec3,
+    max_height: f64,
+    material: Box<dyn Material>,
+}
+
+impl Heightfield {
+    pub fn new(
+        image: &Image,
+        origin: Point,
+        size: Vec3,
+        material: Box<dyn Material>,
+    ) -> Heightfield {
+        assert!(image.width >= 2 && image.height >= 2);
+        let nx = image.width as usize;
+        let nz = image.height as usize;
+        let mut heights = Vec::with_capacity(nx * nz);
+        for j in 0..image.height {
+            for i in 0..image.width {
+                heights.push(luminance(image.get(i, j)).clamp(0.0, 1.0));
+            }
+        }
+        let max_height = heights.iter().cloned().fold(0.0, f64::max);
+
+        let mut heightfield = Heightfield {
+            nx,
+            nz,
+            heights,
+            normals: vec![],
+            origin,
+            size,
+            max_height,
+            material,
+        };
+        heightfield.normals = (0..nx * nz)
+            .map(|k| heightfield.vertex_normal(k % nx, k / nx))
+            .collect();
+        return heightfield;
+    }
+
+    fn cell_width(&self) -> f64 {
+        return self.size.x / (self.nx - 1) as f64;
+    }
+
+    fn cell_depth(&self) -> f64 {
+        return self.size.z / (self.nz - 1) as f64;
+    }
+
+    fn height(&self, i: usize, j: usize) -> f64 {
+        return self.heights[j * self.nx + i] * self.size.y;
+    }
+
+    fn vertex(&self, i: usize, j: usize) -> Point {
+        return self.origin
+            + Vec3::new(
+                i as f64 * self.cell_width(),
+                self.height(i, j),
+                j as f64 * self.cell_depth(),
+            );
+    }
+
+    // central differences, one sided at the border
+    fn vertex_normal(&self, i: usize, j: usize) -> Vec3 {
+        let (i0, i1) = (i.saturating_sub(1), (i + 1).min(self.nx - 1));
+        let (j0, j1) = (j.saturating_sub(1), (j + 1).min(self.nz - 1));
+        let dhdx =
+            (self.height(i1, j) - self.height(i0, j)) / ((i1 - i0) as f64 * self.cell_width());
+        let dhdz =
+            (self.height(i, j1) - self.height(i, j0)) / ((j1 - j0) as f64 * self.cell_depth());
+        return Vec3::new(-dhdx, 1.0, -dhdz).unit_vector();
+    }
+
+    // both triangles of the cell, the nearer hit with the interpolated normal
+    fn hit_cell(
+        &self,
+        ray: &Ray,
+        i: usize,
+        j: usize,
+        t_min: f64,
+        t_max: f64,
+    ) -> Option<(f64, Vec3)> {
+        let corners = [(i, j), (i + 1, j), (i, j + 1), (i + 1, j + 1)];
+        let mut closest: Option<(f64, Vec3)> = None;
+        for [a, b, c] in [[0, 1, 2], [1, 3, 2]] {
+            let (ia, ja) = corners[a];
+            let (ib, jb) = corners[b];
+            let (ic, jc) = corners[c];
+            let t_max = closest.map_or(t_max, |c| c.0);
+            if let Some((t, b1, b2)) = intersect_triangle(
+                ray,
+                self.vertex(ia, ja),
+                self.vertex(ib, jb),
+                self.vertex(ic, jc),
+                t_min,
+                t_max,
+            ) {
+                let normal = (1.0 - b1 - b2) * self.normals[ja * self.nx + ia]
+                    + b1 * self.normals[jb * self.nx + ib]
+                    + b2 * self.normals[jc * self.nx + ic];
+                closest = Some((t, normal.unit_vector()));
+            }
+        }
+        return closest;
+    }
+
+    fn bounds(&self) -> Aabb {
+        let extent = Vec3::new(self.size.x, self.max_height * self.size.y, self.size.z);
+        return Aabb::new(self.origin, self.origin + extent);
+    }
+
+    // slab test against the bounding box
+    fn clip(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<(f64, f64)> {
+        let Aabb { min, max } = self.bounds();
+        let mut t0 = t_min;
+        let mut t1 = t_max;
+        for (o, d, lo, hi) in [
+            (ray.org.x, ray.dir.x, min.x, max.x),
+            (ray.org.y, ray.dir.y, min.y, max.y),
+            (ray.org.z, ray.dir.z, min.z, max.z),
+        ] {
+            let inv = 1.0 / d;
+            let (near, far) = if inv >= 0.0 {
+                ((lo - o) * inv, (hi - o) * inv)
+            } else {
+                ((hi - o) * inv, (lo - o) * inv)
+            };
+            // a ray parallel to a slab and outside it gives nan
+            if near.is_nan() || far.is_nan() {
+                return None;
+            }
+            t0 = t0.max(near);
+            t1 = t1.min(far);
+        }
+        if t0 > t1 {
+            return None;
+        }
+        return Some((t0, t1));
+    }
+}
+
+impl Hittable for Heightfield {
+    // Walks the cells under the ray in order with a 2D DDA, so the first hit found is the
+    // closest.
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        let (t0, t1) = self.clip(ray, t_min, t_max)?;
+        let entry = ray.at(t0) - self.origin;
+        let dx = self.cell_width();
+        let dz = self.cell_depth();
+        let mut i = ((entry.x / dx) as i64).clamp(0, self.nx as i64 - 2);
+        let mut j = ((entry.z / dz) as i64).clamp(0, self.nz as i64 - 2);
+
+        let step_i: i64 = if ray.dir.x >= 0.0 { 1 } else { -1 };
+        let step_j: i64 = if ray.dir.z >= 0.0 { 1 } else { -1 };
+        let boundary = |cell: i64, step: i64, size: f64, origin: f64, o: f64, d: f64| {
+            if d == 0.0 {
+                return f64::INFINITY;
+            }
+            let edge = origin + (cell + if step > 0 { 1 } else { 0 }) as f64 * size;
+            return (edge - o) / d;
+        };
+        let mut t_next_i = boundary(i, step_i, dx, self.origin.x, ray.org.x, ray.dir.x);
+        let mut t_next_j = boundary(j, step_j, dz, self.origin.z, ray.org.z, ray.dir.z);
+        let t_delta_i = (dx / ray.dir.x).abs();
+        let t_delta_j = (dz / ray.dir.z).abs();
+
+        loop {
+            if let Some((t, outward_normal)) =
+                self.hit_cell(ray, i as usize, j as usize, t_min, t_max)
+            {
+                let p = ray.at(t);
+                let front_face = HitRecord::is_front_face(ray, outward_normal);
+                let normal = HitRecord::get_normal(front_face, outward_normal);
+                let mut record = HitRecord::new(p, normal, t, front_face, &*self.material);
+                // v runs up the image, so maps of the same size line up with the terrain
+                record.u = ((p.x - self.origin.x) / self.size.x).clamp(0.0, 1.0);
+                record.v = 1.0 - ((p.z - self.origin.z) / self.size.z).clamp(0.0, 1.0);
+                record.dpdu = Vec3::new(self.size.x, 0.0, 0.0);
+                record.dpdv = Vec3::new(0.0, 0.0, -self.size.z);
+                return Some(record);
+            }
+
+            if t_next_i < t_next_j {
+                if t_next_i > t1 {
+                    return None;
+                }
+                i += step_i;
+                t_next_i += t_delta_i;
+            } else {
+                if t_next_j > t1 {
+                    return None;
+                }
+                j += step_j;
+                t_next_j += t_delta_j;
+            }
+            if i < 0 || j < 0 || i > self.nx as i64 - 2 || j > self.nz as i64 - 2 {
+                return None;
+            }
+        }
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        return Some(self.bounds());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lambertian::Lambertian, vec3::Color};
+
+    const EPSILON: f64 = 0.000001;
+
+    // height rises linearly along x from 0 to 1
+    fn ramp(width: u32, height: u32) -> Heightfield {
+        let mut image = Image::new(width, height);
+        for j in 0..height {
+            for i in 0..width {
+                let h = i as f64 / (width - 1) as f64;
+                image.set(i, j, Color::new(h, h, h));
+            }
+        }
+        let material = Box::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        return Heightfield::new(&image, Point::default(), Vec3::new(4.0, 2.0, 4.0), material);
+    }
+
+    #[test]
+    fn test_vertical_rays() {
+        let heightfield = ramp(9, 5);
+        let down = Ray::new(Point::new(1.3, 10.0, 2.7), Vec3::new(0.0, -1.0, 0.0));
+        let record = heightfield.hit(&down, 0.001, f64::INFINITY).unwrap();
+        assert!((record.p.y - 1.3 / 4.0 * 2.0).abs() <= EPSILON);
+        // slope of 1/2, the normal leans towards -x
+        let expected = Vec3::new(-0.5, 1.0, 0.0).unit_vector();
+        assert!((record.normal - expected).length() <= EPSILON);
+        assert!((record.u - 1.3 / 4.0).abs() <= EPSILON);
+
+        let outside = Ray::new(Point::new(5.0, 10.0, 2.0), Vec3::new(0.0, -1.0, 0.0));
+        assert!(heightfield.hit(&outside, 0.001, f64::INFINITY).is_none());
+    }
+
+    #[test]
+    fn test_grid_traversal() {
+        let heightfield = ramp(33, 17);
+        // skims over the low end and hits the ramp from the side
+        let ray = Ray::new(Point::new(-1.0, 1.0, 0.3), Vec3::new(1.0, 0.0, 0.5));
+        let record = heightfield.hit(&ray, 0.001, f64::INFINITY).unwrap();
+        assert!((record.p.x - 2.0).abs() <= EPSILON);
+        assert!((record.t - 3.0).abs() <= EPSILON);
+
+        // a ray over the highest point misses
+        let over = Ray::new(Point::new(-1.0, 2.5, 0.3), Vec3::new(1.0, -0.05, 0.5));
+        assert!(heightfield.hit(&over, 0.001, f64::INFINITY).is_none());
+    }
+
+    #[test]
+    fn test_v_runs_up_the_image() {
+        // height falls from the top row to the bottom one
+        let mut image = Image::new(3, 5);
+        for j in 0..5 {
+            for i in 0..3 {
+                let h = 1.0 - j as f64 / 4.0;
+                image.set(i, j, Color::new(h, h, h));
+            }
+        }
+        let material = Box::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        let size = Vec3::new(4.0, 1.0, 4.0);
+        let heightfield = Heightfield::new(&image, Point::default(), size, material);
+        for z in [0.3, 1.0, 2.5, 3.8] {
+            let down = Ray::new(Point::new(1.0, 10.0, z), Vec3::new(0.0, -1.0, 0.0));
+            let record = heightfield.hit(&down, 0.001, f64::INFINITY).unwrap();
+            // as in sample_image, v is 1 at the top row and 0 at the bottom one
+            assert!((record.v - (1.0 - z / 4.0)).abs() <= EPSILON);
+            assert!((record.v - record.p.y).abs() <= EPSILON);
+            let along = record.p + 0.1 * record.dpdv;
+            assert!((along.z - (z - 0.4)).abs() <= EPSILON);
+        }
+    }
+}
@@ -1,13 +1,27 @@
+use crate::aabb::Aabb;
 use crate::material::Material;
 use crate::ray::Ray;
-use crate::vec3::{Point, Vec3};
+use crate::vec3::{Color, Point, Vec3};
 
+#[derive(Clone, Copy)]
 pub struct HitRecord<'a> {
     pub p: Point,
     pub normal: Vec3,
     pub t: f64,
     pub front_face: bool,
     pub material: &'a dyn Material,
+    // surface parameterization in [0, 1], left at 0 by objects without one
+    pub u: f64,
+    pub v: f64,
+    // derivatives of the position along u and v, zero where there is no parameterization
+    pub dpdu: Vec3,
+    pub dpdv: Vec3,
+    // interpolated vertex color of meshes that have them
+    pub color: Option<Color>,
+    // index of the top level object in the world that was hit
+    pub object_id: usize,
+    // id the scene assigned to the material, 0 for materials without one
+    pub material_id: usize,
 }
 
 impl<'a> HitRecord<'a> {
@@ -24,6 +38,13 @@ impl<'a> HitRecord<'a> {
             t,
             front_face,
             material,
+            u: 0.0,
+            v: 0.0,
+            dpdu: Vec3::default(),
+            dpdv: Vec3::default(),
+            color: None,
+            object_id: 0,
+            material_id: 0,
         };
     }
 
@@ -40,6 +61,53 @@ impl<'a> HitRecord<'a> {
     }
 }
 
+// step past a crossing so the next call to hit() finds the one after it
+pub const INTERVAL_EPSILON: f64 = 1e-9;
+
+// The part of a ray inside a closed object, from where it enters to where it leaves.
+#[derive(Clone, Copy)]
+pub struct HitInterval<'a> {
+    pub enter: HitRecord<'a>,
+    pub exit: HitRecord<'a>,
+}
+
 pub trait Hittable {
-    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord>;
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>>;
+
+    // None for unbounded objects, which cannot go into a bvh
+    fn bounding_box(&self) -> Option<Aabb> {
+        return None;
+    }
+
+    // Share of light passing along the ray between t_min and t_max, 0 or 1 for solid
+    // objects. Participating media return a random estimate that is right on average.
+    fn transmittance(&self, ray: &Ray, t_min: f64, t_max: f64) -> f64 {
+        if self.hit(ray, t_min, t_max).is_some() {
+            return 0.0;
+        }
+        return 1.0;
+    }
+
+    // Every interval along the whole line of the ray, sorted by t, including the parts
+    // behind the origin. Only meaningful for closed objects. The default walks the
+    // surface crossings with hit() and pairs them by facing.
+    fn intervals(&self, ray: &Ray) -> Vec<HitInterval<'_>> {
+        let mut intervals = vec![];
+        let mut enter: Option<HitRecord> = None;
+        let mut t_min = f64::NEG_INFINITY;
+        while let Some(record) = self.hit(ray, t_min, f64::INFINITY) {
+            t_min = record.t + INTERVAL_EPSILON * record.t.abs().max(1.0);
+            if record.front_face {
+                enter = Some(record);
+                continue;
+            }
+            if let Some(enter) = enter.take() {
+                intervals.push(HitInterval {
+                    enter,
+                    exit: record,
+                });
+            }
+        }
+        return intervals;
+    }
 }
@@ -15,20 +15,37 @@ impl HittableList {
     pub fn add(&mut self, object: Box<dyn Hittable>) {
         self.objects.push(object);
     }
+
+    // moves the objects of other into this list, keeping them top level objects
+    pub fn extend(&mut self, other: HittableList) {
+        self.objects.extend(other.objects);
+    }
 }
 
 impl Hittable for HittableList {
-    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
         let mut closest_so_far = t_max;
         let mut record: Option<HitRecord> = None;
 
-        for o in &self.objects {
+        for (i, o) in self.objects.iter().enumerate() {
             let result = o.hit(ray, t_min, closest_so_far);
-            if let Some(v) = result {
+            if let Some(mut v) = result {
                 closest_so_far = v.t;
+                v.object_id = i;
                 record = Some(v);
             }
         }
         return record;
     }
+
+    fn transmittance(&self, ray: &Ray, t_min: f64, t_max: f64) -> f64 {
+        let mut transmittance = 1.0;
+        for o in &self.objects {
+            transmittance *= o.transmittance(ray, t_min, t_max);
+            if transmittance == 0.0 {
+                break;
+            }
+        }
+        return transmittance;
+    }
 }
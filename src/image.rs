@@ -0,0 +1,55 @@
+use crate::{color::to_string, hdr::read_hdr, netpbm::read_netpbm, vec3::Color};
+
+#[derive(Clone)]
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    pixels: Vec<Color>,
+}
+
+impl Image {
+    pub fn new(width: u32, height: u32) -> Image {
+        return Image {
+            width,
+            height,
+            pixels: vec![Color::default(); (width * height) as usize],
+        };
+    }
+
+    pub fn get(&self, x: u32, y: u32) -> Color {
+        return self.pixels[(y * self.width + x) as usize];
+    }
+
+    pub fn set(&mut self, x: u32, y: u32, color: Color) {
+        self.pixels[(y * self.width + x) as usize] = color;
+    }
+
+    pub fn to_ppm(&self) -> String {
+        let mut buf = format!("P3\n{} {}\n255\n", self.width, self.height);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                buf.push_str(&to_string(self.get(x, y), 1));
+            }
+        }
+        return buf;
+    }
+
+    // (u, v) in [0, 1], v = 0 is the top row
+    pub fn sample_nearest(&self, u: f64, v: f64) -> Color {
+        let x = ((u * self.width as f64) as i64).clamp(0, self.width as i64 - 1);
+        let y = ((v * self.height as f64) as i64).clamp(0, self.height as i64 - 1);
+        return self.get(x as u32, y as u32);
+    }
+}
+
+pub fn luminance(color: Color) -> f64 {
+    return 0.2126 * color.x + 0.7152 * color.y + 0.0722 * color.z;
+}
+
+// Loads .hdr or netpbm (.ppm, .pgm) images depending on the extension.
+pub fn load_image(path: &str) -> Result<Image, String> {
+    if path.ends_with(".hdr") {
+        return read_hdr(path);
+    }
+    return read_netpbm(path);
+}
@@ -0,0 +1,115 @@
+use std::f64::consts::PI;
+
+use crate::{
+    distribution::Distribution2D,
+    environment::{Environment, EnvironmentSample},
+    image::{luminance, Image},
+    math::degrees_to_radians,
+    sampler::random,
+    vec3::{Color, Vec3},
+};
+
+// Equirectangular (latitude-longitude) environment map.
+// The top row of the image is +y, the center column looks down -z.
+pub struct ImageEnvironment {
+    image: Image,
+    rotation: f64,
+    intensity: f64,
+    distribution: Distribution2D,
+}
+
+impl ImageEnvironment {
+    pub fn new(image: Image, rotation_degrees: f64, intensity: f64) -> ImageEnvironment {
+        let width = image.width as usize;
+        let height = image.height as usize;
+        let mut func = Vec::with_capacity(width * height);
+        for y in 0..height {
+            // rows near the poles cover less solid angle
+            let sin_theta = (PI * (y as f64 + 0.5) / height as f64).sin();
+            for x in 0..width {
+                func.push(luminance(image.get(x as u32, y as u32)) * sin_theta);
+            }
+        }
+        let distribution = Distribution2D::new(&func, width, height);
+        return ImageEnvironment {
+            image,
+            rotation: degrees_to_radians(rotation_degrees),
+            intensity,
+            distribution,
+        };
+    }
+
+    fn dir_to_uv(&self, dir: Vec3) -> (f64, f64) {
+        let unit_dir = dir.unit_vector();
+        let theta = unit_dir.y.clamp(-1.0, 1.0).acos();
+        let phi = f64::atan2(unit_dir.x, -unit_dir.z) - self.rotation;
+        let u = (phi / (2.0 * PI) + 0.5).rem_euclid(1.0);
+        return (u, theta / PI);
+    }
+
+    fn uv_to_dir(&self, u: f64, v: f64) -> Vec3 {
+        let theta = v * PI;
+        let phi = (u - 0.5) * 2.0 * PI + self.rotation;
+        let sin_theta = theta.sin();
+        return Vec3::new(sin_theta * phi.sin(), theta.cos(), -sin_theta * phi.cos());
+    }
+}
+
+impl Environment for ImageEnvironment {
+    fn emitted(&self, dir: Vec3) -> Color {
+        let (u, v) = self.dir_to_uv(dir);
+        return self.intensity * self.image.sample_nearest(u, v);
+    }
+
+    fn sample(&self) -> Option<EnvironmentSample> {
+        let ((u, v), map_pdf) = self.distribution.sample_continuous(random(), random());
+        let sin_theta = (v * PI).sin();
+        if map_pdf <= 0.0 || sin_theta <= 0.0 {
+            return None;
+        }
+        let dir = self.uv_to_dir(u, v);
+        return Some(EnvironmentSample {
+            dir,
+            radiance: self.intensity * self.image.sample_nearest(u, v),
+            pdf: map_pdf / (2.0 * PI * PI * sin_theta),
+        });
+    }
+
+    fn pdf(&self, dir: Vec3) -> f64 {
+        let (u, v) = self.dir_to_uv(dir);
+        let sin_theta = (v * PI).sin();
+        if sin_theta <= 0.0 {
+            return 0.0;
+        }
+        return self.distribution.pdf(u, v) / (2.0 * PI * PI * sin_theta);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPSILON: f64 = 0.000001;
+
+    #[test]
+    fn test_dir_uv_round_trip() {
+        let env = ImageEnvironment::new(Image::new(8, 4), 30.0, 1.0);
+        let dir = Vec3::new(0.3, -0.4, 0.5).unit_vector();
+        let (u, v) = env.dir_to_uv(dir);
+        let back = env.uv_to_dir(u, v);
+        assert!((back.x - dir.x).abs() <= EPSILON);
+        assert!((back.y - dir.y).abs() <= EPSILON);
+        assert!((back.z - dir.z).abs() <= EPSILON);
+    }
+
+    #[test]
+    fn test_samples_bright_texel() {
+        let mut image = Image::new(8, 4);
+        image.set(5, 1, Color::new(10.0, 10.0, 10.0));
+        let env = ImageEnvironment::new(image, 0.0, 2.0);
+
+        let s = env.sample().unwrap();
+        assert!((s.radiance.x - 20.0).abs() <= EPSILON);
+        assert!((env.pdf(s.dir) - s.pdf).abs() <= 1e-6 * s.pdf);
+    }
+}
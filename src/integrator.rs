@@ -0,0 +1,225 @@
+use std::f64::consts::PI;
+
+use crate::{
+    camera::Camera,
+    film::Film,
+    hittable::{HitRecord, Hittable},
+    ray::Ray,
+    scene::Scene,
+    spectrum::rgb_to_spectrum,
+    subsurface::Medium,
+    vec3::{Color, Point, Vec3},
+};
+
+const SHADOW_EPSILON: f64 = 0.0001;
+// interactions after which a walk through a medium is given up
+const MAX_WALK_STEPS: u32 = 256;
+
+// Estimates the light arriving along camera rays. Integrators that also trace paths
+// from the lights add what those find in other pixels to the film as splats, and
+// Markov chain integrators keep their chain from one call to the next.
+pub trait Integrator {
+    // Called before each pass over the image, which takes one sample per pixel.
+    fn prepare(&mut self, _scene: &Scene, _pass: u32) {}
+
+    fn li(&mut self, ray: &Ray, scene: &Scene, camera: &dyn Camera, film: &mut Film) -> Color;
+}
+
+// Unidirectional path tracing with next event estimation, see ray_color.
+pub struct PathIntegrator {
+    max_depth: u32,
+}
+
+impl PathIntegrator {
+    pub fn new(max_depth: u32) -> PathIntegrator {
+        return PathIntegrator { max_depth };
+    }
+}
+
+impl Integrator for PathIntegrator {
+    fn li(&mut self, ray: &Ray, scene: &Scene, _: &dyn Camera, _: &mut Film) -> Color {
+        return ray_color(ray, scene, self.max_depth);
+    }
+}
+
+// Radiance leaving a hit along the ray that integrators estimate by other means on top
+// of the path tracer, like the caustics of a photon map.
+pub type Gather<'a> = &'a dyn Fn(&Ray, &HitRecord) -> Color;
+
+pub fn ray_color(ray: &Ray, scene: &Scene, depth: u32) -> Color {
+    return trace(ray, scene, depth, None, &|_, _| Color::default());
+}
+
+pub fn ray_color_gathering(ray: &Ray, scene: &Scene, depth: u32, gather: Gather) -> Color {
+    return trace(ray, scene, depth, None, gather);
+}
+
+// bsdf_pdf is the density of the scattering that produced ray, None after specular bounces
+// and for camera rays, in which case escaping rays see the environment unweighted.
+fn trace(ray: &Ray, scene: &Scene, depth: u32, bsdf_pdf: Option<f64>, gather: Gather) -> Color {
+    if depth == 0 {
+        return Color::default();
+    }
+
+    let result = scene.world.hit(ray, 0.0001, f64::INFINITY);
+    if let Some(record) = result {
+        let mut color = gather(ray, &record);
+
+        for light in &scene.lights {
+            if let Some(sample) = light.sample_li(record.p) {
+                let f = record.material.eval(ray, &record, sample.dir);
+                if f.length_double() == 0.0 {
+                    continue;
+                }
+                // volumes let part of the light through
+                let shadow_ray = Ray::new(record.p, sample.dir);
+                let visibility = scene.world.transmittance(
+                    &shadow_ray,
+                    SHADOW_EPSILON,
+                    sample.distance - SHADOW_EPSILON,
+                );
+                if visibility > 0.0 {
+                    color += project(f, ray) * project(sample.li, ray) * visibility;
+                }
+            }
+        }
+
+        // explicit environment sampling, weighted against the BSDF sampling below
+        if let Some(sample) = scene.environment.sample() {
+            let f = record.material.eval(ray, &record, sample.dir);
+            if f.length_double() > 0.0 {
+                let shadow_ray = Ray::new(record.p, sample.dir);
+                let visibility =
+                    scene
+                        .world
+                        .transmittance(&shadow_ray, SHADOW_EPSILON, f64::INFINITY);
+                if visibility > 0.0 {
+                    let material_pdf = record.material.pdf(ray, &record, sample.dir);
+                    let weight = power_heuristic(sample.pdf, material_pdf);
+                    let radiance = project(f, ray) * project(sample.radiance, ray);
+                    color += radiance * (weight * visibility / sample.pdf);
+                }
+            }
+        }
+
+        let result = record.material.scatter(ray, &record);
+        if let Some(mut v) = result {
+            v.ray.wavelength = ray.wavelength;
+            let attenuation = project(v.attenuation, ray);
+            if let Some(medium) = record.material.medium() {
+                if Vec3::dot(v.ray.dir, record.normal) < 0.0 {
+                    let walk = random_walk(&v.ray, scene, medium, depth - 1, gather);
+                    color += attenuation * walk;
+                    return color;
+                }
+            }
+            let pdf = record.material.pdf(ray, &record, v.ray.dir);
+            let next_pdf = if pdf > 0.0 { Some(pdf) } else { None };
+            color += attenuation * trace(&v.ray, scene, depth - 1, next_pdf, gather);
+        }
+
+        return color;
+    }
+
+    let emitted = project(scene.environment.emitted(ray.dir), ray);
+    if let Some(pdf) = bsdf_pdf {
+        let environment_pdf = scene.environment.pdf(ray.dir);
+        return power_heuristic(pdf, environment_pdf) * emitted;
+    }
+    return emitted;
+}
+
+// Follows a ray that entered a closed object through its surface from one interaction
+// with the medium inside to the next, scattering isotropically, until it leaves again.
+pub fn random_walk(ray: &Ray, scene: &Scene, medium: &Medium, depth: u32, gather: Gather) -> Color {
+    let wavelength = ray.wavelength;
+    let mut ray = Ray::new(ray.org, ray.dir);
+    ray.wavelength = wavelength;
+    let mut throughput = Color::new(1.0, 1.0, 1.0);
+    let mut color = Color::default();
+    for _ in 0..MAX_WALK_STEPS {
+        // a surface that isn't closed lets the walk escape unlit
+        let record = match scene.world.hit(&ray, 0.0001, f64::INFINITY) {
+            Some(record) => record,
+            None => return color,
+        };
+        let distance = medium.sample_distance() / ray.dir.length();
+        if distance < record.t {
+            let weight = medium.scatter_weight(distance * ray.dir.length());
+            throughput = throughput * project(weight, &ray);
+            let p = ray.at(distance);
+            color += throughput * medium_direct_light(p, scene, medium, &ray);
+            ray = Ray::new(p, Vec3::random_unit_vector());
+            ray.wavelength = wavelength;
+            continue;
+        }
+
+        let weight = medium.pass_weight(record.t * ray.dir.length());
+        throughput = throughput * project(weight, &ray);
+        let mut scattered = match record.material.scatter(&ray, &record) {
+            Some(scattered) => scattered,
+            None => return color,
+        };
+        scattered.ray.wavelength = wavelength;
+        throughput = throughput * project(scattered.attenuation, &ray);
+        if Vec3::dot(scattered.ray.dir, record.normal) < 0.0 {
+            return color + throughput * trace(&scattered.ray, scene, depth, None, gather);
+        }
+        // reflected back inside
+        ray = scattered.ray;
+    }
+    return color;
+}
+
+// Light reaching a point inside a medium from the lights of the scene. Shadow rays go
+// straight out through the surface, weighted by its transmission but not bent by it.
+fn medium_direct_light(p: Point, scene: &Scene, medium: &Medium, ray: &Ray) -> Color {
+    let mut color = Color::default();
+    for light in &scene.lights {
+        let sample = match light.sample_li(p) {
+            Some(sample) => sample,
+            None => continue,
+        };
+        let exit = match scene
+            .world
+            .hit(&Ray::new(p, sample.dir), SHADOW_EPSILON, sample.distance)
+        {
+            Some(exit) if !exit.front_face => exit,
+            _ => continue,
+        };
+        let shadow_ray = Ray::new(exit.p, sample.dir);
+        let visibility = scene.world.transmittance(
+            &shadow_ray,
+            SHADOW_EPSILON,
+            sample.distance - exit.t - SHADOW_EPSILON,
+        );
+        if visibility > 0.0 {
+            let cos_theta = -Vec3::dot(sample.dir, exit.normal);
+            let transmission = medium.exit_transmission(cos_theta) * visibility;
+            let li = project(medium.transmittance(exit.t), ray) * project(sample.li, ray);
+            color += li * (transmission / (4.0 * PI));
+        }
+    }
+    return color;
+}
+
+// A color as seen by the path of ray: as it is in RGB, and in the spectral mode the
+// value of its spectrum at the wavelength of the path, in all three channels.
+pub fn project(color: Color, ray: &Ray) -> Color {
+    return match ray.wavelength {
+        Some(wavelength) => {
+            let value = rgb_to_spectrum(color, wavelength);
+            Color::new(value, value, value)
+        }
+        None => color,
+    };
+}
+
+pub fn power_heuristic(pdf: f64, other_pdf: f64) -> f64 {
+    let a = pdf * pdf;
+    let b = other_pdf * other_pdf;
+    if a + b == 0.0 {
+        return 0.0;
+    }
+    return a / (a + b);
+}
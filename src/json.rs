@@ -0,0 +1,235 @@
+use std::collections::HashMap;
+
+// Minimal JSON reader for the glTF importer.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(HashMap<String, Json>),
+}
+
+impl Json {
+    pub fn parse(text: &str) -> Result<Json, String> {
+        let data = text.as_bytes();
+        let mut pos = 0;
+        let value = parse_value(data, &mut pos)?;
+        skip_whitespace(data, &mut pos);
+        if pos != data.len() {
+            return Err(format!("json: unexpected data at byte {}", pos));
+        }
+        return Ok(value);
+    }
+
+    // member of an object, None for other values
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        return match self {
+            Json::Object(members) => members.get(key),
+            _ => None,
+        };
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        return match self {
+            Json::Number(v) => Some(*v),
+            _ => None,
+        };
+    }
+
+    pub fn as_usize(&self) -> Option<usize> {
+        return match self {
+            Json::Number(v) if *v >= 0.0 && v.fract() == 0.0 => Some(*v as usize),
+            _ => None,
+        };
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        return match self {
+            Json::String(v) => Some(v),
+            _ => None,
+        };
+    }
+
+    // elements of an array, empty for other values
+    pub fn elements(&self) -> &[Json] {
+        return match self {
+            Json::Array(v) => v,
+            _ => &[],
+        };
+    }
+}
+
+fn skip_whitespace(data: &[u8], pos: &mut usize) {
+    while *pos < data.len() && data[*pos].is_ascii_whitespace() {
+        *pos += 1;
+    }
+}
+
+fn expect(data: &[u8], pos: &mut usize, literal: &str) -> Result<(), String> {
+    if !data[*pos..].starts_with(literal.as_bytes()) {
+        return Err(format!("json: expected {} at byte {}", literal, pos));
+    }
+    *pos += literal.len();
+    return Ok(());
+}
+
+fn parse_value(data: &[u8], pos: &mut usize) -> Result<Json, String> {
+    skip_whitespace(data, pos);
+    let c = *data.get(*pos).ok_or("json: unexpected end")?;
+    return match c {
+        b'n' => expect(data, pos, "null").map(|_| Json::Null),
+        b't' => expect(data, pos, "true").map(|_| Json::Bool(true)),
+        b'f' => expect(data, pos, "false").map(|_| Json::Bool(false)),
+        b'"' => Ok(Json::String(parse_string(data, pos)?)),
+        b'[' => {
+            *pos += 1;
+            let mut elements = vec![];
+            skip_whitespace(data, pos);
+            if data.get(*pos) == Some(&b']') {
+                *pos += 1;
+                return Ok(Json::Array(elements));
+            }
+            loop {
+                elements.push(parse_value(data, pos)?);
+                skip_whitespace(data, pos);
+                match data.get(*pos) {
+                    Some(b',') => *pos += 1,
+                    Some(b']') => {
+                        *pos += 1;
+                        return Ok(Json::Array(elements));
+                    }
+                    _ => return Err(format!("json: expected , or ] at byte {}", pos)),
+                }
+            }
+        }
+        b'{' => {
+            *pos += 1;
+            let mut members = HashMap::new();
+            skip_whitespace(data, pos);
+            if data.get(*pos) == Some(&b'}') {
+                *pos += 1;
+                return Ok(Json::Object(members));
+            }
+            loop {
+                skip_whitespace(data, pos);
+                if data.get(*pos) != Some(&b'"') {
+                    return Err(format!("json: expected member name at byte {}", pos));
+                }
+                let key = parse_string(data, pos)?;
+                skip_whitespace(data, pos);
+                expect(data, pos, ":")?;
+                members.insert(key, parse_value(data, pos)?);
+                skip_whitespace(data, pos);
+                match data.get(*pos) {
+                    Some(b',') => *pos += 1,
+                    Some(b'}') => {
+                        *pos += 1;
+                        return Ok(Json::Object(members));
+                    }
+                    _ => return Err(format!("json: expected , or }} at byte {}", pos)),
+                }
+            }
+        }
+        _ => {
+            let start = *pos;
+            while *pos < data.len() && b"+-.eE0123456789".contains(&data[*pos]) {
+                *pos += 1;
+            }
+            let text = std::str::from_utf8(&data[start..*pos]).unwrap_or("");
+            let value = text
+                .parse::<f64>()
+                .map_err(|_| format!("json: invalid value at byte {}", start))?;
+            Ok(Json::Number(value))
+        }
+    };
+}
+
+fn parse_string(data: &[u8], pos: &mut usize) -> Result<String, String> {
+    // skip the opening quote
+    *pos += 1;
+    let mut bytes = vec![];
+    loop {
+        let c = *data.get(*pos).ok_or("json: unterminated string")?;
+        *pos += 1;
+        match c {
+            b'"' => break,
+            b'\\' => {
+                let escape = *data.get(*pos).ok_or("json: unterminated string")?;
+                *pos += 1;
+                let decoded = match escape {
+                    b'"' => '"',
+                    b'\\' => '\\',
+                    b'/' => '/',
+                    b'b' => '\u{8}',
+                    b'f' => '\u{c}',
+                    b'n' => '\n',
+                    b'r' => '\r',
+                    b't' => '\t',
+                    b'u' => {
+                        let mut code = parse_hex4(data, pos)?;
+                        // surrogate pairs encode characters outside the basic plane
+                        if (0xd800..0xdc00).contains(&code) && data[*pos..].starts_with(b"\\u") {
+                            *pos += 2;
+                            let low = parse_hex4(data, pos)?;
+                            code = 0x10000 + ((code - 0xd800) << 10) + (low.wrapping_sub(0xdc00));
+                        }
+                        char::from_u32(code).unwrap_or('\u{fffd}')
+                    }
+                    other => {
+                        return Err(format!("json: invalid escape \\{}", other as char));
+                    }
+                };
+                let mut buffer = [0; 4];
+                bytes.extend_from_slice(decoded.encode_utf8(&mut buffer).as_bytes());
+            }
+            _ => bytes.push(c),
+        }
+    }
+    return String::from_utf8(bytes).map_err(|e| format!("json: {}", e));
+}
+
+fn parse_hex4(data: &[u8], pos: &mut usize) -> Result<u32, String> {
+    let digits = data
+        .get(*pos..*pos + 4)
+        .ok_or("json: short unicode escape")?;
+    *pos += 4;
+    let text = std::str::from_utf8(digits).map_err(|e| format!("json: {}", e))?;
+    return u32::from_str_radix(text, 16).map_err(|e| format!("json: {}", e));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let json = Json::parse(
+            r#" {"asset": {"version": "2.0"}, "nodes": [{"name": "Cubeé\n", "translation": [1, -2.5, 3e2]}],
+                "empty": [], "none": {}, "flag": true, "nothing": null} "#,
+        )
+        .unwrap();
+        assert_eq!(
+            json.get("asset").unwrap().get("version").unwrap().as_str(),
+            Some("2.0")
+        );
+        let node = &json.get("nodes").unwrap().elements()[0];
+        assert_eq!(node.get("name").unwrap().as_str(), Some("Cube\u{e9}\n"));
+        let translation: Vec<f64> = node
+            .get("translation")
+            .unwrap()
+            .elements()
+            .iter()
+            .map(|v| v.as_f64().unwrap())
+            .collect();
+        assert_eq!(translation, vec![1.0, -2.5, 300.0]);
+        assert!(json.get("empty").unwrap().elements().is_empty());
+        assert_eq!(json.get("flag"), Some(&Json::Bool(true)));
+        assert_eq!(json.get("nothing"), Some(&Json::Null));
+
+        assert!(Json::parse("[1, 2").is_err());
+        assert!(Json::parse("{\"a\" 1}").is_err());
+        assert!(Json::parse("[1] x").is_err());
+    }
+}
@@ -0,0 +1,109 @@
+use crate::{
+    aabb::{axis_of, Aabb},
+    vec3::Point,
+};
+
+// Balanced kd-tree over points with a value each, for finding everything within a
+// radius. Built in place: each range of items is split at its median along the longest
+// axis of its bounds, and the median item is the node for that range.
+pub struct KdTree<T> {
+    items: Vec<(Point, T)>,
+    axes: Vec<usize>,
+}
+
+impl<T> KdTree<T> {
+    pub fn new(items: Vec<(Point, T)>) -> KdTree<T> {
+        let mut tree = KdTree {
+            axes: vec![0; items.len()],
+            items,
+        };
+        tree.build(0, tree.items.len());
+        return tree;
+    }
+
+    pub fn len(&self) -> usize {
+        return self.items.len();
+    }
+
+    fn build(&mut self, start: usize, end: usize) {
+        if end - start <= 1 {
+            return;
+        }
+        let points: Vec<Point> = self.items[start..end].iter().map(|item| item.0).collect();
+        let axis = Aabb::from_points(&points).longest_axis();
+        let middle = (start + end) / 2;
+        self.items[start..end].select_nth_unstable_by(middle - start, |a, b| {
+            return axis_of(a.0, axis).total_cmp(&axis_of(b.0, axis));
+        });
+        self.axes[middle] = axis;
+        self.build(start, middle);
+        self.build(middle + 1, end);
+    }
+
+    // Calls visit with every item within radius of p.
+    pub fn within(&self, p: Point, radius: f64, visit: &mut impl FnMut(&Point, &T)) {
+        self.search(0, self.items.len(), p, radius, visit);
+    }
+
+    fn search(
+        &self,
+        start: usize,
+        end: usize,
+        p: Point,
+        radius: f64,
+        visit: &mut impl FnMut(&Point, &T),
+    ) {
+        if start >= end {
+            return;
+        }
+        let middle = (start + end) / 2;
+        let (q, value) = &self.items[middle];
+        if (*q - p).length_double() <= radius * radius {
+            visit(q, value);
+        }
+        let axis = self.axes[middle];
+        let offset = axis_of(p, axis) - axis_of(*q, axis);
+        // the side of the split that p is on first, the other only if the radius crosses it
+        let (near, far) = if offset < 0.0 {
+            ((start, middle), (middle + 1, end))
+        } else {
+            ((middle + 1, end), (start, middle))
+        };
+        self.search(near.0, near.1, p, radius, visit);
+        if offset * offset <= radius * radius {
+            self.search(far.0, far.1, p, radius, visit);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+
+    #[test]
+    fn test_matches_linear_search() {
+        let mut rng = rand::thread_rng();
+        let mut random_point = || {
+            return Point::new(
+                rng.gen_range(-1.0..1.0),
+                rng.gen_range(-1.0..1.0),
+                rng.gen_range(-0.2..0.2),
+            );
+        };
+        let points: Vec<Point> = (0..500).map(|_| random_point()).collect();
+        let tree = KdTree::new(points.iter().copied().zip(0..).collect());
+        assert_eq!(tree.len(), 500);
+
+        for _ in 0..50 {
+            let p = random_point();
+            let mut found = vec![];
+            tree.within(p, 0.3, &mut |_, i: &usize| found.push(*i));
+            found.sort();
+            let expected: Vec<usize> = (0..points.len())
+                .filter(|i| (points[*i] - p).length() <= 0.3)
+                .collect();
+            assert_eq!(found, expected);
+        }
+    }
+}
@@ -1,26 +1,55 @@
+use std::f64::consts::PI;
+
 use crate::{
     hittable::HitRecord,
     material::{Material, Scattered},
     ray::Ray,
+    texture::{SolidColor, Texture},
     vec3::{Color, Vec3},
 };
 
 pub struct Lambertian {
-    albedo: Color,
+    albedo: Box<dyn Texture>,
 }
 
 impl Lambertian {
     pub fn new(albedo: Color) -> Lambertian {
+        return Lambertian::textured(Box::new(SolidColor::new(albedo)));
+    }
+
+    pub fn textured(albedo: Box<dyn Texture>) -> Lambertian {
         return Lambertian { albedo };
     }
 }
 
 impl Material for Lambertian {
     fn scatter(&self, _: &Ray, record: &HitRecord) -> Option<Scattered> {
-        let ray = Ray::new(record.p, Vec3::random_in_hemisphere(record.normal));
-        let attenuation = self.albedo;
+        // cosine weighted, so the attenuation is just the albedo
+        let mut dir = record.normal + Vec3::random_unit_vector();
+        if dir.length_double() < 1e-12 {
+            dir = record.normal;
+        }
+        let ray = Ray::new(record.p, dir);
+        let attenuation = self.albedo.value(record);
 
         let scattered = Scattered::new(ray, attenuation);
         return Some(scattered);
     }
+
+    fn eval(&self, _: &Ray, record: &HitRecord, dir: Vec3) -> Color {
+        let cosine = Vec3::dot(record.normal, dir.unit_vector());
+        if cosine <= 0.0 {
+            return Color::default();
+        }
+        return self.albedo.value(record) * (cosine / PI);
+    }
+
+    fn pdf(&self, _: &Ray, record: &HitRecord, dir: Vec3) -> f64 {
+        let cosine = Vec3::dot(record.normal, dir.unit_vector());
+        return f64::max(cosine, 0.0) / PI;
+    }
+
+    fn albedo(&self, record: &HitRecord) -> Color {
+        return self.albedo.value(record);
+    }
 }
@@ -0,0 +1,38 @@
+use crate::{
+    ray::Ray,
+    vec3::{Color, Point, Vec3},
+};
+
+pub struct LightSample {
+    // unit direction from the shading point towards the light
+    pub dir: Vec3,
+    pub distance: f64,
+    // incident radiance divided by the density of the sampled direction
+    pub li: Color,
+}
+
+// A ray leaving a light, for integrators that also trace paths from the lights.
+pub struct LightEmission {
+    pub ray: Ray,
+    // emitted intensity divided by the solid angle density of the direction
+    pub le: Color,
+    pub pdf: f64,
+}
+
+pub trait Light {
+    fn sample_li(&self, p: Point) -> Option<LightSample>;
+
+    // Lights at a position can start light paths, lights at infinity return None.
+    fn position(&self) -> Option<Point> {
+        return None;
+    }
+
+    fn sample_le(&self) -> Option<LightEmission> {
+        return None;
+    }
+
+    // Solid angle density with which sample_le picks dir.
+    fn pdf_le(&self, _dir: Vec3) -> f64 {
+        return 0.0;
+    }
+}
@@ -1,177 +1,234 @@
+#![allow(clippy::needless_return)]
+
+mod aabb;
+mod alpha_mask;
+mod aov;
+mod aperture;
+mod bdpt;
+mod bvh;
 mod camera;
+mod camera_path;
 mod color;
+mod csg;
+mod curve;
+mod denoiser;
+mod density_grid;
 mod dielectric;
+mod directional_light;
+mod distribution;
+mod environment;
+mod equirectangular_camera;
+mod farm;
+mod film;
+mod filter;
+mod fisheye_camera;
+mod gltf;
+mod hdr;
+mod heightfield;
 mod hittable;
 mod hittable_list;
+mod image;
+mod image_environment;
+mod integrator;
+mod json;
+mod kd_tree;
 mod lambertian;
+mod light;
 mod material;
+mod material_tag;
 mod math;
+mod mesh;
 mod metal;
+mod metropolis;
+mod netpbm;
+mod normal_map;
+mod onb;
+mod options;
+mod orthographic_camera;
+mod perspective_camera;
+mod pfm;
+mod photon_map;
+mod ply;
+mod point_light;
+mod polynomial;
+mod post_process;
+mod preetham_sky;
+mod quadric;
 mod ray;
+mod renderer;
+mod sampler;
+mod scene;
+mod scene_file;
+mod sdf;
+mod spectrum;
 mod sphere;
+mod spot_light;
+mod stl;
+mod subsurface;
+mod sun_light;
+mod texture;
+mod tilt_shift_camera;
+mod torus;
+mod triangle;
 mod vec3;
+mod volume;
 
 extern crate rand;
 
-use rand::Rng;
 use std::{fs::File, time::Instant};
 
-use crate::{color::to_string, vec3::Point};
+use aov::Aov;
 use camera::Camera;
-use dielectric::Dielectric;
-use hittable::Hittable;
-use hittable_list::HittableList;
-use lambertian::Lambertian;
-use metal::Metal;
-use ray::Ray;
-use sphere::Sphere;
+use denoiser::Denoiser;
+use farm::{work, Farm, Job};
+use options::Options;
+use pfm::write_pfm;
+use post_process::{apply_chain, PostProcess};
+use rand::Rng;
+use renderer::{render, RenderOutput};
+use scene_file::{read_scene, SceneDescription};
 use std::io::Write;
-use vec3::{Color, Vec3};
-
-const RANGE: i32 = 11;
-
-fn random_scene() -> HittableList {
-    let mut world = HittableList::default();
-
-    // ground
-    world.add(Box::new(Sphere::new(
-        Vec3::new(0.0, -1000.0, 0.0),
-        1000.0,
-        Box::new(Lambertian::new(Color::new(0.5, 0.5, 0.5))),
-    )));
-
-    for a in -RANGE..RANGE {
-        for b in -RANGE..RANGE {
-            let mut rng = rand::thread_rng();
-            let choose_mat = rng.gen_range(0.0..1.0);
-            let center = Point::new(
-                (a as f64) + 0.9 * rng.gen_range(0.0..1.0),
-                0.2,
-                (b as f64) * 0.9 * rng.gen_range(0.0..1.0),
-            );
-
-            if (center - Vec3::new(4.0, 0.2, 0.0)).length() > 0.9 {
-                if choose_mat < 0.8 {
-                    // diffuse
-                    let albedo = Color::random(0.0, 1.0) * Color::random(0.0, 1.0);
-                    world.add(Box::new(Sphere::new(
-                        center,
-                        0.2,
-                        Box::new(Lambertian::new(albedo)),
-                    )));
-                } else if choose_mat < 0.95 {
-                    // metal
-                    let albedo = Color::random(0.5, 1.0);
-                    let fizz = rng.gen_range(0.0..0.5);
-                    world.add(Box::new(Sphere::new(
-                        center,
-                        0.2,
-                        Box::new(Metal::new(albedo, fizz)),
-                    )));
-                } else {
-                    // glass
-                    world.add(Box::new(Sphere::new(
-                        center,
-                        0.2,
-                        Box::new(Dielectric::new(1.5)),
-                    )));
-                }
-            }
+
+fn write_images(
+    output: &RenderOutput,
+    post: &[Box<dyn PostProcess>],
+    path: &str,
+    options: &Options,
+) {
+    let mut image = output.film.to_image();
+    if options.denoise {
+        let aovs = output.aovs.as_ref().unwrap();
+        image = Denoiser::default().denoise(
+            &image,
+            &aovs.to_image(Aov::Normal),
+            &aovs.to_image(Aov::Albedo),
+        );
+    }
+    let image = apply_chain(post, image);
+
+    let mut file = File::create(path).expect("Failed to create file");
+
+    file.write_all(image.to_ppm().as_bytes())
+        .expect("Failed to write data to file");
+
+    file.flush().expect("Failed to flush data to file");
+
+    if let Some(aovs) = &output.aovs {
+        for aov in &options.aovs {
+            let aov_path = Options::aov_output(path, *aov);
+            write_pfm(&aov_path, &aovs.to_image(*aov)).expect("Failed to write aov");
         }
     }
-    world.add(Box::new(Sphere::new(
-        Vec3::new(0.0, 1.0, 0.0),
-        1.0,
-        Box::new(Dielectric::new(1.5)),
-    )));
-    world.add(Box::new(Sphere::new(
-        Vec3::new(-4.0, 1.0, 0.0),
-        1.0,
-        Box::new(Lambertian::new(Color::new(0.4, 0.2, 0.1))),
-    )));
-    world.add(Box::new(Sphere::new(
-        Vec3::new(4.0, 1.0, 0.0),
-        1.0,
-        Box::new(Metal::new(Color::new(0.7, 0.6, 0.5), 0.0)),
-    )));
-
-    return world;
 }
 
-fn ray_color(ray: &Ray, world: &HittableList, depth: u32) -> Color {
-    if depth <= 0 {
-        return Color::default();
-    }
-    let result = world.hit(ray, 0.0001, std::f64::INFINITY);
-    if let Some(record) = result {
-        let result = record.material.scatter(&ray, &record);
-        if let Some(v) = result {
-            return v.attenuation * ray_color(&v.ray, &world, depth - 1);
+// Renders on the farm when there is one, else here.
+fn render_image(
+    farm: Option<&Farm>,
+    description: &SceneDescription,
+    camera: &dyn Camera,
+    frame: Option<u32>,
+    options: &Options,
+    height: u32,
+) -> RenderOutput {
+    let farm = match farm {
+        Some(v) => v,
+        None => return render(&description.scene, camera, options, height),
+    };
+    match farm.render(frame, options, height) {
+        Ok(film) => return RenderOutput { film, aovs: None },
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
         }
-
-        return Color::default();
     }
-
-    let white: Color = Color::new(1.0, 1.0, 1.0);
-    let blue: Color = Color::new(0.5, 0.7, 1.0);
-    let unit_dir = ray.dir.unit_vector();
-    let t = 0.5 * (unit_dir.y + 1.0);
-    return (1.0 - t) * white + t * blue;
 }
 
 fn main() {
     let start = Instant::now();
-    let mut rng = rand::thread_rng();
-
-    const ASPECT_RATIO: f64 = 16.0 / 9.0;
-    const WIDTH: u32 = 200;
-    const HEIGHT: u32 = ((WIDTH as f64) / ASPECT_RATIO) as u32;
-    const SAMPLE_PER_PIXCEL: u32 = 100;
-    const MAX_DEPTH: u32 = 50;
-
-    let lookfrom = Point::new(13.0, 2.0, 3.0);
-    let lookat = Point::new(0.0, 0.0, 0.0);
-    let vup = Vec3::new(0.0, 1.0, 0.0);
-    let dist_to_focus = 10.0;
-    let aperture = 0.1;
-    let camera = Camera::new(
-        lookfrom,
-        lookat,
-        vup,
-        20.0,
-        ASPECT_RATIO,
-        aperture,
-        dist_to_focus,
-    );
-
-    let world = random_scene();
-
-    let mut buf: String;
-
-    buf = format!("P3\n{} {}\n255\n", WIDTH, HEIGHT);
-
-    for i in (0..HEIGHT).rev() {
-        eprint!("\rremain {:3}", i);
-        std::io::stdout().flush().unwrap();
-        for j in 0..WIDTH {
-            let mut pixcel_sum_color = Color::default();
-            for _ in 0..SAMPLE_PER_PIXCEL {
-                let u = (f64::from(j) + rng.gen::<f64>()) / f64::from(WIDTH - 1);
-                let v = (f64::from(i) + rng.gen::<f64>()) / f64::from(HEIGHT - 1);
-                let ray = camera.get_ray(u, v);
-                pixcel_sum_color += ray_color(&ray, &world, MAX_DEPTH);
-            }
-            let cs = to_string(pixcel_sum_color, SAMPLE_PER_PIXCEL);
-            buf.push_str(&cs);
-        }
-    }
 
-    let mut file = File::create("out.ppm").expect("Failed to create file");
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let options = match Options::parse(args.iter().cloned()) {
+        Ok(v) => v,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
 
-    file.write_all(buf.as_bytes())
-        .expect("Failed to write data to file");
+    if let Some(address) = &options.worker {
+        if let Err(e) = work(address) {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
 
-    file.flush().expect("Failed to flush data to file");
+    // workers make the procedural geometry of the scene from the same seed
+    let seed = rand::thread_rng().gen();
+    let description = read_scene(options.scene.as_deref()).and_then(|(text, base_dir)| {
+        Ok((options.make_scene(&text, &base_dir, seed)?, text, base_dir))
+    });
+    let (description, text, base_dir) = match description {
+        Ok(v) => v,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let farm = match &options.farm {
+        Some(address) => {
+            let farm = Job::new(args, text, &base_dir, seed)
+                .and_then(|job| Farm::start(address, options.workers, job));
+            match farm {
+                Ok(v) => {
+                    eprintln!("farm listening on {}", v.address());
+                    Some(v)
+                }
+                Err(e) => {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        None => None,
+    };
+
+    let height = ((options.width as f64) / description.aspect_ratio) as u32;
+
+    // a frame range or an animated camera renders a numbered image sequence
+    let frames = options
+        .frames
+        .or_else(|| description.animation.as_ref().map(|a| a.frame_range()));
+    match frames {
+        None => {
+            let camera = &*description.camera;
+            let output = render_image(farm.as_ref(), &description, camera, None, &options, height);
+            write_images(&output, &description.post, &options.output, &options);
+        }
+        Some((first, last)) => {
+            for frame in first..=last {
+                let camera = match description.camera_at(frame as f64) {
+                    Ok(v) => v,
+                    Err(e) => {
+                        eprintln!("{}", e);
+                        std::process::exit(1);
+                    }
+                };
+                let output = render_image(
+                    farm.as_ref(),
+                    &description,
+                    &*camera,
+                    Some(frame),
+                    &options,
+                    height,
+                );
+                let path = options.frame_output(frame);
+                write_images(&output, &description.post, &path, &options);
+                eprintln!("\rframe {} written to {}", frame, path);
+            }
+        }
+    }
 
     let end = Instant::now();
     eprintln!("\rElapsed time: {:?}", end - start);
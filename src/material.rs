@@ -1,4 +1,9 @@
-use crate::{hittable::HitRecord, ray::Ray, vec3::Color};
+use crate::{
+    hittable::HitRecord,
+    ray::Ray,
+    subsurface::Medium,
+    vec3::{Color, Vec3},
+};
 
 pub struct Scattered {
     pub ray: Ray,
@@ -13,4 +18,32 @@ impl Scattered {
 
 pub trait Material {
     fn scatter(&self, ray_in: &Ray, record: &HitRecord) -> Option<Scattered>;
+
+    // BSDF times the cosine term for an explicitly chosen direction.
+    // Specular materials can't be evaluated this way and keep the default.
+    fn eval(&self, _ray_in: &Ray, _record: &HitRecord, _dir: Vec3) -> Color {
+        return Color::default();
+    }
+
+    // Reflectance color used for the albedo pass and as denoiser guide.
+    fn albedo(&self, _record: &HitRecord) -> Color {
+        return Color::new(1.0, 1.0, 1.0);
+    }
+
+    // Solid angle density with which scatter() picks dir, 0 for specular materials.
+    fn pdf(&self, _ray_in: &Ray, _record: &HitRecord, _dir: Vec3) -> f64 {
+        return 0.0;
+    }
+
+    // Medium filling closed objects of this material, entered by rays that scatter
+    // through the surface.
+    fn medium(&self) -> Option<&Medium> {
+        return None;
+    }
+
+    // False for phase functions, which scatter at points inside volumes where no
+    // cosine applies.
+    fn has_surface(&self) -> bool {
+        return true;
+    }
 }
@@ -0,0 +1,70 @@
+use crate::{
+    aabb::Aabb,
+    hittable::{HitInterval, HitRecord, Hittable},
+    ray::Ray,
+};
+
+// Gives the hits of an object the id the scene assigned to its material, for the material
+// id pass. Every object builds its own instance of a material it shares with others, so
+// the instance alone can't tell which material it is.
+pub struct MaterialTag {
+    object: Box<dyn Hittable>,
+    id: usize,
+}
+
+impl MaterialTag {
+    pub fn new(object: Box<dyn Hittable>, id: usize) -> MaterialTag {
+        return MaterialTag { object, id };
+    }
+}
+
+impl Hittable for MaterialTag {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        let mut record = self.object.hit(ray, t_min, t_max)?;
+        record.material_id = self.id;
+        return Some(record);
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        return self.object.bounding_box();
+    }
+
+    fn transmittance(&self, ray: &Ray, t_min: f64, t_max: f64) -> f64 {
+        return self.object.transmittance(ray, t_min, t_max);
+    }
+
+    fn intervals(&self, ray: &Ray) -> Vec<HitInterval<'_>> {
+        let mut intervals = self.object.intervals(ray);
+        for interval in &mut intervals {
+            interval.enter.material_id = self.id;
+            interval.exit.material_id = self.id;
+        }
+        return intervals;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        lambertian::Lambertian,
+        sphere::Sphere,
+        vec3::{Color, Point, Vec3},
+    };
+
+    #[test]
+    fn test_tag() {
+        let material = Box::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        let sphere = Box::new(Sphere::new(Point::default(), 1.0, material));
+        let tagged = MaterialTag::new(sphere, 3);
+        let ray = Ray::new(Point::new(0.0, 0.0, 3.0), Vec3::new(0.0, 0.0, -1.0));
+        assert_eq!(
+            tagged.hit(&ray, 0.001, f64::INFINITY).unwrap().material_id,
+            3
+        );
+        let intervals = tagged.intervals(&ray);
+        assert_eq!(intervals.len(), 1);
+        assert_eq!(intervals[0].enter.material_id, 3);
+        assert_eq!(intervals[0].exit.material_id, 3);
+    }
+}
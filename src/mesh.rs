@@ -0,0 +1,472 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::{
+    bvh::Bvh,
+    hittable::Hittable,
+    material::Material,
+    triangle::Triangle,
+    vec3::{Color, Point, Vec3},
+};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Subdivision {
+    Loop,
+    CatmullClark,
+}
+
+impl Subdivision {
+    pub fn parse(name: &str) -> Result<Subdivision, String> {
+        return match name {
+            "loop" => Ok(Subdivision::Loop),
+            "catmull_clark" => Ok(Subdivision::CatmullClark),
+            other => Err(format!("unknown subdivision scheme {}", other)),
+        };
+    }
+}
+
+// Polygon mesh with counter-clockwise faces seen from outside. Normals, uvs and colors
+// are per vertex when present.
+#[derive(Clone)]
+pub struct Mesh {
+    pub positions: Vec<Point>,
+    pub faces: Vec<Vec<usize>>,
+    pub normals: Option<Vec<Vec3>>,
+    pub uvs: Option<Vec<(f64, f64)>>,
+    pub colors: Option<Vec<Color>>,
+}
+
+// edge key with the smaller index first
+fn edge(a: usize, b: usize) -> (usize, usize) {
+    return (a.min(b), a.max(b));
+}
+
+impl Mesh {
+    pub fn new(positions: Vec<Point>, faces: Vec<Vec<usize>>) -> Mesh {
+        return Mesh {
+            positions,
+            faces,
+            normals: None,
+            uvs: None,
+            colors: None,
+        };
+    }
+
+    // The built in shapes fit the box from -1 to 1.
+    pub fn cube() -> Mesh {
+        let mut positions = vec![];
+        for i in 0..8 {
+            let c = |bit: usize| if i & bit != 0 { 1.0 } else { -1.0 };
+            positions.push(Point::new(c(1), c(2), c(4)));
+        }
+        let faces = vec![
+            vec![0, 4, 6, 2],
+            vec![1, 3, 7, 5],
+            vec![0, 1, 5, 4],
+            vec![2, 6, 7, 3],
+            vec![0, 2, 3, 1],
+            vec![4, 5, 7, 6],
+        ];
+        return Mesh::new(positions, faces);
+    }
+
+    pub fn tetrahedron() -> Mesh {
+        let positions = vec![
+            Point::new(1.0, 1.0, 1.0),
+            Point::new(-1.0, -1.0, 1.0),
+            Point::new(-1.0, 1.0, -1.0),
+            Point::new(1.0, -1.0, -1.0),
+        ];
+        let faces = vec![vec![0, 1, 3], vec![0, 2, 1], vec![0, 3, 2], vec![1, 2, 3]];
+        return Mesh::new(positions, faces);
+    }
+
+    pub fn octahedron() -> Mesh {
+        let positions = vec![
+            Point::new(1.0, 0.0, 0.0),
+            Point::new(-1.0, 0.0, 0.0),
+            Point::new(0.0, 1.0, 0.0),
+            Point::new(0.0, -1.0, 0.0),
+            Point::new(0.0, 0.0, 1.0),
+            Point::new(0.0, 0.0, -1.0),
+        ];
+        let faces = vec![
+            vec![0, 2, 4],
+            vec![2, 1, 4],
+            vec![1, 3, 4],
+            vec![3, 0, 4],
+            vec![2, 0, 5],
+            vec![1, 2, 5],
+            vec![3, 1, 5],
+            vec![0, 3, 5],
+        ];
+        return Mesh::new(positions, faces);
+    }
+
+    pub fn icosahedron() -> Mesh {
+        let phi = (1.0 + 5.0_f64.sqrt()) / 2.0;
+        let (a, b) = (1.0 / phi, 1.0);
+        let positions = vec![
+            Point::new(-a, b, 0.0),
+            Point::new(a, b, 0.0),
+            Point::new(-a, -b, 0.0),
+            Point::new(a, -b, 0.0),
+            Point::new(0.0, -a, b),
+            Point::new(0.0, a, b),
+            Point::new(0.0, -a, -b),
+            Point::new(0.0, a, -b),
+            Point::new(b, 0.0, -a),
+            Point::new(b, 0.0, a),
+            Point::new(-b, 0.0, -a),
+            Point::new(-b, 0.0, a),
+        ];
+        let faces = [
+            [0, 11, 5],
+            [0, 5, 1],
+            [0, 1, 7],
+            [0, 7, 10],
+            [0, 10, 11],
+            [1, 5, 9],
+            [5, 11, 4],
+            [11, 10, 2],
+            [10, 7, 6],
+            [7, 1, 8],
+            [3, 9, 4],
+            [3, 4, 2],
+            [3, 2, 6],
+            [3, 6, 8],
+            [3, 8, 9],
+            [4, 9, 5],
+            [2, 4, 11],
+            [6, 2, 10],
+            [8, 6, 7],
+            [9, 8, 1],
+        ];
+        return Mesh::new(positions, faces.iter().map(|f| f.to_vec()).collect());
+    }
+
+    pub fn transform(&mut self, scale: f64, offset: Vec3) {
+        for p in &mut self.positions {
+            *p = scale * *p + offset;
+        }
+    }
+
+    // fan triangulation of every face
+    pub fn triangles(&self) -> Vec<[usize; 3]> {
+        let mut triangles = vec![];
+        for face in &self.faces {
+            for i in 1..face.len().saturating_sub(1) {
+                triangles.push([face[0], face[i], face[i + 1]]);
+            }
+        }
+        return triangles;
+    }
+
+    // Area weighted vertex normals for smooth shading.
+    pub fn compute_normals(&mut self) {
+        let mut normals = vec![Vec3::default(); self.positions.len()];
+        for [a, b, c] in self.triangles() {
+            let p = &self.positions;
+            let n = Vec3::cross(p[b] - p[a], p[c] - p[a]);
+            normals[a] += n;
+            normals[b] += n;
+            normals[c] += n;
+        }
+        self.normals = Some(
+            normals
+                .iter()
+                .map(|n| {
+                    if n.length_double() > 0.0 {
+                        return n.unit_vector();
+                    }
+                    return *n;
+                })
+                .collect(),
+        );
+    }
+
+    pub fn subdivide(&self, scheme: Subdivision, levels: u32) -> Mesh {
+        let mut mesh = self.clone();
+        for _ in 0..levels {
+            mesh = match scheme {
+                Subdivision::Loop => mesh.loop_step(),
+                Subdivision::CatmullClark => mesh.catmull_clark_step(),
+            };
+        }
+        return mesh;
+    }
+
+    // One level of Loop subdivision, polygons are triangulated first. Normals, uvs and
+    // colors are dropped.
+    fn loop_step(&self) -> Mesh {
+        let triangles = self.triangles();
+        let n = self.positions.len();
+
+        // vertices opposite each edge, one for boundary edges
+        let mut edges: Vec<(usize, usize)> = vec![];
+        let mut edge_index: HashMap<(usize, usize), usize> = HashMap::new();
+        let mut opposite: Vec<Vec<usize>> = vec![];
+        for [a, b, c] in &triangles {
+            for (u, v, w) in [(*a, *b, *c), (*b, *c, *a), (*c, *a, *b)] {
+                let key = edge(u, v);
+                let i = *edge_index.entry(key).or_insert_with(|| {
+                    edges.push(key);
+                    opposite.push(vec![]);
+                    return edges.len() - 1;
+                });
+                opposite[i].push(w);
+            }
+        }
+
+        let mut neighbors: Vec<Vec<usize>> = vec![vec![]; n];
+        let mut boundary_neighbors: Vec<Vec<usize>> = vec![vec![]; n];
+        for (i, (a, b)) in edges.iter().enumerate() {
+            neighbors[*a].push(*b);
+            neighbors[*b].push(*a);
+            if opposite[i].len() == 1 {
+                boundary_neighbors[*a].push(*b);
+                boundary_neighbors[*b].push(*a);
+            }
+        }
+
+        let p = &self.positions;
+        let mut positions = Vec::with_capacity(n + edges.len());
+        for v in 0..n {
+            let position = if boundary_neighbors[v].len() == 2 {
+                let [b0, b1] = [boundary_neighbors[v][0], boundary_neighbors[v][1]];
+                0.75 * p[v] + 0.125 * (p[b0] + p[b1])
+            } else if !boundary_neighbors[v].is_empty() || neighbors[v].is_empty() {
+                // corners of non-manifold boundaries stay put
+                p[v]
+            } else {
+                let k = neighbors[v].len() as f64;
+                let beta = if neighbors[v].len() == 3 {
+                    3.0 / 16.0
+                } else {
+                    3.0 / (8.0 * k)
+                };
+                let mut sum = Vec3::default();
+                for u in &neighbors[v] {
+                    sum += p[*u];
+                }
+                (1.0 - k * beta) * p[v] + beta * sum
+            };
+            positions.push(position);
+        }
+        for (i, (a, b)) in edges.iter().enumerate() {
+            let position = if opposite[i].len() == 2 {
+                0.375 * (p[*a] + p[*b]) + 0.125 * (p[opposite[i][0]] + p[opposite[i][1]])
+            } else {
+                0.5 * (p[*a] + p[*b])
+            };
+            positions.push(position);
+        }
+
+        let mid = |u: usize, v: usize| n + edge_index[&edge(u, v)];
+        let mut faces = Vec::with_capacity(4 * triangles.len());
+        for [a, b, c] in triangles {
+            let (ab, bc, ca) = (mid(a, b), mid(b, c), mid(c, a));
+            faces.push(vec![a, ab, ca]);
+            faces.push(vec![b, bc, ab]);
+            faces.push(vec![c, ca, bc]);
+            faces.push(vec![ab, bc, ca]);
+        }
+        return Mesh::new(positions, faces);
+    }
+
+    // One level of Catmull-Clark subdivision, every face becomes quads. Normals, uvs and
+    // colors are dropped.
+    fn catmull_clark_step(&self) -> Mesh {
+        let n = self.positions.len();
+        let face_count = self.faces.len();
+        let p = &self.positions;
+
+        let face_points: Vec<Point> = self
+            .faces
+            .iter()
+            .map(|f| {
+                let mut sum = Vec3::default();
+                for v in f {
+                    sum += p[*v];
+                }
+                return sum / f.len() as f64;
+            })
+            .collect();
+
+        let mut edges: Vec<(usize, usize)> = vec![];
+        let mut edge_index: HashMap<(usize, usize), usize> = HashMap::new();
+        let mut edge_faces: Vec<Vec<usize>> = vec![];
+        for (f, face) in self.faces.iter().enumerate() {
+            for i in 0..face.len() {
+                let key = edge(face[i], face[(i + 1) % face.len()]);
+                let e = *edge_index.entry(key).or_insert_with(|| {
+                    edges.push(key);
+                    edge_faces.push(vec![]);
+                    return edges.len() - 1;
+                });
+                edge_faces[e].push(f);
+            }
+        }
+
+        let mut vertex_faces: Vec<Vec<usize>> = vec![vec![]; n];
+        for (f, face) in self.faces.iter().enumerate() {
+            for v in face {
+                vertex_faces[*v].push(f);
+            }
+        }
+        let mut vertex_edges: Vec<Vec<usize>> = vec![vec![]; n];
+        for (e, (a, b)) in edges.iter().enumerate() {
+            vertex_edges[*a].push(e);
+            vertex_edges[*b].push(e);
+        }
+
+        let mut positions = Vec::with_capacity(n + face_count + edges.len());
+        for v in 0..n {
+            let boundary: Vec<usize> = vertex_edges[v]
+                .iter()
+                .filter(|e| edge_faces[**e].len() == 1)
+                .map(|e| {
+                    if edges[*e].0 == v {
+                        edges[*e].1
+                    } else {
+                        edges[*e].0
+                    }
+                })
+                .collect();
+            let position = if boundary.len() == 2 {
+                (6.0 * p[v] + p[boundary[0]] + p[boundary[1]]) / 8.0
+            } else if !boundary.is_empty() || vertex_faces[v].is_empty() {
+                p[v]
+            } else {
+                let k = vertex_edges[v].len() as f64;
+                let mut f = Vec3::default();
+                for face in &vertex_faces[v] {
+                    f += face_points[*face];
+                }
+                f /= vertex_faces[v].len() as f64;
+                let mut r = Vec3::default();
+                for e in &vertex_edges[v] {
+                    let (a, b) = edges[*e];
+                    r += 0.5 * (p[a] + p[b]);
+                }
+                r /= k;
+                (f + 2.0 * r + (k - 3.0) * p[v]) / k
+            };
+            positions.push(position);
+        }
+        positions.extend(face_points.iter().cloned());
+        for (e, (a, b)) in edges.iter().enumerate() {
+            let position = if edge_faces[e].len() == 2 {
+                let (f0, f1) = (edge_faces[e][0], edge_faces[e][1]);
+                (p[*a] + p[*b] + face_points[f0] + face_points[f1]) / 4.0
+            } else {
+                0.5 * (p[*a] + p[*b])
+            };
+            positions.push(position);
+        }
+
+        let edge_point = |u: usize, v: usize| n + face_count + edge_index[&edge(u, v)];
+        let mut faces = vec![];
+        for (f, face) in self.faces.iter().enumerate() {
+            let m = face.len();
+            for i in 0..m {
+                let prev = face[(i + m - 1) % m];
+                let next = face[(i + 1) % m];
+                faces.push(vec![
+                    face[i],
+                    edge_point(face[i], next),
+                    n + f,
+                    edge_point(prev, face[i]),
+                ]);
+            }
+        }
+        return Mesh::new(positions, faces);
+    }
+
+    // Triangles of the mesh in a bvh, so large meshes stay fast to intersect.
+    pub fn into_bvh(self, material: Box<dyn Material>) -> Bvh {
+        let material: Rc<dyn Material> = Rc::from(material);
+        let mut objects: Vec<Box<dyn Hittable>> = vec![];
+        for [a, b, c] in self.triangles() {
+            let p = [self.positions[a], self.positions[b], self.positions[c]];
+            let n = self.normals.as_ref().map(|n| [n[a], n[b], n[c]]);
+            let uv = match &self.uvs {
+                Some(uv) => [uv[a], uv[b], uv[c]],
+                None => [(0.0, 0.0); 3],
+            };
+            let mut triangle = Triangle::new(p, n, uv, material.clone());
+            triangle.set_colors(
+                self.colors
+                    .as_ref()
+                    .map(|colors| [colors[a], colors[b], colors[c]]),
+            );
+            objects.push(Box::new(triangle));
+        }
+        return Bvh::new(objects);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lambertian::Lambertian, ray::Ray, vec3::Color};
+
+    const EPSILON: f64 = 0.000001;
+
+    #[test]
+    fn test_loop_subdivision() {
+        let mesh = Mesh::tetrahedron().subdivide(Subdivision::Loop, 1);
+        // every edge gains a vertex and every triangle splits in four
+        assert_eq!(mesh.positions.len(), 4 + 6);
+        assert_eq!(mesh.faces.len(), 16);
+
+        // the limit surface of a regular mesh shrinks towards the inside
+        let mesh = Mesh::icosahedron().subdivide(Subdivision::Loop, 2);
+        assert_eq!(mesh.faces.len(), 20 * 16);
+        for p in &mesh.positions {
+            assert!(p.length() < 1.0_f64.hypot(1.0 / 1.618034) + EPSILON);
+        }
+    }
+
+    #[test]
+    fn test_catmull_clark_subdivision() {
+        let mesh = Mesh::cube().subdivide(Subdivision::CatmullClark, 1);
+        assert_eq!(mesh.positions.len(), 8 + 6 + 12);
+        assert_eq!(mesh.faces.len(), 24);
+        assert!(mesh.faces.iter().all(|f| f.len() == 4));
+
+        // the cube rounds off: the corners move in, the face centers stay
+        assert!((mesh.positions[0].length() - 5.0 / 9.0 * 3.0_f64.sqrt()).abs() <= EPSILON);
+        assert!((mesh.positions[8].length() - 1.0).abs() <= EPSILON);
+    }
+
+    #[test]
+    fn test_boundary_is_kept() {
+        // an open quad strip, boundary vertices move along the boundary only
+        let positions = vec![
+            Point::new(0.0, 0.0, 0.0),
+            Point::new(1.0, 0.0, 0.0),
+            Point::new(2.0, 0.0, 0.0),
+            Point::new(0.0, 1.0, 0.0),
+            Point::new(1.0, 1.0, 0.0),
+            Point::new(2.0, 1.0, 0.0),
+        ];
+        let mesh = Mesh::new(positions, vec![vec![0, 1, 4, 3], vec![1, 2, 5, 4]]);
+        let subdivided = mesh.subdivide(Subdivision::CatmullClark, 2);
+        assert!(subdivided.positions.iter().all(|p| p.z.abs() <= EPSILON));
+        assert!((subdivided.positions[1].y).abs() <= EPSILON);
+    }
+
+    #[test]
+    fn test_mesh_hit() {
+        let mut mesh = Mesh::cube().subdivide(Subdivision::CatmullClark, 2);
+        mesh.compute_normals();
+        let bvh = mesh.into_bvh(Box::new(Lambertian::new(Color::new(0.5, 0.5, 0.5))));
+        let ray = Ray::new(Point::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        let record = bvh.hit(&ray, 0.001, f64::INFINITY).unwrap();
+        // the rounded cube bulges less than the unit sphere around its face centers
+        assert!(record.t > 4.0 && record.t < 4.2);
+        assert!((record.normal.z - 1.0).abs() <= 0.001);
+        assert!(record.front_face);
+    }
+}
@@ -37,4 +37,8 @@ impl Material for Metal {
             return None;
         }
     }
+
+    fn albedo(&self, _: &HitRecord) -> Color {
+        return self.albedo;
+    }
 }
@@ -0,0 +1,337 @@
+use std::{cell::RefCell, f64::consts::PI, rc::Rc};
+
+use rand::Rng;
+
+use crate::{
+    camera::Camera,
+    film::Film,
+    image::luminance,
+    integrator::{ray_color, Integrator},
+    ray::Ray,
+    sampler::{random, with_sampler, Sampler},
+    scene::Scene,
+    spectrum::{sample_wavelength, spectral_to_rgb},
+    vec3::Color,
+};
+
+// standard deviation of a small step of a primary sample
+const SIGMA: f64 = 0.01;
+// chance of a mutation that draws all numbers of the path anew
+const LARGE_STEP_PROBABILITY: f64 = 0.3;
+// largest f64 below 1
+const ONE_MINUS_EPSILON: f64 = 1.0 - f64::EPSILON / 2.0;
+
+#[derive(Clone, Copy)]
+struct PrimarySample {
+    value: f64,
+    // iteration of the last change, and both as they were before the current mutation
+    modified: u64,
+    backup: f64,
+    modified_backup: u64,
+}
+
+// The random numbers a path was made of. Mutations are applied lazily: a number only
+// takes the steps it missed when the path asks for it, so longer paths can grow the
+// vector and shorter ones leave the rest alone.
+struct PrimarySampler {
+    samples: Vec<PrimarySample>,
+    next: usize,
+    iteration: u64,
+    large_step: bool,
+    last_large_step: u64,
+}
+
+impl PrimarySampler {
+    fn new() -> PrimarySampler {
+        return PrimarySampler {
+            samples: vec![],
+            next: 0,
+            iteration: 0,
+            large_step: true,
+            last_large_step: 0,
+        };
+    }
+
+    fn start_iteration(&mut self) {
+        self.iteration += 1;
+        self.large_step = rand::thread_rng().gen::<f64>() < LARGE_STEP_PROBABILITY;
+        self.next = 0;
+    }
+
+    fn accept(&mut self) {
+        if self.large_step {
+            self.last_large_step = self.iteration;
+        }
+    }
+
+    fn reject(&mut self) {
+        for sample in &mut self.samples {
+            if sample.modified == self.iteration {
+                sample.value = sample.backup;
+                sample.modified = sample.modified_backup;
+            }
+        }
+        self.iteration -= 1;
+    }
+}
+
+impl Sampler for PrimarySampler {
+    fn next(&mut self) -> f64 {
+        let mut rng = rand::thread_rng();
+        if self.next == self.samples.len() {
+            // a number no path asked for yet is as good as drawn at the last large step
+            self.samples.push(PrimarySample {
+                value: rng.gen(),
+                modified: self.last_large_step,
+                backup: 0.0,
+                modified_backup: 0,
+            });
+        }
+        let sample = &mut self.samples[self.next];
+        self.next += 1;
+
+        if sample.modified < self.last_large_step {
+            sample.value = rng.gen();
+            sample.modified = self.last_large_step;
+        }
+        sample.backup = sample.value;
+        sample.modified_backup = sample.modified;
+        if self.large_step {
+            sample.value = rng.gen();
+        } else {
+            // all the small steps since the last change at once, wrapped around [0, 1)
+            let steps = (self.iteration - sample.modified) as f64;
+            let radius = (-2.0 * (1.0 - rng.gen::<f64>()).ln()).sqrt();
+            let normal = radius * (2.0 * PI * rng.gen::<f64>()).cos();
+            sample.value += normal * SIGMA * steps.sqrt();
+            sample.value -= sample.value.floor();
+            sample.value = sample.value.min(ONE_MINUS_EPSILON);
+        }
+        sample.modified = self.iteration;
+        return sample.value;
+    }
+}
+
+// A path through the image: where it lands on the film, what it carries and the
+// importance the chain distributes paths by.
+struct PathSample {
+    x: f64,
+    y: f64,
+    color: Color,
+    importance: f64,
+}
+
+struct Chain {
+    sampler: Rc<RefCell<PrimarySampler>>,
+    current: PathSample,
+}
+
+// Primary sample space Metropolis light transport after Kelemen et al. A Markov chain
+// walks over the random numbers the path tracer draws, the film position among them,
+// visiting paths in proportion to their luminance, so the chain stays in the few paths
+// that carry the light through hard indirect lighting once it found them. Each camera
+// sample of the renderer is one mutation, which adds to the film as splats.
+pub struct MetropolisIntegrator {
+    max_depth: u32,
+    // paths traced to start the chain and to find the overall brightness
+    bootstrap: usize,
+    spectral: bool,
+    // average luminance of the paths, which the chain can't see
+    brightness: f64,
+    chain: Option<Chain>,
+    bootstrapped: bool,
+}
+
+impl MetropolisIntegrator {
+    pub fn new(max_depth: u32, bootstrap: usize, spectral: bool) -> MetropolisIntegrator {
+        return MetropolisIntegrator {
+            max_depth,
+            bootstrap,
+            spectral,
+            brightness: 0.0,
+            chain: None,
+            bootstrapped: false,
+        };
+    }
+
+    // a path from the numbers the installed sampler draws, the first two being the film
+    fn path(&self, scene: &Scene, camera: &dyn Camera, film: &Film) -> PathSample {
+        let width = f64::from(film.width);
+        let height = f64::from(film.height);
+        let x = random() * width;
+        let y = random() * height;
+        let mut ray = camera.get_ray(x / width, 1.0 - y / height);
+        let color = if self.spectral {
+            let wavelength = sample_wavelength();
+            ray.wavelength = Some(wavelength);
+            spectral_to_rgb(ray_color(&ray, scene, self.max_depth).x, wavelength)
+        } else {
+            ray_color(&ray, scene, self.max_depth)
+        };
+        let importance = luminance(color);
+        // also catches NaN
+        let importance = if importance > 0.0 { importance } else { 0.0 };
+        return PathSample {
+            x,
+            y,
+            color,
+            importance,
+        };
+    }
+
+    // Starts the chain from one of the bootstrap paths, picked in proportion to its
+    // importance, and estimates the brightness from all of them.
+    fn start(&mut self, scene: &Scene, camera: &dyn Camera, film: &Film) {
+        let mut rng = rand::thread_rng();
+        let mut total = 0.0;
+        for _ in 0..self.bootstrap {
+            let sampler = Rc::new(RefCell::new(PrimarySampler::new()));
+            let path = with_sampler(sampler.clone(), || self.path(scene, camera, film));
+            total += path.importance;
+            if path.importance > 0.0 && rng.gen::<f64>() * total < path.importance {
+                self.chain = Some(Chain {
+                    sampler,
+                    current: path,
+                });
+            }
+        }
+        self.brightness = total / self.bootstrap.max(1) as f64;
+        self.bootstrapped = true;
+    }
+}
+
+impl Integrator for MetropolisIntegrator {
+    fn li(&mut self, _: &Ray, scene: &Scene, camera: &dyn Camera, film: &mut Film) -> Color {
+        // the bootstrap needs the camera, so it runs with the first sample
+        if !self.bootstrapped {
+            self.start(scene, camera, film);
+        }
+        // a black image has nothing to start the chain from
+        let mut chain = match self.chain.take() {
+            Some(chain) => chain,
+            None => return Color::default(),
+        };
+
+        chain.sampler.borrow_mut().start_iteration();
+        let proposed = with_sampler(chain.sampler.clone(), || self.path(scene, camera, film));
+        let accept = f64::min(1.0, proposed.importance / chain.current.importance);
+
+        // both paths add what they are expected to, whichever the chain moves to
+        let current = &chain.current;
+        if accept > 0.0 {
+            let weight = accept * self.brightness / proposed.importance;
+            film.add_splat(proposed.x, proposed.y, proposed.color * weight);
+        }
+        if accept < 1.0 {
+            let weight = (1.0 - accept) * self.brightness / current.importance;
+            film.add_splat(current.x, current.y, current.color * weight);
+        }
+
+        if rand::thread_rng().gen::<f64>() < accept {
+            chain.current = proposed;
+            chain.sampler.borrow_mut().accept();
+        } else {
+            chain.sampler.borrow_mut().reject();
+        }
+        self.chain = Some(chain);
+        return Color::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        environment::ConstantEnvironment,
+        hittable_list::HittableList,
+        lambertian::Lambertian,
+        options::Options,
+        perspective_camera::PerspectiveCamera,
+        point_light::PointLight,
+        renderer::render,
+        sphere::Sphere,
+        vec3::{Point, Vec3},
+    };
+
+    const EPSILON: f64 = 0.000001;
+
+    #[test]
+    fn test_primary_sampler() {
+        let mut sampler = PrimarySampler::new();
+        let first: Vec<f64> = (0..4).map(|_| sampler.next()).collect();
+
+        // a rejected small step puts the numbers back
+        sampler.start_iteration();
+        sampler.large_step = false;
+        let small: Vec<f64> = (0..4).map(|_| sampler.next()).collect();
+        for (a, b) in first.iter().zip(&small) {
+            let distance = f64::min((a - b).abs(), 1.0 - (a - b).abs());
+            assert!(distance < 10.0 * SIGMA, "{} {}", a, b);
+        }
+        sampler.reject();
+        sampler.next = 0;
+        for a in &first {
+            assert!((sampler.samples[sampler.next].value - a).abs() <= EPSILON);
+            sampler.next += 1;
+        }
+
+        // numbers drawn past the end of the path are new ones
+        sampler.start_iteration();
+        let longer: Vec<f64> = (0..6).map(|_| sampler.next()).collect();
+        assert_eq!(sampler.samples.len(), 6);
+        assert!(longer.iter().all(|x| (0.0..1.0).contains(x)));
+    }
+
+    #[test]
+    fn test_matches_path_tracing() {
+        // a sphere on the ground lit by a point light
+        let mut world = HittableList::default();
+        world.add(Box::new(Sphere::new(
+            Point::new(0.0, -100.0, 0.0),
+            100.0,
+            Box::new(Lambertian::new(Color::new(0.6, 0.6, 0.6))),
+        )));
+        world.add(Box::new(Sphere::new(
+            Point::new(0.0, 0.5, 0.0),
+            0.5,
+            Box::new(Lambertian::new(Color::new(0.8, 0.3, 0.2))),
+        )));
+        let environment = Box::new(ConstantEnvironment::new(Color::new(0.1, 0.1, 0.2)));
+        let mut scene = Scene::new(world, environment);
+        scene.add_light(Box::new(PointLight::new(
+            Point::new(-2.0, 2.0, 1.0),
+            Color::new(60.0, 60.0, 60.0),
+        )));
+        let camera = PerspectiveCamera::new(
+            Point::new(0.0, 1.0, 4.0),
+            Point::new(0.0, 0.5, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            50.0,
+            1.5,
+            0.0,
+            4.0,
+        );
+
+        // average of the left and right halves of the image with each integrator
+        let mut halves = vec![];
+        for integrator in ["path", "mlt"] {
+            let mut options = Options::default();
+            options.width = 24;
+            options.samples_per_pixel = 64;
+            options.max_depth = 6;
+            options.bootstrap = 20000;
+            options.integrator = String::from(integrator);
+            let film = render(&scene, &camera, &options, 16).film;
+            let mut sums = [Color::default(); 2];
+            for y in 0..film.height {
+                for x in 0..film.width {
+                    sums[(2 * x / film.width) as usize] += film.pixel(x, y);
+                }
+            }
+            halves.push(sums.map(|sum| sum / f64::from(film.width * film.height / 2)));
+        }
+        for (a, b) in halves[0].iter().zip(&halves[1]) {
+            assert!((*a - *b).length() < 0.05 * a.length(), "{:?} {:?}", a, b);
+        }
+    }
+}
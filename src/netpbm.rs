@@ -0,0 +1,126 @@
+use crate::{image::Image, vec3::Color};
+
+// Reader for plain and binary netpbm images (P2, P3, P5, P6) with 8 or 16 bit samples.
+// Values are returned as given in the file, scaled to [0, 1] without decoding the gamma.
+pub fn read_netpbm(path: &str) -> Result<Image, String> {
+    let data = std::fs::read(path).map_err(|e| format!("{}: {}", path, e))?;
+    return parse_netpbm(&data).map_err(|e| format!("{}: {}", path, e));
+}
+
+pub fn parse_netpbm(data: &[u8]) -> Result<Image, String> {
+    let mut pos = 0;
+    let magic = next_token(data, &mut pos).ok_or("missing magic number")?;
+    let (channels, binary) = match magic.as_str() {
+        "P2" => (1, false),
+        "P3" => (3, false),
+        "P5" => (1, true),
+        "P6" => (3, true),
+        _ => return Err(format!("unsupported netpbm type {}", magic)),
+    };
+    let width = next_number(data, &mut pos)?;
+    let height = next_number(data, &mut pos)?;
+    let max_value = next_number(data, &mut pos)?;
+    if max_value == 0 || max_value > 65535 {
+        return Err(format!("invalid max value {}", max_value));
+    }
+    // a single whitespace byte separates the header from binary data
+    pos += 1;
+
+    let count = (width * height * channels) as usize;
+    let mut values = Vec::with_capacity(count);
+    if binary {
+        let bytes_per_sample = if max_value < 256 { 1 } else { 2 };
+        let end = pos + count * bytes_per_sample;
+        if end > data.len() {
+            return Err(String::from("unexpected end of pixel data"));
+        }
+        for chunk in data[pos..end].chunks(bytes_per_sample) {
+            let value = if bytes_per_sample == 1 {
+                chunk[0] as u32
+            } else {
+                ((chunk[0] as u32) << 8) | chunk[1] as u32
+            };
+            values.push(value);
+        }
+    } else {
+        pos -= 1;
+        for _ in 0..count {
+            values.push(next_number(data, &mut pos)?);
+        }
+    }
+
+    let scale = 1.0 / max_value as f64;
+    let mut image = Image::new(width, height);
+    for y in 0..height {
+        for x in 0..width {
+            let i = ((y * width + x) * channels) as usize;
+            let color = if channels == 1 {
+                let v = values[i] as f64 * scale;
+                Color::new(v, v, v)
+            } else {
+                Color::new(
+                    values[i] as f64 * scale,
+                    values[i + 1] as f64 * scale,
+                    values[i + 2] as f64 * scale,
+                )
+            };
+            image.set(x, y, color);
+        }
+    }
+    return Ok(image);
+}
+
+fn next_token(data: &[u8], pos: &mut usize) -> Option<String> {
+    // skip whitespace and comments
+    loop {
+        while *pos < data.len() && data[*pos].is_ascii_whitespace() {
+            *pos += 1;
+        }
+        if *pos < data.len() && data[*pos] == b'#' {
+            while *pos < data.len() && data[*pos] != b'\n' {
+                *pos += 1;
+            }
+            continue;
+        }
+        break;
+    }
+    let start = *pos;
+    while *pos < data.len() && !data[*pos].is_ascii_whitespace() {
+        *pos += 1;
+    }
+    if start == *pos {
+        return None;
+    }
+    return Some(String::from_utf8_lossy(&data[start..*pos]).to_string());
+}
+
+fn next_number(data: &[u8], pos: &mut usize) -> Result<u32, String> {
+    let token = next_token(data, pos).ok_or("unexpected end of file")?;
+    return token
+        .parse()
+        .map_err(|_| format!("invalid number {}", token));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPSILON: f64 = 0.000001;
+
+    #[test]
+    fn test_parse_plain() {
+        let image = parse_netpbm(b"P3\n# comment\n2 1\n255\n255 0 0  0 51 255\n").unwrap();
+        assert_eq!(image.width, 2);
+        assert!((image.get(0, 0).x - 1.0).abs() <= EPSILON);
+        assert!((image.get(1, 0).y - 0.2).abs() <= EPSILON);
+    }
+
+    #[test]
+    fn test_parse_binary_gray() {
+        let mut data = b"P5 2 2 255\n".to_vec();
+        data.extend_from_slice(&[0, 255, 51, 102]);
+        let image = parse_netpbm(&data).unwrap();
+        assert!((image.get(1, 0).z - 1.0).abs() <= EPSILON);
+        assert!((image.get(1, 1).x - 0.4).abs() <= EPSILON);
+    }
+}
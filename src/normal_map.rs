@@ -0,0 +1,227 @@
+use crate::{
+    hittable::HitRecord,
+    image::{luminance, Image},
+    material::{Material, Scattered},
+    onb::Onb,
+    ray::Ray,
+    subsurface::Medium,
+    texture::sample_image,
+    vec3::{Color, Vec3},
+};
+
+// Surface detail that tilts the shading normal without changing the geometry.
+pub trait ShadingNormal {
+    // the perturbed normal, on the same side as record.normal
+    fn normal(&self, record: &HitRecord) -> Vec3;
+}
+
+// Tangent and bitangent in the plane of the normal, following dpdu and dpdv where the
+// object has a parameterization and an arbitrary frame elsewhere. Lengths are kept so
+// bump heights are in world units.
+fn tangent_frame(record: &HitRecord) -> (Vec3, Vec3) {
+    let n = record.normal;
+    let dpdu = record.dpdu - Vec3::dot(record.dpdu, n) * n;
+    let dpdv = record.dpdv - Vec3::dot(record.dpdv, n) * n;
+    if dpdu.length_double() < 1e-18 || dpdv.length_double() < 1e-18 {
+        let onb = Onb::new(n);
+        return (onb.u, onb.v);
+    }
+    return (dpdu, dpdv);
+}
+
+// Tangent space normal map with x along u, y along v and z out of the surface, stored
+// as colors in [0, 1]. strength scales the tilt.
+pub struct NormalMap {
+    map: Image,
+    strength: f64,
+}
+
+impl NormalMap {
+    pub fn new(map: Image, strength: f64) -> NormalMap {
+        return NormalMap { map, strength };
+    }
+}
+
+impl ShadingNormal for NormalMap {
+    fn normal(&self, record: &HitRecord) -> Vec3 {
+        let n = record.normal;
+        let (dpdu, dpdv) = tangent_frame(record);
+        let t = dpdu.unit_vector();
+        let mut b = Vec3::cross(n, t);
+        if Vec3::dot(b, dpdv) < 0.0 {
+            b = -1.0 * b;
+        }
+        let m = 2.0 * sample_image(&self.map, record.u, record.v) - Vec3::new(1.0, 1.0, 1.0);
+        let perturbed = self.strength * (m.x * t + m.y * b) + m.z * n;
+        if Vec3::dot(perturbed, n) <= 0.0 {
+            return n;
+        }
+        return perturbed.unit_vector();
+    }
+}
+
+// Height map whose luminance displaces the surface by up to height along the normal.
+pub struct BumpMap {
+    map: Image,
+    height: f64,
+}
+
+impl BumpMap {
+    pub fn new(map: Image, height: f64) -> BumpMap {
+        return BumpMap { map, height };
+    }
+
+    fn displacement(&self, u: f64, v: f64) -> f64 {
+        return self.height * luminance(sample_image(&self.map, u, v));
+    }
+}
+
+impl ShadingNormal for BumpMap {
+    fn normal(&self, record: &HitRecord) -> Vec3 {
+        let n = record.normal;
+        let (dpdu, dpdv) = tangent_frame(record);
+        // central differences one texel apart
+        let du = 1.0 / self.map.width as f64;
+        let dv = 1.0 / self.map.height as f64;
+        let (u, v) = (record.u, record.v);
+        let dddu = (self.displacement(u + du, v) - self.displacement(u - du, v)) / (2.0 * du);
+        let dddv = (self.displacement(u, v + dv) - self.displacement(u, v - dv)) / (2.0 * dv);
+
+        // normal of the displaced surface p + d n, ignoring the change of n itself
+        let perturbed = Vec3::cross(dpdu + dddu * n, dpdv + dddv * n);
+        let flat = Vec3::cross(dpdu, dpdv);
+        let perturbed = if Vec3::dot(flat, n) < 0.0 {
+            -1.0 * perturbed
+        } else {
+            perturbed
+        };
+        if Vec3::dot(perturbed, n) <= 0.0 {
+            return n;
+        }
+        return perturbed.unit_vector();
+    }
+}
+
+// Wraps a material and shades it with a perturbed normal.
+pub struct Perturbed {
+    material: Box<dyn Material>,
+    shading: Box<dyn ShadingNormal>,
+}
+
+impl Perturbed {
+    pub fn new(material: Box<dyn Material>, shading: Box<dyn ShadingNormal>) -> Perturbed {
+        return Perturbed { material, shading };
+    }
+
+    fn shade<'a>(&self, record: &HitRecord<'a>) -> HitRecord<'a> {
+        let mut shaded = *record;
+        shaded.normal = self.shading.normal(record);
+        return shaded;
+    }
+}
+
+impl Material for Perturbed {
+    fn scatter(&self, ray_in: &Ray, record: &HitRecord) -> Option<Scattered> {
+        return self.material.scatter(ray_in, &self.shade(record));
+    }
+
+    fn eval(&self, ray_in: &Ray, record: &HitRecord, dir: Vec3) -> Color {
+        return self.material.eval(ray_in, &self.shade(record), dir);
+    }
+
+    fn albedo(&self, record: &HitRecord) -> Color {
+        return self.material.albedo(&self.shade(record));
+    }
+
+    fn pdf(&self, ray_in: &Ray, record: &HitRecord, dir: Vec3) -> f64 {
+        return self.material.pdf(ray_in, &self.shade(record), dir);
+    }
+
+    fn medium(&self) -> Option<&Medium> {
+        return self.material.medium();
+    }
+
+    fn has_surface(&self) -> bool {
+        return self.material.has_surface();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lambertian::Lambertian, vec3::Point};
+
+    const EPSILON: f64 = 0.000001;
+
+    fn record(material: &dyn Material) -> HitRecord<'_> {
+        // a surface facing +z with u along x and v along y
+        let mut record = HitRecord::new(
+            Point::default(),
+            Vec3::new(0.0, 0.0, 1.0),
+            1.0,
+            true,
+            material,
+        );
+        record.u = 0.3;
+        record.v = 0.6;
+        record.dpdu = Vec3::new(2.0, 0.0, 0.0);
+        record.dpdv = Vec3::new(0.0, 2.0, 0.0);
+        return record;
+    }
+
+    fn filled(color: Color) -> Image {
+        let mut image = Image::new(4, 4);
+        for y in 0..4 {
+            for x in 0..4 {
+                image.set(x, y, color);
+            }
+        }
+        return image;
+    }
+
+    #[test]
+    fn test_normal_map() {
+        let material = Lambertian::new(Color::new(0.5, 0.5, 0.5));
+        let record = record(&material);
+
+        let flat = NormalMap::new(filled(Color::new(0.5, 0.5, 1.0)), 1.0);
+        assert!((flat.normal(&record) - record.normal).length() <= EPSILON);
+
+        // leaning 45 degrees towards +v
+        let tilted = NormalMap::new(filled(Color::new(0.5, 1.0, 1.0)), 1.0);
+        let n = tilted.normal(&record);
+        assert!((n - Vec3::new(0.0, 1.0, 1.0).unit_vector()).length() <= EPSILON);
+
+        // the back side of the surface leans the same way along v
+        let mut back = record;
+        back.normal = Vec3::new(0.0, 0.0, -1.0);
+        let n = tilted.normal(&back);
+        assert!((n - Vec3::new(0.0, 1.0, -1.0).unit_vector()).length() <= EPSILON);
+    }
+
+    #[test]
+    fn test_bump_map() {
+        // brightness rises along x, so the surface climbs with u
+        let mut ramp = Image::new(8, 1);
+        for x in 0..8 {
+            let value = x as f64 / 8.0;
+            ramp.set(x, 0, Color::new(value, value, value));
+        }
+        let bump = BumpMap::new(ramp, 0.5);
+        let material = Lambertian::new(Color::new(0.5, 0.5, 0.5));
+        let record = record(&material);
+
+        // height rises by 0.5 per unit of u, over 2 units of distance
+        let n = bump.normal(&record);
+        assert!((n - Vec3::new(-0.25, 0.0, 1.0).unit_vector()).length() <= EPSILON);
+
+        let gray = Box::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        let perturbed = Perturbed::new(gray, Box::new(bump));
+        let ray = Ray::new(Point::new(0.0, 0.0, 1.0), Vec3::new(0.0, 0.0, -1.0));
+        let lit = perturbed.eval(&ray, &record, Vec3::new(0.0, 0.0, 1.0));
+        let flat = record
+            .material
+            .eval(&ray, &record, Vec3::new(0.0, 0.0, 1.0));
+        assert!(lit.x < flat.x);
+    }
+}
@@ -0,0 +1,26 @@
+use crate::vec3::Vec3;
+
+// Orthonormal basis whose w axis is the given direction.
+pub struct Onb {
+    pub u: Vec3,
+    pub v: Vec3,
+    pub w: Vec3,
+}
+
+impl Onb {
+    pub fn new(w: Vec3) -> Onb {
+        let w = w.unit_vector();
+        let a = if w.x.abs() > 0.9 {
+            Vec3::new(0.0, 1.0, 0.0)
+        } else {
+            Vec3::new(1.0, 0.0, 0.0)
+        };
+        let v = Vec3::cross(w, a).unit_vector();
+        let u = Vec3::cross(w, v);
+        return Onb { u, v, w };
+    }
+
+    pub fn local(&self, a: Vec3) -> Vec3 {
+        return a.x * self.u + a.y * self.v + a.z * self.w;
+    }
+}
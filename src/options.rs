@@ -0,0 +1,456 @@
+use std::{cell::RefCell, path::Path, rc::Rc};
+
+use crate::{
+    aov::Aov,
+    bdpt::BidirectionalIntegrator,
+    directional_light::DirectionalLight,
+    environment::{ConstantEnvironment, Environment, GradientEnvironment},
+    filter::{BoxFilter, Filter, GaussianFilter, MitchellFilter, TentFilter},
+    hdr::read_hdr,
+    image_environment::ImageEnvironment,
+    integrator::{Integrator, PathIntegrator},
+    light::Light,
+    math::degrees_to_radians,
+    metropolis::MetropolisIntegrator,
+    photon_map::PhotonIntegrator,
+    point_light::PointLight,
+    preetham_sky::PreethamSky,
+    sampler::{with_sampler, SeededSampler},
+    scene_file::{is_gltf, parse_gltf_scene, parse_scene, SceneDescription},
+    spot_light::SpotLight,
+    vec3::{Color, Vec3},
+};
+
+pub struct Options {
+    pub width: u32,
+    pub samples_per_pixel: u32,
+    pub max_depth: u32,
+    pub filter: String,
+    pub filter_radius: Option<f64>,
+    pub output: String,
+    pub scene: Option<String>,
+    pub environment: Option<String>,
+    pub environment_intensity: f64,
+    pub environment_rotation: f64,
+    pub sun_elevation: f64,
+    pub sun_azimuth: f64,
+    pub sun_intensity: f64,
+    pub turbidity: f64,
+    pub lights: Vec<String>,
+    pub frames: Option<(u32, u32)>,
+    pub aovs: Vec<Aov>,
+    pub denoise: bool,
+    // trace one wavelength per path instead of RGB
+    pub spectral: bool,
+    pub integrator: String,
+    // photons traced for each pass of the photon mapping integrators
+    pub photons: usize,
+    pub photon_radius: f64,
+    // paths traced to start the Metropolis integrator
+    pub bootstrap: usize,
+    // address a render farm coordinator listens on, and the workers it starts itself
+    pub farm: Option<String>,
+    pub workers: usize,
+    pub tile_size: u32,
+    // address of the coordinator to render tiles for
+    pub worker: Option<String>,
+}
+
+impl Options {
+    pub fn default() -> Options {
+        return Options {
+            width: 200,
+            samples_per_pixel: 100,
+            max_depth: 50,
+            filter: String::from("box"),
+            filter_radius: None,
+            output: String::from("out.ppm"),
+            scene: None,
+            environment: None,
+            environment_intensity: 1.0,
+            environment_rotation: 0.0,
+            sun_elevation: 45.0,
+            sun_azimuth: 0.0,
+            sun_intensity: 5.0,
+            turbidity: 3.0,
+            lights: vec![],
+            frames: None,
+            aovs: vec![],
+            denoise: false,
+            spectral: false,
+            integrator: String::from("path"),
+            photons: 100000,
+            photon_radius: 0.05,
+            bootstrap: 100000,
+            farm: None,
+            workers: 0,
+            tile_size: 32,
+            worker: None,
+        };
+    }
+
+    pub fn parse(args: impl Iterator<Item = String>) -> Result<Options, String> {
+        let mut options = Options::default();
+        let mut args = args;
+
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .ok_or_else(|| format!("missing value for {}", arg))
+            };
+            match arg.as_str() {
+                "--width" => options.width = parse_number(&value()?)?,
+                "--samples" => options.samples_per_pixel = parse_number(&value()?)?,
+                "--depth" => options.max_depth = parse_number(&value()?)?,
+                "--filter" => options.filter = value()?,
+                "--filter-radius" => options.filter_radius = Some(parse_number(&value()?)?),
+                "--output" | "-o" => options.output = value()?,
+                "--scene" => options.scene = Some(value()?),
+                "--env" => options.environment = Some(value()?),
+                "--env-intensity" => options.environment_intensity = parse_number(&value()?)?,
+                "--env-rotation" => options.environment_rotation = parse_number(&value()?)?,
+                "--sun-elevation" => options.sun_elevation = parse_number(&value()?)?,
+                "--sun-azimuth" => options.sun_azimuth = parse_number(&value()?)?,
+                "--sun-intensity" => options.sun_intensity = parse_number(&value()?)?,
+                "--turbidity" => options.turbidity = parse_number(&value()?)?,
+                "--light" => options.lights.push(value()?),
+                "--denoise" => options.denoise = true,
+                "--spectral" => options.spectral = true,
+                "--integrator" => options.integrator = value()?,
+                "--photons" => options.photons = parse_number(&value()?)?,
+                "--photon-radius" => options.photon_radius = parse_number(&value()?)?,
+                "--bootstrap" => options.bootstrap = parse_number(&value()?)?,
+                "--farm" => options.farm = Some(value()?),
+                "--workers" => options.workers = parse_number(&value()?)?,
+                "--tile-size" => options.tile_size = parse_number(&value()?)?,
+                "--worker" => options.worker = Some(value()?),
+                "--aov" => options.aovs = parse_aovs(&value()?)?,
+                "--frames" => options.frames = Some(parse_frame_range(&value()?)?),
+                _ => return Err(format!("unknown option {}", arg)),
+            }
+        }
+
+        // workers only send back the image
+        if options.farm.is_some() && (!options.aovs.is_empty() || options.denoise) {
+            return Err(String::from("--aov and --denoise don't work with --farm"));
+        }
+        // and each tile would trace its own photons or start its own chain
+        let tiled = ["photon", "ppm", "mlt"];
+        if options.farm.is_some() && tiled.contains(&options.integrator.as_str()) {
+            return Err(format!(
+                "--integrator {} doesn't work with --farm",
+                options.integrator
+            ));
+        }
+        if options.tile_size == 0 {
+            return Err(String::from("--tile-size must be positive"));
+        }
+        options.make_filter()?;
+        options.make_integrator()?;
+        options.make_lights()?;
+        return Ok(options);
+    }
+
+    pub fn make_filter(&self) -> Result<Box<dyn Filter>, String> {
+        if self
+            .filter_radius
+            .is_some_and(|radius| radius <= 0.0 || radius.is_nan())
+        {
+            return Err(String::from("--filter-radius must be positive"));
+        }
+        let filter: Box<dyn Filter> = match self.filter.as_str() {
+            "box" => Box::new(BoxFilter::new(self.filter_radius.unwrap_or(0.5))),
+            "tent" => Box::new(TentFilter::new(self.filter_radius.unwrap_or(1.0))),
+            "gaussian" => Box::new(GaussianFilter::new(self.filter_radius.unwrap_or(1.5), 2.0)),
+            "mitchell" => Box::new(MitchellFilter::new(
+                self.filter_radius.unwrap_or(2.0),
+                1.0 / 3.0,
+                1.0 / 3.0,
+            )),
+            _ => return Err(format!("unknown filter {}", self.filter)),
+        };
+        return Ok(filter);
+    }
+
+    pub fn make_integrator(&self) -> Result<Box<dyn Integrator>, String> {
+        let integrator: Box<dyn Integrator> = match self.integrator.as_str() {
+            "path" => Box::new(PathIntegrator::new(self.max_depth)),
+            "bdpt" => Box::new(BidirectionalIntegrator::new(self.max_depth)),
+            "photon" | "ppm" => Box::new(PhotonIntegrator::new(
+                self.max_depth,
+                self.photons,
+                self.photon_radius,
+                self.integrator == "ppm",
+                self.spectral,
+            )),
+            "mlt" => Box::new(MetropolisIntegrator::new(
+                self.max_depth,
+                self.bootstrap,
+                self.spectral,
+            )),
+            _ => return Err(format!("unknown integrator {}", self.integrator)),
+        };
+        return Ok(integrator);
+    }
+
+    // The scene in text with the environment and lights given on the command line, which
+    // override those of the scene. Procedural geometry is drawn from seed, so the same seed
+    // makes the same scene in every process.
+    pub fn make_scene(
+        &self,
+        text: &str,
+        base_dir: &Path,
+        seed: u64,
+    ) -> Result<SceneDescription, String> {
+        let sampler = Rc::new(RefCell::new(SeededSampler::new(seed)));
+        let description = match &self.scene {
+            Some(path) if is_gltf(path) => parse_gltf_scene(Path::new(path)),
+            _ => with_sampler(sampler, || parse_scene(text, base_dir)),
+        };
+        let mut description = description.map_err(|e| match &self.scene {
+            Some(path) => format!("{}: {}", path, e),
+            None => e,
+        })?;
+        if let Some(environment) = self.make_environment()? {
+            description.scene.environment = environment;
+        }
+        for light in self.make_lights()? {
+            description.scene.add_light(light);
+        }
+        return Ok(description);
+    }
+
+    // gradient, sky, a constant "r,g,b" color or the path of an equirectangular .hdr image.
+    // None keeps the environment of the scene.
+    pub fn make_environment(&self) -> Result<Option<Box<dyn Environment>>, String> {
+        let environment = match &self.environment {
+            Some(v) => v,
+            None => return Ok(None),
+        };
+        let intensity = self.environment_intensity;
+        if environment == "sky" {
+            return Ok(Some(Box::new(self.make_sky())));
+        }
+        if environment == "gradient" {
+            return Ok(Some(Box::new(GradientEnvironment::new(
+                intensity * Color::new(1.0, 1.0, 1.0),
+                intensity * Color::new(0.5, 0.7, 1.0),
+            ))));
+        }
+        if environment.ends_with(".hdr") {
+            let image = read_hdr(environment)?;
+            return Ok(Some(Box::new(ImageEnvironment::new(
+                image,
+                self.environment_rotation,
+                intensity,
+            ))));
+        }
+        let color = parse_vec3(environment)?;
+        return Ok(Some(Box::new(ConstantEnvironment::new(intensity * color))));
+    }
+
+    pub fn make_lights(&self) -> Result<Vec<Box<dyn Light>>, String> {
+        let mut lights: Vec<Box<dyn Light>> = vec![];
+        if self.environment.as_deref() == Some("sky") {
+            lights.push(Box::new(self.make_sky().sun_light(self.sun_intensity)));
+        }
+        for spec in &self.lights {
+            lights.push(parse_light(spec)?);
+        }
+        return Ok(lights);
+    }
+
+    // Output file for one frame of a sequence. A run of # in the output name is replaced
+    // by the zero padded frame number, otherwise the number is appended to the stem.
+    pub fn frame_output(&self, frame: u32) -> String {
+        if let Some(start) = self.output.find('#') {
+            let digits = self.output[start..]
+                .chars()
+                .take_while(|&c| c == '#')
+                .count();
+            return format!(
+                "{}{:0width$}{}",
+                &self.output[..start],
+                frame,
+                &self.output[start + digits..],
+                width = digits
+            );
+        }
+        return match self.output.rfind('.') {
+            Some(i) => format!("{}_{:04}{}", &self.output[..i], frame, &self.output[i..]),
+            None => format!("{}_{:04}", self.output, frame),
+        };
+    }
+
+    // out.ppm becomes out.depth.pfm and so on
+    pub fn aov_output(output: &str, aov: Aov) -> String {
+        let stem = match output.rfind('.') {
+            Some(i) => &output[..i],
+            None => output,
+        };
+        return format!("{}.{}.pfm", stem, aov.name());
+    }
+
+    fn make_sky(&self) -> PreethamSky {
+        let sun_dir = PreethamSky::sun_direction(self.sun_elevation, self.sun_azimuth);
+        return PreethamSky::new(sun_dir, self.turbidity);
+    }
+}
+
+// point:x,y,z:watts
+// spot:x,y,z:tx,ty,tz:watts:inner_deg,outer_deg
+// directional:dx,dy,dz:irradiance
+// each may be followed by :r,g,b to tint the light
+pub fn parse_light(spec: &str) -> Result<Box<dyn Light>, String> {
+    let fields: Vec<&str> = spec.split(':').collect();
+    let expected = match fields[0] {
+        "point" | "directional" => 3,
+        "spot" => 5,
+        _ => return Err(format!("unknown light {}", spec)),
+    };
+    let color = match fields.len() {
+        n if n == expected => Color::new(1.0, 1.0, 1.0),
+        n if n == expected + 1 => parse_vec3(fields[expected])?,
+        _ => return Err(format!("invalid light {}", spec)),
+    };
+
+    let light: Box<dyn Light> = match fields[0] {
+        "point" => {
+            let power: f64 = parse_number(fields[2])?;
+            Box::new(PointLight::new(parse_vec3(fields[1])?, power * color))
+        }
+        "spot" => {
+            let power: f64 = parse_number(fields[3])?;
+            let angles: Vec<&str> = fields[4].split(',').collect();
+            if angles.len() != 2 {
+                return Err(format!("invalid spot angles {}", fields[4]));
+            }
+            let position = parse_vec3(fields[1])?;
+            let target = parse_vec3(fields[2])?;
+            let inner = degrees_to_radians(parse_number(angles[0])?);
+            let outer = degrees_to_radians(parse_number(angles[1])?);
+            SpotLight::check(position, target, inner, outer)
+                .map_err(|e| format!("{}: {}", spec, e))?;
+            Box::new(SpotLight::new(
+                position,
+                target,
+                power * color,
+                inner,
+                outer,
+            ))
+        }
+        _ => {
+            let irradiance: f64 = parse_number(fields[2])?;
+            let dir = parse_vec3(fields[1])?;
+            DirectionalLight::check(dir).map_err(|e| format!("{}: {}", spec, e))?;
+            Box::new(DirectionalLight::new(dir, irradiance * color))
+        }
+    };
+    return Ok(light);
+}
+
+// comma separated aov names, or all
+pub fn parse_aovs(s: &str) -> Result<Vec<Aov>, String> {
+    if s == "all" {
+        return Ok(Aov::all());
+    }
+    return s.split(',').map(Aov::parse).collect();
+}
+
+// "first-last" or a single frame number
+pub fn parse_frame_range(s: &str) -> Result<(u32, u32), String> {
+    let range = match s.split_once('-') {
+        Some((first, last)) => (parse_number(first)?, parse_number(last)?),
+        None => {
+            let frame = parse_number(s)?;
+            (frame, frame)
+        }
+    };
+    if range.0 > range.1 {
+        return Err(format!("invalid frame range {}", s));
+    }
+    return Ok(range);
+}
+
+pub fn parse_vec3(s: &str) -> Result<Vec3, String> {
+    let values: Vec<f64> = s
+        .split(',')
+        .map(parse_number)
+        .collect::<Result<Vec<f64>, String>>()?;
+    if values.len() != 3 {
+        return Err(format!("invalid vector {}", s));
+    }
+    return Ok(Vec3::new(values[0], values[1], values[2]));
+}
+
+pub fn parse_number<T: std::str::FromStr>(s: &str) -> Result<T, String> {
+    return s.parse::<T>().map_err(|_| format!("invalid number {}", s));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frame_output() {
+        let mut options = Options::default();
+        options.output = String::from("out.ppm");
+        assert_eq!(options.frame_output(7), "out_0007.ppm");
+        options.output = String::from("frames/shot_###.ppm");
+        assert_eq!(options.frame_output(42), "frames/shot_042.ppm");
+    }
+
+    #[test]
+    fn test_aov_output() {
+        assert_eq!(
+            Options::aov_output("frames/shot_012.ppm", Aov::Depth),
+            "frames/shot_012.depth.pfm"
+        );
+    }
+
+    #[test]
+    fn test_filter_radius() {
+        let parse = |radius: &str| {
+            let args = ["--filter", "tent", "--filter-radius", radius];
+            return Options::parse(args.iter().map(|arg| arg.to_string()));
+        };
+        assert!(parse("1.5").is_ok());
+        assert!(parse("0").is_err());
+        assert!(parse("-1").is_err());
+    }
+
+    #[test]
+    fn test_spot_light() {
+        let parse = |spec: &str| Options::parse(["--light", spec].iter().map(|a| a.to_string()));
+        assert!(parse("spot:0,2,0:0,0,0:100:20,30").is_ok());
+        assert!(parse("spot:0,2,0:0,2,0:100:20,30").is_err());
+        assert!(parse("spot:0,2,0:0,0,0:100:30,30").is_err());
+    }
+
+    #[test]
+    fn test_directional_light() {
+        let parse = |spec: &str| Options::parse(["--light", spec].iter().map(|a| a.to_string()));
+        assert!(parse("directional:0,1,0:2").is_ok());
+        assert!(parse("directional:0,0,0:2").is_err());
+    }
+
+    #[test]
+    fn test_farm_integrators() {
+        let parse = |integrator: &str| {
+            let args = ["--farm", "127.0.0.1:0", "--integrator", integrator];
+            return Options::parse(args.iter().map(|arg| arg.to_string()));
+        };
+        assert!(parse("path").is_ok());
+        assert!(parse("bdpt").is_ok());
+        for integrator in ["photon", "ppm", "mlt"] {
+            assert!(parse(integrator).is_err());
+        }
+    }
+
+    #[test]
+    fn test_parse_frame_range() {
+        assert_eq!(parse_frame_range("3-10"), Ok((3, 10)));
+        assert_eq!(parse_frame_range("5"), Ok((5, 5)));
+        assert!(parse_frame_range("10-3").is_err());
+    }
+}
@@ -0,0 +1,72 @@
+use crate::{
+    camera::{look_at_basis, Camera},
+    ray::Ray,
+    vec3::{Point, Vec3},
+};
+
+// Parallel projection, height is the extent of the view in world units.
+pub struct OrthographicCamera {
+    lower_left_corner: Point,
+    horizontal: Vec3,
+    vertical: Vec3,
+    dir: Vec3,
+}
+
+impl OrthographicCamera {
+    pub fn new(
+        lookfrom: Point,
+        lookat: Point,
+        vup: Vec3,
+        height: f64,
+        aspect_ratio: f64,
+    ) -> OrthographicCamera {
+        let (u, v, w) = look_at_basis(lookfrom, lookat, vup);
+        let horizontal = height * aspect_ratio * u;
+        let vertical = height * v;
+        return OrthographicCamera {
+            lower_left_corner: lookfrom - horizontal / 2.0 - vertical / 2.0,
+            horizontal,
+            vertical,
+            dir: -1.0 * w,
+        };
+    }
+}
+
+impl Camera for OrthographicCamera {
+    fn get_ray(&self, s: f64, t: f64) -> Ray {
+        return Ray::new(
+            self.lower_left_corner + s * self.horizontal + t * self.vertical,
+            self.dir,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPSILON: f64 = 0.000001;
+
+    #[test]
+    fn test_get_ray() {
+        let camera = OrthographicCamera::new(
+            Point::new(0.0, 0.0, 5.0),
+            Point::new(0.0, 0.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            2.0,
+            2.0,
+        );
+
+        let center = camera.get_ray(0.5, 0.5);
+        assert!((center.org - Point::new(0.0, 0.0, 5.0)).length() <= EPSILON);
+
+        // rays start across the whole view and are all parallel
+        let lower_left = camera.get_ray(0.0, 0.0);
+        assert!((lower_left.org - Point::new(-2.0, -1.0, 5.0)).length() <= EPSILON);
+        let upper_right = camera.get_ray(1.0, 1.0);
+        assert!((upper_right.org - Point::new(2.0, 1.0, 5.0)).length() <= EPSILON);
+        for ray in [center, lower_left, upper_right] {
+            assert!((ray.dir - Vec3::new(0.0, 0.0, -1.0)).length() <= EPSILON);
+        }
+    }
+}
@@ -0,0 +1,217 @@
+use crate::{
+    aperture::{Aperture, CircularAperture},
+    camera::{look_at_basis, Camera, CameraConnection},
+    hittable::Hittable,
+    math::degrees_to_radians,
+    ray::Ray,
+    vec3::{Point, Vec3},
+};
+
+pub struct PerspectiveCamera {
+    origin: Point,
+    u: Vec3,
+    v: Vec3,
+    w: Vec3,
+    viewport_width: f64,
+    viewport_height: f64,
+    focus_dist: f64,
+    lens_radius: f64,
+    aperture_shape: Box<dyn Aperture>,
+}
+
+impl PerspectiveCamera {
+    pub fn new(
+        lookfrom: Point,
+        lookat: Point,
+        vup: Vec3,
+        vfov: f64,
+        aspect_ratio: f64,
+        aperture: f64,
+        focus_dist: f64,
+    ) -> PerspectiveCamera {
+        let theta = degrees_to_radians(vfov);
+        let h = (theta * 0.5).tan();
+        let viewport_height = 2.0 * h;
+        let viewport_width = viewport_height * aspect_ratio;
+
+        let (u, v, w) = look_at_basis(lookfrom, lookat, vup);
+        let lens_radius = aperture * 0.5;
+
+        return PerspectiveCamera {
+            origin: lookfrom,
+            u,
+            v,
+            w,
+            viewport_width,
+            viewport_height,
+            focus_dist,
+            lens_radius,
+            aperture_shape: Box::new(CircularAperture {}),
+        };
+    }
+
+    pub fn set_aperture_shape(&mut self, aperture_shape: Box<dyn Aperture>) {
+        self.aperture_shape = aperture_shape;
+    }
+
+    // Focuses on whatever the pinhole ray through (s, t) hits first.
+    // Returns the new focus distance, or None when the ray escapes.
+    pub fn autofocus(&mut self, world: &dyn Hittable, s: f64, t: f64) -> Option<f64> {
+        let ray = Ray::new(self.origin, self.image_plane_dir(s, t));
+        let record = world.hit(&ray, 0.0001, f64::INFINITY)?;
+        // distance along the view axis, the plane of focus is perpendicular to it
+        let focus_dist = Vec3::dot(record.p - self.origin, -1.0 * self.w);
+        self.focus_dist = focus_dist;
+        return Some(focus_dist);
+    }
+
+    // direction to the image plane at unit distance
+    fn image_plane_dir(&self, s: f64, t: f64) -> Vec3 {
+        return (s - 0.5) * self.viewport_width * self.u
+            + (t - 0.5) * self.viewport_height * self.v
+            - self.w;
+    }
+
+    // The image is spread evenly over the plane of focus, so directions at an angle to
+    // the view axis are picked with a density falling off with the cosine cubed.
+    fn direction_pdf(&self, cos_theta: f64) -> f64 {
+        let area = self.viewport_width * self.viewport_height;
+        return 1.0 / (area * cos_theta * cos_theta * cos_theta);
+    }
+}
+
+impl Camera for PerspectiveCamera {
+    fn get_ray(&self, s: f64, t: f64) -> Ray {
+        let rd = self.lens_radius * self.aperture_shape.sample();
+        let offset = self.u * rd.x + self.v * rd.y;
+        let focus = self.origin + self.focus_dist * self.image_plane_dir(s, t);
+        return Ray::new(self.origin + offset, focus - self.origin - offset);
+    }
+
+    fn connect(&self, p: Point) -> Option<CameraConnection> {
+        let rd = self.lens_radius * self.aperture_shape.sample();
+        let lens = self.origin + self.u * rd.x + self.v * rd.y;
+        let to_point = p - lens;
+        let depth = Vec3::dot(to_point, -1.0 * self.w);
+        if depth <= 0.0 {
+            return None;
+        }
+        // the ray through the lens crosses the plane of focus where get_ray aims
+        let focus = lens + to_point * (self.focus_dist / depth) - self.origin;
+        let s = Vec3::dot(focus, self.u) / (self.focus_dist * self.viewport_width) + 0.5;
+        let t = Vec3::dot(focus, self.v) / (self.focus_dist * self.viewport_height) + 0.5;
+        if !(0.0..=1.0).contains(&s) || !(0.0..=1.0).contains(&t) {
+            return None;
+        }
+
+        // camera rays carry a weight of one, the lens density cancels out
+        let distance = to_point.length();
+        let pdf = self.direction_pdf(depth / distance);
+        return Some(CameraConnection {
+            s,
+            t,
+            dir: to_point / -distance,
+            distance,
+            importance: pdf / (distance * distance),
+            pdf,
+        });
+    }
+
+    fn pdf(&self, dir: Vec3) -> f64 {
+        let cos_theta = Vec3::dot(dir.unit_vector(), -1.0 * self.w);
+        if cos_theta <= 0.0 {
+            return 0.0;
+        }
+        return self.direction_pdf(cos_theta);
+    }
+}
+
+// Vertical field of view in degrees of a lens with the given focal length in front of
+// a sensor of the given width, both in millimetres.
+pub fn physical_vfov(sensor_width: f64, focal_length: f64, aspect_ratio: f64) -> f64 {
+    let sensor_height = sensor_width / aspect_ratio;
+    return 2.0 * (sensor_height / (2.0 * focal_length)).atan().to_degrees();
+}
+
+// Aperture diameter in scene units (metres) for a focal length in millimetres.
+pub fn physical_aperture(focal_length: f64, f_number: f64) -> f64 {
+    return focal_length / f_number / 1000.0;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lambertian::Lambertian, sphere::Sphere, vec3::Color};
+
+    const EPSILON: f64 = 0.000001;
+
+    #[test]
+    fn test_physical() {
+        // 50mm on full frame at 3:2 has a vertical field of view of about 27 degrees
+        assert!((physical_vfov(36.0, 50.0, 1.5) - 26.991).abs() < 0.001);
+        assert!((physical_aperture(50.0, 2.0) - 0.025).abs() <= EPSILON);
+    }
+
+    #[test]
+    fn test_autofocus() {
+        let sphere = Sphere::new(
+            Point::new(0.0, 0.0, -5.0),
+            1.0,
+            Box::new(Lambertian::new(Color::new(0.5, 0.5, 0.5))),
+        );
+        let mut camera = PerspectiveCamera::new(
+            Point::default(),
+            Point::new(0.0, 0.0, -1.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            40.0,
+            1.0,
+            0.5,
+            1.0,
+        );
+
+        let focus_dist = camera.autofocus(&sphere, 0.5, 0.5).unwrap();
+        assert!((focus_dist - 4.0).abs() <= EPSILON);
+        assert!(camera.autofocus(&sphere, 0.0, 0.0).is_none());
+        assert!((camera.focus_dist - 4.0).abs() <= EPSILON);
+    }
+
+    #[test]
+    fn test_connect() {
+        let camera = PerspectiveCamera::new(
+            Point::new(1.0, 2.0, 3.0),
+            Point::new(0.0, 0.0, -1.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            40.0,
+            1.5,
+            0.3,
+            4.0,
+        );
+        // points in focus project back to where get_ray aimed from any lens position
+        let p = camera.get_ray(0.3, 0.8).at(1.0);
+        for _ in 0..10 {
+            let connection = camera.connect(p).unwrap();
+            assert!((connection.s - 0.3).abs() <= EPSILON);
+            assert!((connection.t - 0.8).abs() <= EPSILON);
+            let lens = p + connection.distance * connection.dir;
+            assert!((lens - camera.origin).length() <= 0.15 + EPSILON);
+        }
+        assert!(camera.connect(Point::new(1.0, 2.0, 5.0)).is_none());
+
+        // the density of directions integrates to one over the image
+        let steps = 200;
+        let mut integral = 0.0;
+        for i in 0..steps {
+            for j in 0..steps {
+                let s = (i as f64 + 0.5) / steps as f64;
+                let t = (j as f64 + 0.5) / steps as f64;
+                let dir = camera.image_plane_dir(s, t);
+                let cos_theta = Vec3::dot(dir.unit_vector(), -1.0 * camera.w);
+                // solid angle of an image cell at unit distance along the axis
+                let area = camera.viewport_width * camera.viewport_height / (steps * steps) as f64;
+                let solid_angle = area * cos_theta / dir.length_double();
+                integral += camera.pdf(dir) * solid_angle;
+            }
+        }
+        assert!((integral - 1.0).abs() < 0.001);
+    }
+}
@@ -0,0 +1,20 @@
+use std::{fs::File, io::Write};
+
+use crate::image::Image;
+
+// Writes a little endian RGB portable float map. Rows are stored bottom to top.
+pub fn write_pfm(path: &str, image: &Image) -> Result<(), String> {
+    let mut buf = format!("PF\n{} {}\n-1.0\n", image.width, image.height).into_bytes();
+    for y in (0..image.height).rev() {
+        for x in 0..image.width {
+            let c = image.get(x, y);
+            for v in [c.x, c.y, c.z] {
+                buf.extend_from_slice(&(v as f32).to_le_bytes());
+            }
+        }
+    }
+    let mut file = File::create(path).map_err(|e| format!("{}: {}", path, e))?;
+    file.write_all(&buf)
+        .map_err(|e| format!("{}: {}", path, e))?;
+    return Ok(());
+}
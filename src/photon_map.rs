@@ -0,0 +1,259 @@
+use std::f64::consts::PI;
+
+use crate::{
+    camera::Camera,
+    film::Film,
+    hittable::{HitRecord, Hittable},
+    integrator::{project, ray_color_gathering, Integrator},
+    kd_tree::KdTree,
+    light::Light,
+    ray::Ray,
+    sampler::random_index,
+    scene::Scene,
+    spectrum::{sample_wavelength, spectral_to_rgb},
+    vec3::{Color, Vec3},
+};
+
+// shrinking rate of the radius between passes of progressive photon mapping
+const ALPHA: f64 = 2.0 / 3.0;
+
+// Light arriving at a diffuse surface after glass or mirrors, stored where it lands.
+struct Photon {
+    // direction of travel
+    dir: Vec3,
+    // flux in RGB, also in the spectral mode
+    power: Color,
+}
+
+// Path tracing with a caustic photon map. Photons leave the point and spot lights, pass
+// through specular surfaces such as Dielectric and Metal, and are stored at the first
+// diffuse surface they reach, where the path tracer can't find the light they carry.
+// Every diffuse hit of a camera path adds a density estimate of the photons around it.
+// The progressive variant traces a new map for each pass with a radius shrinking over
+// the passes, so the average of the passes converges to the right image.
+pub struct PhotonIntegrator {
+    max_depth: u32,
+    photons: usize,
+    radius: f64,
+    progressive: bool,
+    spectral: bool,
+    map: KdTree<Photon>,
+}
+
+impl PhotonIntegrator {
+    pub fn new(
+        max_depth: u32,
+        photons: usize,
+        radius: f64,
+        progressive: bool,
+        spectral: bool,
+    ) -> PhotonIntegrator {
+        return PhotonIntegrator {
+            max_depth,
+            photons,
+            radius,
+            progressive,
+            spectral,
+            map: KdTree::new(vec![]),
+        };
+    }
+
+    fn trace_photons(&self, scene: &Scene) -> KdTree<Photon> {
+        let lights: Vec<&dyn Light> = scene
+            .lights
+            .iter()
+            .map(|light| light.as_ref())
+            .filter(|light| light.position().is_some())
+            .collect();
+        let mut photons = vec![];
+        if lights.is_empty() {
+            return KdTree::new(photons);
+        }
+
+        let selection = 1.0 / lights.len() as f64;
+        for _ in 0..self.photons {
+            let light = lights[random_index(lights.len())];
+            let emission = match light.sample_le() {
+                Some(emission) => emission,
+                None => continue,
+            };
+            let mut ray = emission.ray;
+            if self.spectral {
+                ray.wavelength = Some(sample_wavelength());
+            }
+            let mut power = project(emission.le, &ray) / (selection * self.photons as f64);
+            let mut specular = false;
+
+            for _ in 0..self.max_depth {
+                let record = match scene.world.hit(&ray, 0.0001, f64::INFINITY) {
+                    Some(record) => record,
+                    None => break,
+                };
+                let mut scattered = match record.material.scatter(&ray, &record) {
+                    Some(scattered) => scattered,
+                    None => break,
+                };
+                // diffuse surfaces straight from the light are left to light sampling
+                if record.material.pdf(&ray, &record, scattered.ray.dir) > 0.0 {
+                    if specular && record.material.has_surface() {
+                        let power = match ray.wavelength {
+                            Some(wavelength) => spectral_to_rgb(power.x, wavelength),
+                            None => power,
+                        };
+                        let dir = ray.dir.unit_vector();
+                        photons.push((record.p, Photon { dir, power }));
+                    }
+                    break;
+                }
+                // photons don't random walk through subsurface media
+                if record.material.medium().is_some()
+                    && Vec3::dot(scattered.ray.dir, record.normal) < 0.0
+                {
+                    break;
+                }
+                specular = true;
+                power = power * project(scattered.attenuation, &ray);
+                scattered.ray.wavelength = ray.wavelength;
+                ray = scattered.ray;
+            }
+        }
+        return KdTree::new(photons);
+    }
+
+    // density estimate of the photons around a diffuse hit, reflected along the ray
+    fn gather(&self, ray: &Ray, record: &HitRecord) -> Color {
+        if self.map.len() == 0 || !record.material.has_surface() {
+            return Color::default();
+        }
+        let mut sum = Color::default();
+        self.map.within(record.p, self.radius, &mut |_, photon| {
+            let dir = -1.0 * photon.dir;
+            let cos_theta = Vec3::dot(record.normal, dir);
+            if cos_theta <= 0.0 {
+                return;
+            }
+            // eval includes the cosine, the photon power already has it
+            let f = record.material.eval(ray, record, dir) / cos_theta;
+            sum += project(f, ray) * project(photon.power, ray);
+        });
+        return sum / (PI * self.radius * self.radius);
+    }
+}
+
+impl Integrator for PhotonIntegrator {
+    fn prepare(&mut self, scene: &Scene, pass: u32) {
+        if pass > 0 {
+            if !self.progressive {
+                return;
+            }
+            let pass = f64::from(pass);
+            self.radius *= ((pass + ALPHA) / (pass + 1.0)).sqrt();
+        }
+        self.map = self.trace_photons(scene);
+    }
+
+    fn li(&mut self, ray: &Ray, scene: &Scene, _: &dyn Camera, _: &mut Film) -> Color {
+        let gather = |ray: &Ray, record: &HitRecord| self.gather(ray, record);
+        return ray_color_gathering(ray, scene, self.max_depth, &gather);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        dielectric::Dielectric, environment::ConstantEnvironment, hittable_list::HittableList,
+        lambertian::Lambertian, point_light::PointLight, sphere::Sphere, vec3::Point,
+    };
+
+    // a glass sphere focusing a point light onto the ground below it
+    fn glass_scene() -> Scene {
+        let mut world = HittableList::default();
+        world.add(Box::new(Sphere::new(
+            Point::new(0.0, -100.0, 0.0),
+            100.0,
+            Box::new(Lambertian::new(Color::new(0.8, 0.8, 0.8))),
+        )));
+        world.add(Box::new(Sphere::new(
+            Point::new(0.0, 1.0, 0.0),
+            0.5,
+            Box::new(Dielectric::new(1.5)),
+        )));
+        let environment = Box::new(ConstantEnvironment::new(Color::default()));
+        let mut scene = Scene::new(world, environment);
+        scene.add_light(Box::new(PointLight::new(
+            Point::new(0.0, 3.0, 0.0),
+            Color::new(20.0, 20.0, 20.0),
+        )));
+        return scene;
+    }
+
+    fn gather_at(integrator: &PhotonIntegrator, scene: &Scene, org: Point, target: Point) -> Color {
+        let ray = Ray::new(org, target - org);
+        let record = scene.world.hit(&ray, 0.0001, f64::INFINITY).unwrap();
+        return integrator.gather(&ray, &record);
+    }
+
+    #[test]
+    fn test_caustic_photons() {
+        let scene = glass_scene();
+        let mut integrator = PhotonIntegrator::new(6, 20000, 0.05, false, false);
+        integrator.prepare(&scene, 0);
+        assert!(integrator.map.len() > 0);
+
+        // every photon went through or off the glass and landed on the ground
+        let center = Point::new(0.0, -100.0, 0.0);
+        integrator
+            .map
+            .within(Point::default(), 100.0, &mut |p, photon| {
+                assert!(((*p - center).length() - 100.0).abs() < 0.001, "{:?}", p);
+                assert!(photon.power.x > 0.0);
+            });
+
+        // seen past the sphere, the focus below it is bright and the open ground is not
+        let focus = gather_at(
+            &integrator,
+            &scene,
+            Point::new(2.0, 1.0, 0.0),
+            Point::default(),
+        );
+        assert!(focus.x > 1.0, "{:?}", focus);
+        let open = Point::new(2.0, 0.0, 2.0);
+        let open = gather_at(&integrator, &scene, Point::new(2.0, 1.0, 2.0), open);
+        assert!(open.x < 0.1 * focus.x, "{:?} {:?}", open, focus);
+    }
+
+    #[test]
+    fn test_progressive_convergence() {
+        let scene = glass_scene();
+        let focus = |integrator: &PhotonIntegrator| {
+            return gather_at(
+                integrator,
+                &scene,
+                Point::new(2.0, 1.0, 0.0),
+                Point::default(),
+            )
+            .x;
+        };
+        // many photons in a small radius resolve the sharp peak of the focus
+        let mut reference = PhotonIntegrator::new(6, 200000, 0.005, false, false);
+        reference.prepare(&scene, 0);
+        let reference = focus(&reference);
+
+        // passes with few photons each, averaged as the renderer does
+        let mut progressive = PhotonIntegrator::new(6, 2000, 0.05, true, false);
+        let mut sum = 0.0;
+        for pass in 0..32 {
+            progressive.prepare(&scene, pass);
+            sum += focus(&progressive);
+        }
+        let averaged = sum / 32.0;
+        // the same photons in one map keep the starting radius, which blurs the peak
+        // however many there are, while the shrinking radius closes in on it
+        let mut fixed = PhotonIntegrator::new(6, 64000, 0.05, false, false);
+        fixed.prepare(&scene, 0);
+        let fixed = focus(&fixed);
+        assert!(averaged < reference, "{} {}", averaged, reference);
+        assert!(averaged > 1.5 * fixed, "{} {}", averaged, fixed);
+    }
+}
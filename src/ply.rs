@@ -0,0 +1,422 @@
+use crate::{mesh::Mesh, vec3::Vec3};
+
+// Reader for ascii and binary PLY meshes. Vertices take positions and, when present,
+// normals, texture coordinates and colors; faces are polygons of vertex indices. Other
+// elements and properties are skipped.
+pub fn read_ply(path: &str) -> Result<Mesh, String> {
+    let data = std::fs::read(path).map_err(|e| format!("{}: {}", path, e))?;
+    return parse_ply(&data).map_err(|e| format!("{}: {}", path, e));
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Format {
+    Ascii,
+    LittleEndian,
+    BigEndian,
+}
+
+#[derive(Clone, Copy)]
+enum Scalar {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl Scalar {
+    fn parse(name: &str) -> Result<Scalar, String> {
+        return match name {
+            "char" | "int8" => Ok(Scalar::I8),
+            "uchar" | "uint8" => Ok(Scalar::U8),
+            "short" | "int16" => Ok(Scalar::I16),
+            "ushort" | "uint16" => Ok(Scalar::U16),
+            "int" | "int32" => Ok(Scalar::I32),
+            "uint" | "uint32" => Ok(Scalar::U32),
+            "float" | "float32" => Ok(Scalar::F32),
+            "double" | "float64" => Ok(Scalar::F64),
+            other => Err(format!("unknown property type {}", other)),
+        };
+    }
+
+    fn size(&self) -> usize {
+        return match self {
+            Scalar::I8 | Scalar::U8 => 1,
+            Scalar::I16 | Scalar::U16 => 2,
+            Scalar::I32 | Scalar::U32 | Scalar::F32 => 4,
+            Scalar::F64 => 8,
+        };
+    }
+
+    // colors stored as integers use the full range of the type
+    fn color_scale(&self) -> f64 {
+        return match self {
+            Scalar::U8 => 1.0 / 255.0,
+            Scalar::U16 => 1.0 / 65535.0,
+            _ => 1.0,
+        };
+    }
+}
+
+struct Property {
+    name: String,
+    kind: Scalar,
+    // type of the count for list properties
+    count: Option<Scalar>,
+}
+
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+// List counts and face indices must be whole numbers that aren't negative.
+fn whole(value: f64) -> Result<usize, String> {
+    if value < 0.0 || value.fract() != 0.0 {
+        return Err(format!("invalid list count or index {}", value));
+    }
+    return Ok(value as usize);
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    format: Format,
+}
+
+impl Reader<'_> {
+    // the most values of the kind the data left could hold
+    fn room(&self, kind: Scalar) -> usize {
+        let size = if self.format == Format::Ascii {
+            1
+        } else {
+            kind.size()
+        };
+        return (self.data.len() - self.pos) / size;
+    }
+
+    fn next(&mut self, kind: Scalar) -> Result<f64, String> {
+        if self.format == Format::Ascii {
+            while self.pos < self.data.len() && self.data[self.pos].is_ascii_whitespace() {
+                self.pos += 1;
+            }
+            let start = self.pos;
+            while self.pos < self.data.len() && !self.data[self.pos].is_ascii_whitespace() {
+                self.pos += 1;
+            }
+            let token = std::str::from_utf8(&self.data[start..self.pos]).unwrap_or("");
+            return token
+                .parse::<f64>()
+                .map_err(|_| format!("invalid number {:?} at byte {}", token, start));
+        }
+
+        let size = kind.size();
+        let bytes = self
+            .data
+            .get(self.pos..self.pos + size)
+            .ok_or("unexpected end of data")?;
+        self.pos += size;
+        let mut b = [0u8; 8];
+        b[..size].copy_from_slice(bytes);
+        if self.format == Format::BigEndian {
+            b[..size].reverse();
+        }
+        return Ok(match kind {
+            Scalar::I8 => b[0] as i8 as f64,
+            Scalar::U8 => b[0] as f64,
+            Scalar::I16 => i16::from_le_bytes([b[0], b[1]]) as f64,
+            Scalar::U16 => u16::from_le_bytes([b[0], b[1]]) as f64,
+            Scalar::I32 => i32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+            Scalar::U32 => u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+            Scalar::F32 => f32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+            Scalar::F64 => f64::from_le_bytes(b),
+        });
+    }
+}
+
+pub fn parse_ply(data: &[u8]) -> Result<Mesh, String> {
+    let mut pos = 0;
+    let mut next_line = || -> Result<String, String> {
+        let end = data[pos..]
+            .iter()
+            .position(|b| *b == b'\n')
+            .ok_or("unexpected end of header")?;
+        let line = String::from_utf8_lossy(&data[pos..pos + end])
+            .trim()
+            .to_string();
+        pos += end + 1;
+        return Ok(line);
+    };
+
+    if next_line()? != "ply" {
+        return Err(String::from("not a ply file"));
+    }
+    let mut format = None;
+    let mut elements: Vec<Element> = vec![];
+    loop {
+        let line = next_line()?;
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        match tokens.as_slice() {
+            ["end_header"] => break,
+            ["format", name, _] => {
+                format = Some(match *name {
+                    "ascii" => Format::Ascii,
+                    "binary_little_endian" => Format::LittleEndian,
+                    "binary_big_endian" => Format::BigEndian,
+                    other => return Err(format!("unknown format {}", other)),
+                });
+            }
+            ["element", name, count] => elements.push(Element {
+                name: name.to_string(),
+                count: count
+                    .parse()
+                    .map_err(|_| format!("invalid element count {}", count))?,
+                properties: vec![],
+            }),
+            ["property", "list", count, kind, name] => {
+                let element = elements.last_mut().ok_or("property before element")?;
+                element.properties.push(Property {
+                    name: name.to_string(),
+                    kind: Scalar::parse(kind)?,
+                    count: Some(Scalar::parse(count)?),
+                });
+            }
+            ["property", kind, name] => {
+                let element = elements.last_mut().ok_or("property before element")?;
+                element.properties.push(Property {
+                    name: name.to_string(),
+                    kind: Scalar::parse(kind)?,
+                    count: None,
+                });
+            }
+            ["comment", ..] | ["obj_info", ..] | [] => {}
+            _ => return Err(format!("invalid header line {:?}", line)),
+        }
+    }
+
+    let mut reader = Reader {
+        data,
+        pos,
+        format: format.ok_or("missing format")?,
+    };
+    let mut positions = vec![];
+    let mut normals = vec![];
+    let mut uvs = vec![];
+    let mut colors = vec![];
+    let mut faces = vec![];
+    for element in &elements {
+        let index = |names: &[&str]| {
+            return element
+                .properties
+                .iter()
+                .position(|p| names.contains(&p.name.as_str()));
+        };
+        let xyz = [index(&["x"]), index(&["y"]), index(&["z"])];
+        let nxyz = [index(&["nx"]), index(&["ny"]), index(&["nz"])];
+        let st = [
+            index(&["s", "u", "texture_u"]),
+            index(&["t", "v", "texture_v"]),
+        ];
+        let rgb = [
+            index(&["red", "r", "diffuse_red"]),
+            index(&["green", "g", "diffuse_green"]),
+            index(&["blue", "b", "diffuse_blue"]),
+        ];
+        let indices = index(&["vertex_indices", "vertex_index"]);
+
+        for _ in 0..element.count {
+            let mut values: Vec<f64> = Vec::with_capacity(element.properties.len());
+            let mut list = vec![];
+            for (i, property) in element.properties.iter().enumerate() {
+                match property.count {
+                    Some(count) => {
+                        let n = whole(reader.next(count)?)?;
+                        if n > reader.room(property.kind) {
+                            return Err(format!("list of {} items past the end of the data", n));
+                        }
+                        let mut items = Vec::with_capacity(n);
+                        for _ in 0..n {
+                            items.push(reader.next(property.kind)?);
+                        }
+                        if Some(i) == indices {
+                            list = items;
+                        }
+                        values.push(0.0);
+                    }
+                    None => values.push(reader.next(property.kind)?),
+                }
+            }
+
+            if element.name == "vertex" {
+                let get = |i: [Option<usize>; 3]| {
+                    return Some(Vec3::new(values[i[0]?], values[i[1]?], values[i[2]?]));
+                };
+                positions.push(get(xyz).ok_or("vertices need x, y and z")?);
+                if let Some(n) = get(nxyz) {
+                    normals.push(n);
+                }
+                if let (Some(s), Some(t)) = (st[0], st[1]) {
+                    uvs.push((values[s], values[t]));
+                }
+                if let Some(c) = get(rgb) {
+                    let scale = element.properties[rgb[0].unwrap()].kind.color_scale();
+                    // stored for display, decoded with the gamma 2 the output uses
+                    let c = scale * c;
+                    colors.push(c * c);
+                }
+            } else if element.name == "face" {
+                let face = list.iter().map(|i| whole(*i));
+                faces.push(face.collect::<Result<Vec<usize>, String>>()?);
+            }
+        }
+    }
+
+    if faces.iter().flatten().any(|i| *i >= positions.len()) {
+        return Err(String::from("face index out of range"));
+    }
+    let mut mesh = Mesh::new(positions, faces);
+    let complete = |n: usize| n > 0 && n == mesh.positions.len();
+    if complete(normals.len()) {
+        mesh.normals = Some(normals.iter().map(|n| n.unit_vector()).collect());
+    }
+    if complete(uvs.len()) {
+        mesh.uvs = Some(uvs);
+    }
+    if complete(colors.len()) {
+        mesh.colors = Some(colors);
+    }
+    return Ok(mesh);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vec3::{Color, Point};
+
+    const EPSILON: f64 = 0.000001;
+
+    #[test]
+    fn test_ascii() {
+        let text = "ply
+format ascii 1.0
+comment a colored quad
+element vertex 4
+property float x
+property float y
+property float z
+property uchar red
+property uchar green
+property uchar blue
+element face 1
+property list uchar int vertex_indices
+element edge 1
+property int vertex1
+property int vertex2
+end_header
+0 0 0 255 0 0
+1 0 0 0 255 0
+1 1 0 0 0 255
+0 1 0 255 255 255
+4 0 1 2 3
+0 2
+";
+        let mesh = parse_ply(text.as_bytes()).unwrap();
+        assert_eq!(mesh.positions.len(), 4);
+        assert_eq!(mesh.faces, vec![vec![0, 1, 2, 3]]);
+        assert!((mesh.positions[2] - Point::new(1.0, 1.0, 0.0)).length() <= EPSILON);
+        let colors = mesh.colors.unwrap();
+        assert!((colors[1] - Color::new(0.0, 1.0, 0.0)).length() <= EPSILON);
+        assert!(mesh.normals.is_none());
+    }
+
+    #[test]
+    fn test_binary() {
+        let header = "ply
+format binary_big_endian 1.0
+element vertex 3
+property double x
+property double y
+property double z
+property float nx
+property float ny
+property float nz
+property ushort red
+property ushort green
+property ushort blue
+element face 1
+property list uchar uint vertex_index
+end_header
+";
+        let mut data = header.as_bytes().to_vec();
+        for (p, gray) in [
+            ([0.0, 0.0, 0.0], 0u16),
+            ([2.0, 0.0, 0.0], 65535),
+            ([0.0, 2.0, 0.0], 32768),
+        ] {
+            for v in p {
+                data.extend_from_slice(&f64::to_be_bytes(v));
+            }
+            for v in [0.0f32, 0.0, 2.0] {
+                data.extend_from_slice(&f32::to_be_bytes(v));
+            }
+            for _ in 0..3 {
+                data.extend_from_slice(&gray.to_be_bytes());
+            }
+        }
+        data.push(3);
+        for i in [0u32, 1, 2] {
+            data.extend_from_slice(&i.to_be_bytes());
+        }
+
+        let mesh = parse_ply(&data).unwrap();
+        assert_eq!(mesh.faces, vec![vec![0, 1, 2]]);
+        assert!((mesh.positions[1] - Point::new(2.0, 0.0, 0.0)).length() <= EPSILON);
+        assert!((mesh.normals.unwrap()[0] - Vec3::new(0.0, 0.0, 1.0)).length() <= EPSILON);
+        let colors = mesh.colors.unwrap();
+        assert!((colors[1].x - 1.0).abs() <= EPSILON);
+        assert!((colors[2].x - 0.25).abs() <= 0.001);
+
+        data.truncate(data.len() - 2);
+        assert!(parse_ply(&data).is_err());
+    }
+
+    #[test]
+    fn test_bad_lists() {
+        let header = "ply
+format ascii 1.0
+element vertex 3
+property float x
+property float y
+property float z
+element face 1
+property list int int vertex_indices
+end_header
+0 0 0
+1 0 0
+0 1 0
+";
+        for face in [
+            "3 0 1 2",
+            "3 0 -1 2",
+            "-3 0 1 2",
+            "3 0 1.5 2",
+            "2000000000 0 1 2",
+        ] {
+            let result = parse_ply(format!("{}{}\n", header, face).as_bytes());
+            assert_eq!(result.is_ok(), face == "3 0 1 2", "{}", face);
+        }
+        let mut data = b"ply
+format binary_little_endian 1.0
+element face 1
+property list uint int vertex_indices
+end_header
+"
+        .to_vec();
+        data.extend_from_slice(&u32::MAX.to_le_bytes());
+        assert!(parse_ply(&data).is_err());
+    }
+}
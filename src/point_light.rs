@@ -0,0 +1,75 @@
+use std::f64::consts::PI;
+
+use crate::{
+    light::{Light, LightEmission, LightSample},
+    ray::Ray,
+    vec3::{Color, Point, Vec3},
+};
+
+// Isotropic point light, power is the total emitted flux in watts.
+pub struct PointLight {
+    position: Point,
+    intensity: Color,
+}
+
+impl PointLight {
+    pub fn new(position: Point, power: Color) -> PointLight {
+        return PointLight {
+            position,
+            intensity: power / (4.0 * PI),
+        };
+    }
+}
+
+impl Light for PointLight {
+    fn sample_li(&self, p: Point) -> Option<LightSample> {
+        let to_light = self.position - p;
+        let distance_squared = to_light.length_double();
+        if distance_squared == 0.0 {
+            return None;
+        }
+        let distance = distance_squared.sqrt();
+        return Some(LightSample {
+            dir: to_light / distance,
+            distance,
+            li: self.intensity / distance_squared,
+        });
+    }
+
+    fn position(&self) -> Option<Point> {
+        return Some(self.position);
+    }
+
+    fn sample_le(&self) -> Option<LightEmission> {
+        let pdf = 1.0 / (4.0 * PI);
+        return Some(LightEmission {
+            ray: Ray::new(self.position, Vec3::random_unit_vector()),
+            le: self.intensity / pdf,
+            pdf,
+        });
+    }
+
+    fn pdf_le(&self, _: Vec3) -> f64 {
+        return 1.0 / (4.0 * PI);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPSILON: f64 = 0.000001;
+
+    #[test]
+    fn test_inverse_square_falloff() {
+        let light = PointLight::new(Point::new(0.0, 2.0, 0.0), Color::new(4.0 * PI, 0.0, 0.0));
+
+        let near = light.sample_li(Point::new(0.0, 1.0, 0.0)).unwrap();
+        let far = light.sample_li(Point::new(0.0, 0.0, 0.0)).unwrap();
+
+        assert!((near.li.x - 1.0).abs() <= EPSILON);
+        assert!((far.li.x - 0.25).abs() <= EPSILON);
+        assert!((far.distance - 2.0).abs() <= EPSILON);
+        assert!((far.dir.y - 1.0).abs() <= EPSILON);
+    }
+}
@@ -0,0 +1,256 @@
+// Closed form real roots of polynomials up to degree four, after Schwarze's solvers in
+// Graphics Gems. Coefficients are given lowest degree first, c[0] + c[1] x + c[2] x^2 ...
+// Roots are returned unsorted.
+
+const EPSILON: f64 = 1e-9;
+
+// Zero relative to the size of the terms x was made from, so the same tests hold at
+// any scene scale.
+fn is_zero(x: f64, scale: f64) -> bool {
+    return x.abs() <= EPSILON * scale;
+}
+
+fn magnitude(c: &[f64]) -> f64 {
+    return c.iter().fold(0.0, |m, x| m.max(x.abs()));
+}
+
+pub fn solve_quadratic(c: [f64; 3]) -> Vec<f64> {
+    // x^2 + 2px + q = 0
+    let p = c[1] / (2.0 * c[2]);
+    let q = c[0] / c[2];
+    // a leading coefficient too small to divide by leaves a line
+    if !(p * p + q.abs()).is_finite() {
+        if c[1] == 0.0 {
+            return vec![];
+        }
+        return vec![-c[0] / c[1]];
+    }
+    let d = p * p - q;
+    if is_zero(d, p * p + q.abs()) {
+        return vec![-p];
+    }
+    if d < 0.0 {
+        return vec![];
+    }
+    // the root further from zero, then the other from their product, which keeps it
+    // accurate however far apart the two are
+    let far = -p - p.signum() * d.sqrt();
+    return vec![far, q / far];
+}
+
+pub fn solve_cubic(c: [f64; 4]) -> Vec<f64> {
+    // x^3 + Ax^2 + Bx + C = 0
+    let a = c[2] / c[3];
+    let b = c[1] / c[3];
+    let cc = c[0] / c[3];
+    if ![a, b, cc].iter().all(|x| x.is_finite()) {
+        return solve_quadratic([c[0], c[1], c[2]]);
+    }
+
+    // substitute x = y - A/3 to eliminate the quadratic term: y^3 + 3py + 2q = 0
+    let sq_a = a * a;
+    let p = (-sq_a / 3.0 + b) / 3.0;
+    let q_terms = [2.0 / 27.0 * a * sq_a, -a * b / 3.0, cc];
+    let q = q_terms.iter().sum::<f64>() / 2.0;
+    let cb_p = p * p * p;
+    let d = q * q + cb_p;
+
+    let mut roots = if is_zero(d, q * q + cb_p.abs()) {
+        if is_zero(q, magnitude(&q_terms)) {
+            vec![0.0]
+        } else {
+            let u = (-q).cbrt();
+            vec![2.0 * u, -u]
+        }
+    } else if d < 0.0 {
+        // three real roots
+        let phi = (-q / (-cb_p).sqrt()).clamp(-1.0, 1.0).acos() / 3.0;
+        let t = 2.0 * (-p).sqrt();
+        let third = std::f64::consts::PI / 3.0;
+        vec![
+            t * phi.cos(),
+            -t * (phi + third).cos(),
+            -t * (phi - third).cos(),
+        ]
+    } else {
+        let sqrt_d = d.sqrt();
+        vec![(sqrt_d - q).cbrt() - (sqrt_d + q).cbrt()]
+    };
+
+    for root in &mut roots {
+        *root -= a / 3.0;
+    }
+    return roots;
+}
+
+pub fn solve_quartic(c: [f64; 5]) -> Vec<f64> {
+    // x^4 + Ax^3 + Bx^2 + Cx + D = 0
+    let a = c[3] / c[4];
+    let b = c[2] / c[4];
+    let cc = c[1] / c[4];
+    let d = c[0] / c[4];
+    if ![a, b, cc, d].iter().all(|x| x.is_finite()) {
+        return solve_cubic([c[0], c[1], c[2], c[3]]);
+    }
+
+    // substitute x = y - A/4 to eliminate the cubic term: y^4 + py^2 + qy + r = 0
+    let sq_a = a * a;
+    let p = -3.0 / 8.0 * sq_a + b;
+    let q = sq_a * a / 8.0 - a * b / 2.0 + cc;
+    let r_terms = [
+        -3.0 / 256.0 * sq_a * sq_a,
+        sq_a * b / 16.0,
+        -a * cc / 4.0,
+        d,
+    ];
+    let r = r_terms.iter().sum::<f64>();
+
+    let mut roots = if is_zero(r, magnitude(&r_terms)) {
+        // no absolute term: y (y^3 + py + q) = 0
+        let mut roots = solve_cubic([q, p, 0.0, 1.0]);
+        roots.push(0.0);
+        roots
+    } else {
+        // one root of the resolvent cubic splits the quartic into two quadratics
+        let z = solve_cubic([r * p / 2.0 - q * q / 8.0, -r, -p / 2.0, 1.0])[0];
+        let u = z * z - r;
+        let v = 2.0 * z - p;
+        if (u < 0.0 && !is_zero(u, z * z + r.abs()))
+            || (v < 0.0 && !is_zero(v, 2.0 * z.abs() + p.abs()))
+        {
+            return vec![];
+        }
+        let u = u.max(0.0).sqrt();
+        let v = if q < 0.0 {
+            -v.max(0.0).sqrt()
+        } else {
+            v.max(0.0).sqrt()
+        };
+        let mut roots = solve_quadratic([z - u, v, 1.0]);
+        roots.extend(solve_quadratic([z + u, -v, 1.0]));
+        roots
+    };
+
+    for root in &mut roots {
+        *root -= a / 4.0;
+    }
+    return roots;
+}
+
+// A few Newton steps to recover precision lost in the closed form solution.
+pub fn polish_root(c: &[f64], x: f64) -> f64 {
+    let mut x = x;
+    for _ in 0..2 {
+        let mut value = 0.0;
+        let mut derivative = 0.0;
+        for coefficient in c.iter().rev() {
+            derivative = derivative * x + value;
+            value = value * x + coefficient;
+        }
+        let step = value / derivative;
+        if !step.is_finite() {
+            break;
+        }
+        x -= step;
+    }
+    return x;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPSILON: f64 = 0.000001;
+
+    fn sorted(mut roots: Vec<f64>) -> Vec<f64> {
+        roots.sort_by(|a, b| a.total_cmp(b));
+        return roots;
+    }
+
+    #[test]
+    fn test_quadratic_and_cubic() {
+        let roots = sorted(solve_quadratic([-6.0, 1.0, 1.0]));
+        assert!((roots[0] + 3.0).abs() <= EPSILON);
+        assert!((roots[1] - 2.0).abs() <= EPSILON);
+        assert!(solve_quadratic([1.0, 0.0, 1.0]).is_empty());
+
+        // (x - 1)(x - 2)(x - 3)
+        let roots = sorted(solve_cubic([-6.0, 11.0, -6.0, 1.0]));
+        assert_eq!(roots.len(), 3);
+        for (root, expected) in roots.iter().zip([1.0, 2.0, 3.0]) {
+            assert!((root - expected).abs() <= EPSILON);
+        }
+    }
+
+    #[test]
+    fn test_quartic() {
+        // (x - 1)(x + 2)(x - 3)(x + 4) = x^4 + 2x^3 - 13x^2 - 14x + 24
+        let c = [24.0, -14.0, -13.0, 2.0, 1.0];
+        let roots = sorted(
+            solve_quartic(c)
+                .iter()
+                .map(|x| polish_root(&c, *x))
+                .collect(),
+        );
+        assert_eq!(roots.len(), 4);
+        for (root, expected) in roots.iter().zip([-4.0, -2.0, 1.0, 3.0]) {
+            assert!((root - expected).abs() <= EPSILON);
+        }
+
+        // x^4 + 1 has no real roots
+        assert!(solve_quartic([1.0, 0.0, 0.0, 0.0, 1.0]).is_empty());
+    }
+
+    #[test]
+    fn test_scale() {
+        // the roots of the tests above scaled down by k, as in a scene modelled in km
+        let k = 0.001;
+        let roots = sorted(solve_quadratic([-6.0 * k * k, k, 1.0]));
+        assert_eq!(roots.len(), 2);
+        assert!((roots[0] / k + 3.0).abs() <= EPSILON);
+        assert!((roots[1] / k - 2.0).abs() <= EPSILON);
+
+        let c = [
+            24.0 * k.powi(4),
+            -14.0 * k.powi(3),
+            -13.0 * k * k,
+            2.0 * k,
+            1.0,
+        ];
+        let roots = sorted(
+            solve_quartic(c)
+                .iter()
+                .map(|x| polish_root(&c, *x))
+                .collect(),
+        );
+        assert_eq!(roots.len(), 4);
+        for (root, expected) in roots.iter().zip([-4.0, -2.0, 1.0, 3.0]) {
+            assert!((root / k - expected).abs() <= EPSILON);
+        }
+
+        // and up by 1 / k
+        let c = [
+            24.0 / k.powi(4),
+            -14.0 / k.powi(3),
+            -13.0 / (k * k),
+            2.0 / k,
+            1.0,
+        ];
+        let roots = sorted(
+            solve_quartic(c)
+                .iter()
+                .map(|x| polish_root(&c, *x))
+                .collect(),
+        );
+        assert_eq!(roots.len(), 4);
+        for (root, expected) in roots.iter().zip([-4.0, -2.0, 1.0, 3.0]) {
+            assert!((root * k - expected).abs() <= EPSILON);
+        }
+
+        // a nearly flat parabola keeps its root near the origin, with the other far out
+        let roots = sorted(solve_quadratic([-1.0, 1.0, 1e-20]));
+        assert!((roots[1] - 1.0).abs() <= EPSILON);
+        assert_eq!(solve_quadratic([-1.0, 2.0, 0.0]), vec![0.5]);
+        assert!(solve_quadratic([1.0, 0.0, 0.0]).is_empty());
+    }
+}
@@ -0,0 +1,491 @@
+use crate::{
+    image::{luminance, Image},
+    vec3::Color,
+};
+
+// One stage of the chain run over the float framebuffer before it is written out.
+pub trait PostProcess {
+    fn apply(&self, image: &Image) -> Image;
+}
+
+pub fn apply_chain(chain: &[Box<dyn PostProcess>], image: Image) -> Image {
+    let mut image = image;
+    for stage in chain {
+        image = stage.apply(&image);
+    }
+    return image;
+}
+
+pub struct Exposure {
+    scale: f64,
+}
+
+impl Exposure {
+    // stops, +1 doubles the brightness
+    pub fn new(stops: f64) -> Exposure {
+        return Exposure {
+            scale: 2.0_f64.powf(stops),
+        };
+    }
+}
+
+impl PostProcess for Exposure {
+    fn apply(&self, image: &Image) -> Image {
+        return map_pixels(image, |c| self.scale * c);
+    }
+}
+
+// Neutralises light of the given color temperature, e.g. 3200 for tungsten.
+pub struct WhiteBalance {
+    gain: Color,
+}
+
+impl WhiteBalance {
+    pub fn new(temperature: f64, tint: f64) -> WhiteBalance {
+        let white = kelvin_to_rgb(temperature);
+        let reference = kelvin_to_rgb(6500.0);
+        let gain = reference / white;
+        // positive tint pushes towards magenta
+        let gain = Color::new(gain.x, gain.y * (1.0 - tint), gain.z);
+        return WhiteBalance {
+            gain: gain / luminance(gain),
+        };
+    }
+}
+
+impl PostProcess for WhiteBalance {
+    fn apply(&self, image: &Image) -> Image {
+        return map_pixels(image, |c| self.gain * c);
+    }
+}
+
+// Darkens towards the corners, strength 1 makes the corners black.
+pub struct Vignette {
+    strength: f64,
+    falloff: f64,
+}
+
+impl Vignette {
+    pub fn new(strength: f64, falloff: f64) -> Vignette {
+        return Vignette { strength, falloff };
+    }
+}
+
+impl PostProcess for Vignette {
+    fn apply(&self, image: &Image) -> Image {
+        let mut output = image.clone();
+        for y in 0..image.height {
+            for x in 0..image.width {
+                let (dx, dy) = centered(image, x as f64 + 0.5, y as f64 + 0.5);
+                // 1 in the corners
+                let r = ((dx * dx + dy * dy) / 2.0).sqrt();
+                let factor = 1.0 - self.strength * r.powf(self.falloff);
+                output.set(x, y, factor.max(0.0) * image.get(x, y));
+            }
+        }
+        return output;
+    }
+}
+
+// Glow around pixels brighter than the threshold: the excess is blurred with a gaussian
+// whose radius is a fraction of the image width and added back.
+pub struct Bloom {
+    threshold: f64,
+    radius: f64,
+    intensity: f64,
+}
+
+impl Bloom {
+    pub fn new(threshold: f64, radius: f64, intensity: f64) -> Bloom {
+        return Bloom {
+            threshold,
+            radius,
+            intensity,
+        };
+    }
+}
+
+impl PostProcess for Bloom {
+    fn apply(&self, image: &Image) -> Image {
+        let bright = map_pixels(image, |c| {
+            let l = luminance(c);
+            if l <= self.threshold {
+                return Color::default();
+            }
+            return (l - self.threshold) / l * c;
+        });
+
+        let sigma = (self.radius * image.width as f64).max(0.5);
+        let kernel = gaussian_kernel(sigma);
+        let blurred = blur(&blur(&bright, &kernel, true), &kernel, false);
+
+        let mut output = image.clone();
+        for y in 0..image.height {
+            for x in 0..image.width {
+                output.set(x, y, image.get(x, y) + self.intensity * blurred.get(x, y));
+            }
+        }
+        return output;
+    }
+}
+
+// Red and blue are scaled radially around the center by opposite amounts.
+pub struct ChromaticAberration {
+    strength: f64,
+}
+
+impl ChromaticAberration {
+    pub fn new(strength: f64) -> ChromaticAberration {
+        return ChromaticAberration { strength };
+    }
+}
+
+impl PostProcess for ChromaticAberration {
+    fn apply(&self, image: &Image) -> Image {
+        let cx = image.width as f64 * 0.5;
+        let cy = image.height as f64 * 0.5;
+        let mut output = image.clone();
+        for y in 0..image.height {
+            for x in 0..image.width {
+                let px = x as f64 + 0.5 - cx;
+                let py = y as f64 + 0.5 - cy;
+                let red_scale = 1.0 + self.strength;
+                let blue_scale = 1.0 - self.strength;
+                let red = sample_bilinear(image, cx + px * red_scale, cy + py * red_scale);
+                let blue = sample_bilinear(image, cx + px * blue_scale, cy + py * blue_scale);
+                output.set(x, y, Color::new(red.x, image.get(x, y).y, blue.z));
+            }
+        }
+        return output;
+    }
+}
+
+// Radial distortion r' = r (1 + k1 r^2 + k2 r^4), positive is barrel and negative is
+// pincushion. r is 1 at the middle of the long edge.
+pub struct LensDistortion {
+    k1: f64,
+    k2: f64,
+}
+
+impl LensDistortion {
+    pub fn new(k1: f64, k2: f64) -> LensDistortion {
+        return LensDistortion { k1, k2 };
+    }
+}
+
+impl PostProcess for LensDistortion {
+    fn apply(&self, image: &Image) -> Image {
+        let cx = image.width as f64 * 0.5;
+        let cy = image.height as f64 * 0.5;
+        let half = cx.max(cy);
+        let mut output = image.clone();
+        for y in 0..image.height {
+            for x in 0..image.width {
+                let px = (x as f64 + 0.5 - cx) / half;
+                let py = (y as f64 + 0.5 - cy) / half;
+                let r2 = px * px + py * py;
+                let scale = 1.0 + self.k1 * r2 + self.k2 * r2 * r2;
+                let color = sample_bilinear(image, cx + px * scale * half, cy + py * scale * half);
+                output.set(x, y, color);
+            }
+        }
+        return output;
+    }
+}
+
+// 3D lookup table in the .cube format. Tables are authored for display values, so
+// pixels are gamma encoded before the lookup and decoded afterwards.
+pub struct ColorLut {
+    size: usize,
+    table: Vec<Color>,
+    domain_min: Color,
+    domain_max: Color,
+}
+
+impl ColorLut {
+    pub fn load(path: &str) -> Result<ColorLut, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        return ColorLut::parse(&text).map_err(|e| format!("{}: {}", path, e));
+    }
+
+    pub fn parse(text: &str) -> Result<ColorLut, String> {
+        let mut size = 0;
+        let mut table = vec![];
+        let mut domain_min = Color::new(0.0, 0.0, 0.0);
+        let mut domain_max = Color::new(1.0, 1.0, 1.0);
+
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') || line.starts_with("TITLE") {
+                continue;
+            }
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            match fields[0] {
+                "LUT_3D_SIZE" => size = parse_field(&fields, 1)? as usize,
+                "DOMAIN_MIN" => domain_min = parse_color(&fields[1..])?,
+                "DOMAIN_MAX" => domain_max = parse_color(&fields[1..])?,
+                "LUT_1D_SIZE" => return Err("1D lookup tables are not supported".to_string()),
+                _ => table.push(parse_color(&fields)?),
+            }
+        }
+
+        if size < 2 {
+            return Err("missing LUT_3D_SIZE".to_string());
+        }
+        // the lookup divides by the width of the domain in each channel
+        let (min, max) = (domain_min, domain_max);
+        if min.x >= max.x || min.y >= max.y || min.z >= max.z {
+            return Err("DOMAIN_MIN must be below DOMAIN_MAX in every channel".to_string());
+        }
+        if table.len() != size * size * size {
+            return Err(format!(
+                "expected {} entries, found {}",
+                size * size * size,
+                table.len()
+            ));
+        }
+        return Ok(ColorLut {
+            size,
+            table,
+            domain_min,
+            domain_max,
+        });
+    }
+
+    // red changes fastest
+    fn entry(&self, r: usize, g: usize, b: usize) -> Color {
+        return self.table[(b * self.size + g) * self.size + r];
+    }
+
+    pub fn lookup(&self, c: Color) -> Color {
+        let n = (self.size - 1) as f64;
+        let t = (c - self.domain_min) / (self.domain_max - self.domain_min);
+        let coord = |v: f64| {
+            let v = v.clamp(0.0, 1.0) * n;
+            let i = (v.floor() as usize).min(self.size - 2);
+            return (i, v - i as f64);
+        };
+        let (r, fr) = coord(t.x);
+        let (g, fg) = coord(t.y);
+        let (b, fb) = coord(t.z);
+
+        let mut result = Color::default();
+        for (db, wb) in [(0, 1.0 - fb), (1, fb)] {
+            for (dg, wg) in [(0, 1.0 - fg), (1, fg)] {
+                for (dr, wr) in [(0, 1.0 - fr), (1, fr)] {
+                    result += wr * wg * wb * self.entry(r + dr, g + dg, b + db);
+                }
+            }
+        }
+        return result;
+    }
+}
+
+impl PostProcess for ColorLut {
+    fn apply(&self, image: &Image) -> Image {
+        return map_pixels(image, |c| {
+            let graded = self.lookup(c.clamp(0.0, 1.0).sqrt());
+            return graded * graded;
+        });
+    }
+}
+
+fn parse_field(fields: &[&str], i: usize) -> Result<f64, String> {
+    let field = fields.get(i).ok_or("missing value")?;
+    return field
+        .parse::<f64>()
+        .map_err(|_| format!("invalid number {}", field));
+}
+
+fn parse_color(fields: &[&str]) -> Result<Color, String> {
+    return Ok(Color::new(
+        parse_field(fields, 0)?,
+        parse_field(fields, 1)?,
+        parse_field(fields, 2)?,
+    ));
+}
+
+fn map_pixels(image: &Image, f: impl Fn(Color) -> Color) -> Image {
+    let mut output = image.clone();
+    for y in 0..image.height {
+        for x in 0..image.width {
+            output.set(x, y, f(image.get(x, y)));
+        }
+    }
+    return output;
+}
+
+// position relative to the center, -1 to 1 along both axes
+fn centered(image: &Image, x: f64, y: f64) -> (f64, f64) {
+    return (
+        2.0 * x / image.width as f64 - 1.0,
+        2.0 * y / image.height as f64 - 1.0,
+    );
+}
+
+// (x, y) in raster space, clamped at the borders
+fn sample_bilinear(image: &Image, x: f64, y: f64) -> Color {
+    let x = (x - 0.5).clamp(0.0, (image.width - 1) as f64);
+    let y = (y - 0.5).clamp(0.0, (image.height - 1) as f64);
+    let x0 = x.floor() as u32;
+    let y0 = y.floor() as u32;
+    let x1 = (x0 + 1).min(image.width - 1);
+    let y1 = (y0 + 1).min(image.height - 1);
+    let fx = x - x0 as f64;
+    let fy = y - y0 as f64;
+    let top = (1.0 - fx) * image.get(x0, y0) + fx * image.get(x1, y0);
+    let bottom = (1.0 - fx) * image.get(x0, y1) + fx * image.get(x1, y1);
+    return (1.0 - fy) * top + fy * bottom;
+}
+
+fn gaussian_kernel(sigma: f64) -> Vec<f64> {
+    let radius = (3.0 * sigma).ceil() as i64;
+    let mut kernel: Vec<f64> = (-radius..=radius)
+        .map(|i| (-((i * i) as f64) / (2.0 * sigma * sigma)).exp())
+        .collect();
+    let sum: f64 = kernel.iter().sum();
+    for w in &mut kernel {
+        *w /= sum;
+    }
+    return kernel;
+}
+
+fn blur(image: &Image, kernel: &[f64], horizontal: bool) -> Image {
+    let radius = (kernel.len() / 2) as i64;
+    let mut output = image.clone();
+    for y in 0..image.height as i64 {
+        for x in 0..image.width as i64 {
+            let mut sum = Color::default();
+            for (k, w) in kernel.iter().enumerate() {
+                let offset = k as i64 - radius;
+                let (sx, sy) = if horizontal {
+                    ((x + offset).clamp(0, image.width as i64 - 1), y)
+                } else {
+                    (x, (y + offset).clamp(0, image.height as i64 - 1))
+                };
+                sum += *w * image.get(sx as u32, sy as u32);
+            }
+            output.set(x as u32, y as u32, sum);
+        }
+    }
+    return output;
+}
+
+// Approximate linear rgb of a black body, fitted to the CIE 1964 color matching functions.
+fn kelvin_to_rgb(temperature: f64) -> Color {
+    let t = temperature.clamp(1000.0, 40000.0) / 100.0;
+    let r = if t <= 66.0 {
+        255.0
+    } else {
+        329.698727446 * (t - 60.0).powf(-0.1332047592)
+    };
+    let g = if t <= 66.0 {
+        99.4708025861 * t.ln() - 161.1195681661
+    } else {
+        288.1221695283 * (t - 60.0).powf(-0.0755148492)
+    };
+    let b = if t >= 66.0 {
+        255.0
+    } else if t <= 19.0 {
+        0.0
+    } else {
+        138.5177312231 * (t - 10.0).ln() - 305.0447927307
+    };
+    let srgb = Color::new(r, g, b).clamp(1.0, 255.0) / 255.0;
+    // the fit is for display values
+    return srgb * srgb;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPSILON: f64 = 0.000001;
+
+    fn constant_image(width: u32, height: u32, color: Color) -> Image {
+        let mut image = Image::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                image.set(x, y, color);
+            }
+        }
+        return image;
+    }
+
+    #[test]
+    fn test_exposure_and_white_balance() {
+        let image = constant_image(2, 2, Color::new(0.25, 0.5, 1.0));
+        let chain: Vec<Box<dyn PostProcess>> = vec![
+            Box::new(Exposure::new(1.0)),
+            Box::new(WhiteBalance::new(6500.0, 0.0)),
+        ];
+        let c = apply_chain(&chain, image).get(1, 1);
+        assert!((c.x - 0.5).abs() <= EPSILON);
+        assert!((c.z - 2.0).abs() <= EPSILON);
+
+        // correcting for tungsten light makes the image bluer
+        let warm =
+            WhiteBalance::new(3200.0, 0.0).apply(&constant_image(1, 1, Color::new(1.0, 1.0, 1.0)));
+        assert!(warm.get(0, 0).z > warm.get(0, 0).x);
+    }
+
+    #[test]
+    fn test_vignette() {
+        let image = constant_image(10, 10, Color::new(1.0, 1.0, 1.0));
+        let result = Vignette::new(0.5, 2.0).apply(&image);
+        assert!(result.get(5, 5).x > 0.99);
+        assert!(result.get(0, 0).x < 0.6);
+    }
+
+    #[test]
+    fn test_bloom_spreads_bright_pixels() {
+        let mut image = constant_image(9, 9, Color::new(0.1, 0.1, 0.1));
+        image.set(4, 4, Color::new(10.0, 10.0, 10.0));
+        let result = Bloom::new(1.0, 0.2, 1.0).apply(&image);
+        assert!(result.get(5, 4).x > 0.2);
+        assert!(result.get(4, 4).x < 10.0 + 9.0);
+
+        // nothing above the threshold leaves the image unchanged
+        let dark = constant_image(4, 4, Color::new(0.5, 0.5, 0.5));
+        assert!((Bloom::new(1.0, 0.2, 1.0).apply(&dark).get(2, 2).x - 0.5).abs() <= EPSILON);
+    }
+
+    #[test]
+    fn test_zero_distortion_is_identity() {
+        let mut image = Image::new(4, 3);
+        image.set(1, 2, Color::new(1.0, 2.0, 3.0));
+        let distorted = LensDistortion::new(0.0, 0.0).apply(&image);
+        let aberrated = ChromaticAberration::new(0.0).apply(&image);
+        assert!((distorted.get(1, 2).y - 2.0).abs() <= EPSILON);
+        assert!((aberrated.get(1, 2).z - 3.0).abs() <= EPSILON);
+    }
+
+    #[test]
+    fn test_color_lut() {
+        // identity table with the red and blue outputs swapped
+        let mut text = String::from("TITLE \"swap\"\nLUT_3D_SIZE 2\n");
+        for b in 0..2 {
+            for g in 0..2 {
+                for r in 0..2 {
+                    text.push_str(&format!("{} {} {}\n", b, g, r));
+                }
+            }
+        }
+        let lut = ColorLut::parse(&text).unwrap();
+        let c = lut.lookup(Color::new(0.25, 0.5, 0.75));
+        assert!((c.x - 0.75).abs() <= EPSILON);
+        assert!((c.y - 0.5).abs() <= EPSILON);
+        assert!((c.z - 0.25).abs() <= EPSILON);
+
+        assert!(ColorLut::parse("LUT_3D_SIZE 2\n0 0 0\n").is_err());
+        let flat = text.replace(
+            "LUT_3D_SIZE 2",
+            "LUT_3D_SIZE 2\nDOMAIN_MIN 0 0.5 0\nDOMAIN_MAX 1 0.5 1",
+        );
+        let error = ColorLut::parse(&flat).err().unwrap();
+        assert_eq!(
+            error,
+            "DOMAIN_MIN must be below DOMAIN_MAX in every channel"
+        );
+    }
+}
@@ -0,0 +1,171 @@
+use std::f64::consts::PI;
+
+use crate::{
+    environment::Environment,
+    math::degrees_to_radians,
+    sun_light::SunLight,
+    vec3::{Color, Vec3},
+};
+
+// Preetham, Shirley and Smits, "A Practical Analytic Model for Daylight" (1999).
+// Luminance comes out in kcd/m^2 and is scaled by LUMINANCE_SCALE into render units.
+const LUMINANCE_SCALE: f64 = 0.1;
+// angular radius of the sun seen from the earth
+const SUN_ANGULAR_RADIUS: f64 = 0.00465;
+
+pub struct PreethamSky {
+    sun_dir: Vec3,
+    turbidity: f64,
+    theta_sun: f64,
+    perez_y: [f64; 5],
+    perez_x: [f64; 5],
+    perez_yy: [f64; 5],
+    zenith: Vec3,
+}
+
+impl PreethamSky {
+    pub fn new(sun_dir: Vec3, turbidity: f64) -> PreethamSky {
+        let sun_dir = sun_dir.unit_vector();
+        let t = turbidity;
+        // keep the sun slightly above the horizon, the fit breaks down below it
+        let theta_sun = sun_dir.y.clamp(0.0, 1.0).acos().min(PI / 2.0 - 0.001);
+
+        let perez_yy = [
+            0.1787 * t - 1.4630,
+            -0.3554 * t + 0.4275,
+            -0.0227 * t + 5.3251,
+            0.1206 * t - 2.5771,
+            -0.0670 * t + 0.3703,
+        ];
+        let perez_x = [
+            -0.0193 * t - 0.2592,
+            -0.0665 * t + 0.0008,
+            -0.0004 * t + 0.2125,
+            -0.0641 * t - 0.8989,
+            -0.0033 * t + 0.0452,
+        ];
+        let perez_y = [
+            -0.0167 * t - 0.2608,
+            -0.0950 * t + 0.0092,
+            -0.0079 * t + 0.2102,
+            -0.0441 * t - 1.6537,
+            -0.0109 * t + 0.0529,
+        ];
+
+        let s = theta_sun;
+        let (s2, s3) = (s * s, s * s * s);
+        let chi = (4.0 / 9.0 - t / 120.0) * (PI - 2.0 * s);
+        let zenith_yy = (4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192;
+        let zenith_x = t * t * (0.00166 * s3 - 0.00375 * s2 + 0.00209 * s)
+            + t * (-0.02903 * s3 + 0.06377 * s2 - 0.03202 * s + 0.00394)
+            + (0.11693 * s3 - 0.21196 * s2 + 0.06052 * s + 0.25886);
+        let zenith_y = t * t * (0.00275 * s3 - 0.00610 * s2 + 0.00317 * s)
+            + t * (-0.04214 * s3 + 0.08970 * s2 - 0.04153 * s + 0.00516)
+            + (0.15346 * s3 - 0.26756 * s2 + 0.06670 * s + 0.26688);
+
+        return PreethamSky {
+            sun_dir,
+            turbidity,
+            theta_sun,
+            perez_y,
+            perez_x,
+            perez_yy,
+            zenith: Vec3::new(zenith_x, zenith_y, zenith_yy),
+        };
+    }
+
+    // elevation above the horizon and azimuth measured from -z towards +x, in degrees
+    pub fn sun_direction(elevation: f64, azimuth: f64) -> Vec3 {
+        let elevation = degrees_to_radians(elevation);
+        let azimuth = degrees_to_radians(azimuth);
+        return Vec3::new(
+            elevation.cos() * azimuth.sin(),
+            elevation.sin(),
+            -elevation.cos() * azimuth.cos(),
+        );
+    }
+
+    // Sun light matching this sky: same direction, colored by the atmospheric transmittance.
+    pub fn sun_light(&self, irradiance: f64) -> SunLight {
+        return SunLight::new(
+            self.sun_dir,
+            SUN_ANGULAR_RADIUS,
+            irradiance * self.sun_transmittance(),
+        );
+    }
+
+    // Rayleigh and aerosol extinction along the path to the sun at 680, 550 and 440 nm
+    fn sun_transmittance(&self) -> Color {
+        let theta_degrees = self.theta_sun.to_degrees();
+        let air_mass = 1.0 / (self.theta_sun.cos() + 0.15 * (93.885 - theta_degrees).powf(-1.253));
+        let beta = 0.04608 * self.turbidity - 0.04586;
+        let alpha = 1.3;
+        let transmittance = |lambda: f64| {
+            let rayleigh = (-0.008735 * lambda.powf(-4.08) * air_mass).exp();
+            let aerosol = (-beta * lambda.powf(-alpha) * air_mass).exp();
+            return rayleigh * aerosol;
+        };
+        return Color::new(
+            transmittance(0.68),
+            transmittance(0.55),
+            transmittance(0.44),
+        );
+    }
+
+    fn perez(coefficients: &[f64; 5], cos_theta: f64, gamma: f64) -> f64 {
+        let [a, b, c, d, e] = *coefficients;
+        let cos_gamma = gamma.cos();
+        return (1.0 + a * (b / cos_theta).exp())
+            * (1.0 + c * (d * gamma).exp() + e * cos_gamma * cos_gamma);
+    }
+}
+
+impl Environment for PreethamSky {
+    fn emitted(&self, dir: Vec3) -> Color {
+        let unit_dir = dir.unit_vector();
+        // below the horizon the horizon color is continued
+        let cos_theta = unit_dir.y.max(0.01);
+        let gamma = Vec3::dot(unit_dir, self.sun_dir).clamp(-1.0, 1.0).acos();
+
+        let ratio = |coefficients: &[f64; 5]| {
+            return PreethamSky::perez(coefficients, cos_theta, gamma)
+                / PreethamSky::perez(coefficients, 1.0, self.theta_sun);
+        };
+        let x = self.zenith.x * ratio(&self.perez_x);
+        let y = self.zenith.y * ratio(&self.perez_y);
+        let luminance = self.zenith.z * ratio(&self.perez_yy) * LUMINANCE_SCALE;
+
+        // xyY to XYZ to linear sRGB
+        let cx = x / y * luminance;
+        let cy = luminance;
+        let cz = (1.0 - x - y) / y * luminance;
+        let color = Color::new(
+            3.2404542 * cx - 1.5371385 * cy - 0.4985314 * cz,
+            -0.9692660 * cx + 1.8760108 * cy + 0.0415560 * cz,
+            0.0556434 * cx - 0.2040259 * cy + 1.0572252 * cz,
+        );
+        return color.clamp(0.0, f64::INFINITY);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sky_is_blue_away_from_sun() {
+        let sky = PreethamSky::new(PreethamSky::sun_direction(30.0, 0.0), 3.0);
+        let c = sky.emitted(Vec3::new(0.0, 1.0, 1.0));
+        assert!(c.z > c.x);
+    }
+
+    #[test]
+    fn test_sun_reddens_near_horizon() {
+        let high = PreethamSky::new(PreethamSky::sun_direction(70.0, 0.0), 3.0);
+        let low = PreethamSky::new(PreethamSky::sun_direction(5.0, 0.0), 3.0);
+        let high_t = high.sun_transmittance();
+        let low_t = low.sun_transmittance();
+        assert!(low_t.z / low_t.x < high_t.z / high_t.x);
+        assert!(low_t.y < high_t.y);
+    }
+}
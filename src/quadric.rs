@@ -0,0 +1,283 @@
+use std::f64::consts::PI;
+
+use crate::{
+    aabb::Aabb,
+    hittable::{HitRecord, Hittable},
+    material::Material,
+    polynomial::solve_quadratic,
+    ray::Ray,
+    vec3::{Point, Vec3},
+};
+
+// Implicit surface
+//   a x^2 + b y^2 + c z^2 + d xy + e xz + f yz + g x + h y + i z + j = 0
+// in coordinates relative to center, clipped to the box [min, max] since most quadrics
+// are unbounded. The coefficients are stored in that order.
+pub struct Quadric {
+    center: Point,
+    coefficients: [f64; 10],
+    min: Vec3,
+    max: Vec3,
+    material: Box<dyn Material>,
+}
+
+impl Quadric {
+    pub fn new(
+        center: Point,
+        coefficients: [f64; 10],
+        min: Vec3,
+        max: Vec3,
+        material: Box<dyn Material>,
+    ) -> Quadric {
+        return Quadric {
+            center,
+            coefficients,
+            min,
+            max,
+            material,
+        };
+    }
+
+    pub fn ellipsoid(center: Point, radii: Vec3, material: Box<dyn Material>) -> Quadric {
+        let inv = |r: f64| 1.0 / (r * r);
+        let coefficients = [
+            inv(radii.x),
+            inv(radii.y),
+            inv(radii.z),
+            0.0,
+            0.0,
+            0.0,
+            0.0,
+            0.0,
+            0.0,
+            -1.0,
+        ];
+        return Quadric::new(center, coefficients, -1.0 * radii, radii, material);
+    }
+
+    // Open tube around the y axis from the center up to height.
+    pub fn cylinder(
+        center: Point,
+        radius: f64,
+        height: f64,
+        material: Box<dyn Material>,
+    ) -> Quadric {
+        let coefficients = [
+            1.0,
+            0.0,
+            1.0,
+            0.0,
+            0.0,
+            0.0,
+            0.0,
+            0.0,
+            0.0,
+            -radius * radius,
+        ];
+        return Quadric::new(
+            center,
+            coefficients,
+            upright_min(radius),
+            upright_max(radius, height),
+            material,
+        );
+    }
+
+    // Apex at the center, opening upwards to radius at height.
+    pub fn cone(center: Point, radius: f64, height: f64, material: Box<dyn Material>) -> Quadric {
+        let slope = radius / height;
+        let coefficients = [1.0, -slope * slope, 1.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0];
+        return Quadric::new(
+            center,
+            coefficients,
+            upright_min(radius),
+            upright_max(radius, height),
+            material,
+        );
+    }
+
+    // Bowl with its bottom at the center, radius wide at height.
+    pub fn paraboloid(
+        center: Point,
+        radius: f64,
+        height: f64,
+        material: Box<dyn Material>,
+    ) -> Quadric {
+        let coefficients = [
+            1.0,
+            0.0,
+            1.0,
+            0.0,
+            0.0,
+            0.0,
+            0.0,
+            -radius * radius / height,
+            0.0,
+            0.0,
+        ];
+        return Quadric::new(
+            center,
+            coefficients,
+            upright_min(radius),
+            upright_max(radius, height),
+            material,
+        );
+    }
+
+    // Hyperboloid of one sheet, radius at the waist through the center, extending
+    // height / 2 up and down.
+    pub fn hyperboloid(
+        center: Point,
+        radius: f64,
+        height: f64,
+        material: Box<dyn Material>,
+    ) -> Quadric {
+        let half = 0.5 * height;
+        // as wide again at the ends as the waist
+        let k = 3.0 * radius * radius / (half * half);
+        let coefficients = [1.0, -k, 1.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, -radius * radius];
+        let top = 2.0 * radius;
+        return Quadric::new(
+            center,
+            coefficients,
+            Vec3::new(-top, -half, -top),
+            Vec3::new(top, half, top),
+            material,
+        );
+    }
+
+    fn inside_bounds(&self, p: Point) -> bool {
+        return self.min.x <= p.x
+            && p.x <= self.max.x
+            && self.min.y <= p.y
+            && p.y <= self.max.y
+            && self.min.z <= p.z
+            && p.z <= self.max.z;
+    }
+
+    fn gradient(&self, p: Point) -> Vec3 {
+        let [a, b, c, d, e, f, g, h, i, _] = self.coefficients;
+        return Vec3::new(
+            2.0 * a * p.x + d * p.y + e * p.z + g,
+            2.0 * b * p.y + d * p.x + f * p.z + h,
+            2.0 * c * p.z + e * p.x + f * p.y + i,
+        );
+    }
+}
+
+fn upright_min(radius: f64) -> Vec3 {
+    return Vec3::new(-radius, 0.0, -radius);
+}
+
+fn upright_max(radius: f64, height: f64) -> Vec3 {
+    return Vec3::new(radius, height, radius);
+}
+
+impl Hittable for Quadric {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        let [a, b, c, d, e, f, g, h, i, j] = self.coefficients;
+        let o = ray.org - self.center;
+        let v = ray.dir;
+
+        let qa = a * v.x * v.x
+            + b * v.y * v.y
+            + c * v.z * v.z
+            + d * v.x * v.y
+            + e * v.x * v.z
+            + f * v.y * v.z;
+        let qb = 2.0 * (a * o.x * v.x + b * o.y * v.y + c * o.z * v.z)
+            + d * (o.x * v.y + o.y * v.x)
+            + e * (o.x * v.z + o.z * v.x)
+            + f * (o.y * v.z + o.z * v.y)
+            + g * v.x
+            + h * v.y
+            + i * v.z;
+        let qc = a * o.x * o.x
+            + b * o.y * o.y
+            + c * o.z * o.z
+            + d * o.x * o.y
+            + e * o.x * o.z
+            + f * o.y * o.z
+            + g * o.x
+            + h * o.y
+            + i * o.z
+            + j;
+
+        let mut roots = solve_quadratic([qc, qb, qa]);
+        roots.sort_by(|x, y| x.total_cmp(y));
+        for t in roots {
+            if t <= t_min || t >= t_max {
+                continue;
+            }
+            let local = o + t * v;
+            if !self.inside_bounds(local) {
+                continue;
+            }
+            let outward_normal = self.gradient(local).unit_vector();
+            let front_face = HitRecord::is_front_face(ray, outward_normal);
+            let normal = HitRecord::get_normal(front_face, outward_normal);
+            let mut record = HitRecord::new(ray.at(t), normal, t, front_face, &*self.material);
+            // u around the y axis, v up through the bounds
+            record.u = (local.z.atan2(local.x) + PI) / (2.0 * PI);
+            record.v = (local.y - self.min.y) / (self.max.y - self.min.y);
+            record.dpdu = 2.0 * PI * Vec3::new(-local.z, 0.0, local.x);
+            record.dpdv = Vec3::new(0.0, self.max.y - self.min.y, 0.0);
+            return Some(record);
+        }
+        return None;
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        return Some(Aabb::new(self.center + self.min, self.center + self.max));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lambertian::Lambertian, vec3::Color};
+
+    const EPSILON: f64 = 0.000001;
+
+    fn material() -> Box<dyn Material> {
+        return Box::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+    }
+
+    #[test]
+    fn test_ellipsoid() {
+        let ellipsoid = Quadric::ellipsoid(Point::default(), Vec3::new(2.0, 1.0, 1.0), material());
+        let ray = Ray::new(Point::new(-5.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        let record = ellipsoid.hit(&ray, 0.001, f64::INFINITY).unwrap();
+        assert!((record.t - 3.0).abs() <= EPSILON);
+        assert!((record.normal.x + 1.0).abs() <= EPSILON);
+    }
+
+    #[test]
+    fn test_cylinder_is_clipped() {
+        let cylinder = Quadric::cylinder(Point::new(0.0, 1.0, 0.0), 0.5, 2.0, material());
+        let ray = Ray::new(Point::new(-5.0, 2.0, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        let record = cylinder.hit(&ray, 0.001, f64::INFINITY).unwrap();
+        assert!((record.t - 4.5).abs() <= EPSILON);
+        assert!((record.v - 0.5).abs() <= EPSILON);
+
+        // above the top
+        let above = Ray::new(Point::new(-5.0, 3.5, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        assert!(cylinder.hit(&above, 0.001, f64::INFINITY).is_none());
+
+        // down the open tube only the inside wall is hit, from the inside
+        let down = Ray::new(Point::new(0.0, 2.9, 0.0), Vec3::new(0.3, -1.0, 0.0));
+        let record = cylinder.hit(&down, 0.001, f64::INFINITY).unwrap();
+        assert!(!record.front_face);
+    }
+
+    #[test]
+    fn test_cone_normal() {
+        let cone = Quadric::cone(Point::default(), 1.0, 1.0, material());
+        let ray = Ray::new(Point::new(-5.0, 0.5, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        let record = cone.hit(&ray, 0.001, f64::INFINITY).unwrap();
+        assert!((record.p.x + 0.5).abs() <= EPSILON);
+        // the 45 degree wall faces outwards and down
+        assert!((record.normal.x + 0.5_f64.sqrt()).abs() <= EPSILON);
+        assert!((record.normal.y + 0.5_f64.sqrt()).abs() <= EPSILON);
+    }
+}
@@ -4,6 +4,8 @@ use crate::vec3::{Point, Vec3};
 pub struct Ray {
     pub org: Vec3,
     pub dir: Vec3,
+    // in nanometres for paths of the spectral mode, None when rendering in RGB
+    pub wavelength: Option<f64>,
 }
 
 impl Ray {
@@ -11,7 +13,11 @@ impl Ray {
         return self.org + self.dir * t;
     }
     pub fn new(org: Vec3, dir: Vec3) -> Ray {
-        return Ray { org, dir };
+        return Ray {
+            org,
+            dir,
+            wavelength: None,
+        };
     }
 }
 
@@ -0,0 +1,135 @@
+use crate::{
+    aov::AovBuffers,
+    camera::Camera,
+    film::Film,
+    hittable::Hittable,
+    options::Options,
+    sampler::random,
+    scene::Scene,
+    spectrum::{sample_wavelength, spectral_to_rgb},
+};
+
+pub struct RenderOutput {
+    pub film: Film,
+    // only filled when auxiliary passes or denoising were requested
+    pub aovs: Option<AovBuffers>,
+}
+
+// A rectangle of pixels, x0 <= x < x1 and y0 <= y < y1.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Tile {
+    pub x0: u32,
+    pub y0: u32,
+    pub x1: u32,
+    pub y1: u32,
+}
+
+impl Tile {
+    // Tiles of the given size covering the image row by row, cut to fit at the edges.
+    pub fn split(width: u32, height: u32, size: u32) -> Vec<Tile> {
+        let mut tiles = vec![];
+        for y0 in (0..height).step_by(size as usize) {
+            for x0 in (0..width).step_by(size as usize) {
+                tiles.push(Tile {
+                    x0,
+                    y0,
+                    x1: (x0 + size).min(width),
+                    y1: (y0 + size).min(height),
+                });
+            }
+        }
+        return tiles;
+    }
+}
+
+pub fn render(scene: &Scene, camera: &dyn Camera, options: &Options, height: u32) -> RenderOutput {
+    let tile = Tile {
+        x0: 0,
+        y0: 0,
+        x1: options.width,
+        y1: height,
+    };
+    return render_tile(scene, camera, options, height, &tile);
+}
+
+// Takes the samples of the pixels in tile only, on a film of the whole image since the
+// filter and the paths traced from the lights reach past the tile.
+pub fn render_tile(
+    scene: &Scene,
+    camera: &dyn Camera,
+    options: &Options,
+    height: u32,
+    tile: &Tile,
+) -> RenderOutput {
+    let width = options.width;
+    let mut film = Film::new(width, height, options.make_filter().unwrap());
+    film.set_splat_scale(1.0 / f64::from(options.samples_per_pixel));
+    let mut integrator = options.make_integrator().unwrap();
+    // the denoiser is guided by the normal and albedo passes
+    let mut aovs = if options.aovs.is_empty() && !options.denoise {
+        None
+    } else {
+        Some(AovBuffers::new(width, height))
+    };
+
+    // one sample per pixel per pass, integrators may prepare for each pass
+    for pass in 0..options.samples_per_pixel {
+        eprint!("\rremain {:3}", options.samples_per_pixel - pass - 1);
+        integrator.prepare(scene, pass);
+        for i in tile.y0..tile.y1 {
+            for j in tile.x0..tile.x1 {
+                // raster position, y grows downwards
+                let x = f64::from(j) + random();
+                let y = f64::from(i) + random();
+                let u = x / f64::from(width);
+                let v = 1.0 - y / f64::from(height);
+                let mut ray = camera.get_ray(u, v);
+                let color = if options.spectral {
+                    let wavelength = sample_wavelength();
+                    ray.wavelength = Some(wavelength);
+                    let radiance = integrator.li(&ray, scene, camera, &mut film);
+                    spectral_to_rgb(radiance.x, wavelength)
+                } else {
+                    integrator.li(&ray, scene, camera, &mut film)
+                };
+                film.add_sample(x, y, color);
+
+                if let Some(aovs) = &mut aovs {
+                    let record = scene.world.hit(&ray, 0.0001, f64::INFINITY);
+                    aovs.add_sample(j, i, &ray, record.as_ref(), color);
+                }
+            }
+        }
+    }
+    return RenderOutput { film, aovs };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split() {
+        let tiles = Tile::split(70, 40, 32);
+        assert_eq!(tiles.len(), 6);
+        assert_eq!(
+            tiles[2],
+            Tile {
+                x0: 64,
+                y0: 0,
+                x1: 70,
+                y1: 32
+            }
+        );
+        // every pixel in exactly one tile
+        for y in 0..40 {
+            for x in 0..70 {
+                let count = tiles
+                    .iter()
+                    .filter(|t| (t.x0..t.x1).contains(&x) && (t.y0..t.y1).contains(&y))
+                    .count();
+                assert_eq!(count, 1);
+            }
+        }
+    }
+}
@@ -0,0 +1,109 @@
+use std::{cell::RefCell, rc::Rc};
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+// Source of the random numbers drawn while rendering: the film position, the lens, the
+// wavelength, light and environment samples, scattering and media. By default they come
+// from thread_rng. The Metropolis integrator installs its own sampler to replay a path
+// from the numbers it was made of and to mutate them.
+pub trait Sampler {
+    // uniform in [0, 1)
+    fn next(&mut self) -> f64;
+}
+
+// The same numbers for the same seed, for procedural scenes that have to come out alike
+// in every process of a render farm.
+pub struct SeededSampler {
+    rng: StdRng,
+}
+
+impl SeededSampler {
+    pub fn new(seed: u64) -> SeededSampler {
+        return SeededSampler {
+            rng: StdRng::seed_from_u64(seed),
+        };
+    }
+}
+
+impl Sampler for SeededSampler {
+    fn next(&mut self) -> f64 {
+        return self.rng.gen();
+    }
+}
+
+pub type SharedSampler = Rc<RefCell<dyn Sampler>>;
+
+thread_local! {
+    static SAMPLER: RefCell<Option<SharedSampler>> = const { RefCell::new(None) };
+}
+
+pub fn random() -> f64 {
+    let installed = SAMPLER.with(|sampler| sampler.borrow().clone());
+    return match installed {
+        Some(sampler) => sampler.borrow_mut().next(),
+        None => rand::thread_rng().gen(),
+    };
+}
+
+// uniform in [min, max)
+pub fn random_range(min: f64, max: f64) -> f64 {
+    return min + (max - min) * random();
+}
+
+// uniform in 0..n
+pub fn random_index(n: usize) -> usize {
+    return ((random() * n as f64) as usize).min(n - 1);
+}
+
+// Runs f with the draws of this thread coming from sampler.
+pub fn with_sampler<R>(sampler: SharedSampler, f: impl FnOnce() -> R) -> R {
+    let previous = SAMPLER.with(|installed| installed.replace(Some(sampler)));
+    let result = f();
+    SAMPLER.with(|installed| installed.replace(previous));
+    return result;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Sequence {
+        values: Vec<f64>,
+        next: usize,
+    }
+
+    impl Sampler for Sequence {
+        fn next(&mut self) -> f64 {
+            self.next += 1;
+            return self.values[self.next - 1];
+        }
+    }
+
+    #[test]
+    fn test_with_sampler() {
+        let sequence = Rc::new(RefCell::new(Sequence {
+            values: vec![0.25, 0.5, 0.99],
+            next: 0,
+        }));
+        let draws = with_sampler(sequence.clone(), || {
+            return (random(), random_range(2.0, 4.0), random_index(3));
+        });
+        assert_eq!(draws, (0.25, 3.0, 2));
+        assert_eq!(sequence.borrow().next, 3);
+
+        // thread_rng again afterwards
+        let x = random();
+        assert!((0.0..1.0).contains(&x));
+        assert_eq!(sequence.borrow().next, 3);
+    }
+
+    #[test]
+    fn test_seeded_sampler() {
+        let draw = |seed| {
+            let sampler = Rc::new(RefCell::new(SeededSampler::new(seed)));
+            return with_sampler(sampler, || (0..4).map(|_| random()).collect::<Vec<f64>>());
+        };
+        assert_eq!(draw(7), draw(7));
+        assert_ne!(draw(7), draw(8));
+    }
+}
@@ -0,0 +1,97 @@
+use crate::{
+    dielectric::Dielectric,
+    environment::Environment,
+    hittable_list::HittableList,
+    lambertian::Lambertian,
+    light::Light,
+    material_tag::MaterialTag,
+    metal::Metal,
+    sampler::{random, random_range},
+    sphere::Sphere,
+    vec3::{Color, Point, Vec3},
+};
+
+pub struct Scene {
+    pub world: HittableList,
+    pub environment: Box<dyn Environment>,
+    pub lights: Vec<Box<dyn Light>>,
+}
+
+impl Scene {
+    pub fn new(world: HittableList, environment: Box<dyn Environment>) -> Scene {
+        return Scene {
+            world,
+            environment,
+            lights: vec![],
+        };
+    }
+
+    pub fn add_light(&mut self, light: Box<dyn Light>) {
+        self.lights.push(light);
+    }
+}
+
+const RANGE: i32 = 11;
+
+// Each sphere has a material of its own, which takes the next of the material ids.
+pub fn random_scene(next_material_id: &mut usize) -> HittableList {
+    let mut world = HittableList::default();
+    let mut add = |object: Sphere| {
+        world.add(Box::new(MaterialTag::new(
+            Box::new(object),
+            *next_material_id,
+        )));
+        *next_material_id += 1;
+    };
+
+    // ground
+    add(Sphere::new(
+        Vec3::new(0.0, -1000.0, 0.0),
+        1000.0,
+        Box::new(Lambertian::new(Color::new(0.5, 0.5, 0.5))),
+    ));
+
+    for a in -RANGE..RANGE {
+        for b in -RANGE..RANGE {
+            let choose_mat = random();
+            let center = Point::new(
+                (a as f64) + 0.9 * random(),
+                0.2,
+                (b as f64) * 0.9 * random(),
+            );
+
+            if (center - Vec3::new(4.0, 0.2, 0.0)).length() > 0.9 {
+                if choose_mat < 0.8 {
+                    // diffuse
+                    let albedo = Color::random(0.0, 1.0) * Color::random(0.0, 1.0);
+                    add(Sphere::new(center, 0.2, Box::new(Lambertian::new(albedo))));
+                } else if choose_mat < 0.95 {
+                    // metal
+                    let albedo = Color::random(0.5, 1.0);
+                    let fizz = random_range(0.0, 0.5);
+                    add(Sphere::new(center, 0.2, Box::new(Metal::new(albedo, fizz))));
+                } else {
+                    // glass
+                    add(Sphere::new(center, 0.2, Box::new(Dielectric::new(1.5))));
+                }
+            }
+        }
+    }
+    add(Sphere::new(
+        Vec3::new(0.0, 1.0, 0.0),
+        1.0,
+        Box::new(Dielectric::new(1.5)),
+    ));
+    add(Sphere::new(
+        Vec3::new(-4.0, 1.0, 0.0),
+        1.0,
+        Box::new(Lambertian::new(Color::new(0.4, 0.2, 0.1))),
+    ));
+    add(Sphere::new(
+        Vec3::new(4.0, 1.0, 0.0),
+        1.0,
+        Box::new(Metal::new(Color::new(0.7, 0.6, 0.5), 0.0)),
+    ));
+
+    return world;
+}
@@ -0,0 +1,1363 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use crate::{
+    aabb::Aabb,
+    alpha_mask::AlphaMask,
+    aperture::{Aperture, CircularAperture, ImageAperture, PolygonAperture},
+    bvh::Bvh,
+    camera::Camera,
+    camera_path::{CameraAnimation, CameraState, Interpolation, KeyframePath, Turntable},
+    csg::{Csg, CsgOperation},
+    curve::{fur, Curve},
+    density_grid::{read_vol, DensityGrid},
+    dielectric::Dielectric,
+    directional_light::DirectionalLight,
+    environment::{ConstantEnvironment, Environment, GradientEnvironment},
+    equirectangular_camera::EquirectangularCamera,
+    fisheye_camera::FisheyeCamera,
+    gltf::{load_gltf, GltfCamera, Projection},
+    hdr::read_hdr,
+    heightfield::Heightfield,
+    hittable::Hittable,
+    hittable_list::HittableList,
+    image::load_image,
+    image_environment::ImageEnvironment,
+    lambertian::Lambertian,
+    light::Light,
+    material::Material,
+    material_tag::MaterialTag,
+    math::degrees_to_radians,
+    mesh::{Mesh, Subdivision},
+    metal::Metal,
+    normal_map::{BumpMap, NormalMap, Perturbed},
+    options::{parse_number, parse_vec3},
+    orthographic_camera::OrthographicCamera,
+    perspective_camera::{physical_aperture, physical_vfov, PerspectiveCamera},
+    ply::read_ply,
+    point_light::PointLight,
+    post_process::{
+        Bloom, ChromaticAberration, ColorLut, Exposure, LensDistortion, PostProcess, Vignette,
+        WhiteBalance,
+    },
+    preetham_sky::PreethamSky,
+    quadric::Quadric,
+    scene::{random_scene, Scene},
+    sdf::{
+        Sdf, SdfBox, SdfCapsule, SdfObject, SdfRepeat, SdfSmoothUnion, SdfSphere, SdfTorus,
+        SdfTranslate, SdfTwist, SdfUnion,
+    },
+    sphere::Sphere,
+    spot_light::SpotLight,
+    stl::read_stl,
+    subsurface::Subsurface,
+    texture::{ImageTexture, SolidColor, VertexColor},
+    tilt_shift_camera::TiltShiftCamera,
+    torus::Torus,
+    vec3::{Color, Vec3},
+    volume::{GridVolume, HenyeyGreenstein},
+};
+
+// The scene used when no scene file is given.
+pub const DEFAULT_SCENE: &str = "\
+camera type=perspective lookfrom=13,2,3 lookat=0,0,0 vup=0,1,0 vfov=20 aperture=0.1 focus_dist=10
+random_spheres
+";
+
+pub struct SceneDescription {
+    pub scene: Scene,
+    pub camera: Box<dyn Camera>,
+    pub aspect_ratio: f64,
+    pub animation: Option<Box<dyn CameraAnimation>>,
+    // applied to the framebuffer in file order
+    pub post: Vec<Box<dyn PostProcess>>,
+    camera_directive: Directive,
+    base_dir: PathBuf,
+}
+
+impl SceneDescription {
+    // Camera for one frame of the animation, or the still camera without one.
+    pub fn camera_at(&self, frame: f64) -> Result<Box<dyn Camera>, String> {
+        let animation = match &self.animation {
+            Some(v) => v,
+            None => {
+                return make_camera(
+                    &self.camera_directive,
+                    self.aspect_ratio,
+                    &self.scene.world,
+                    &self.base_dir,
+                )
+            }
+        };
+        let state = animation.state_at(frame);
+        let mut d = self.camera_directive.clone();
+        d.set("lookfrom", format_vec3(state.lookfrom));
+        d.set("lookat", format_vec3(state.lookat));
+        if let Some(vfov) = state.vfov {
+            d.set("vfov", vfov.to_string());
+        }
+        if let Some(focus_dist) = state.focus_dist {
+            d.set("focus_dist", focus_dist.to_string());
+        }
+        return make_camera(&d, self.aspect_ratio, &self.scene.world, &self.base_dir);
+    }
+}
+
+// One line of a scene file: a directive name followed by key=value parameters.
+// Vectors and colors are written as comma separated numbers, e.g. lookfrom=13,2,3.
+#[derive(Clone)]
+pub struct Directive {
+    pub name: String,
+    pub line: usize,
+    params: HashMap<String, String>,
+}
+
+impl Directive {
+    pub fn new(name: &str, line: usize) -> Directive {
+        return Directive {
+            name: name.to_string(),
+            line,
+            params: HashMap::new(),
+        };
+    }
+
+    pub fn parse(line_number: usize, line: &str) -> Result<Option<Directive>, String> {
+        let line = match line.find('#') {
+            Some(i) => &line[..i],
+            None => line,
+        };
+        let mut tokens = line.split_whitespace();
+        let name = match tokens.next() {
+            Some(v) => v.to_string(),
+            None => return Ok(None),
+        };
+        let mut params = HashMap::new();
+        for token in tokens {
+            let (key, value) = token.split_once('=').ok_or_else(|| {
+                format!("line {}: expected key=value, got {}", line_number, token)
+            })?;
+            params.insert(key.to_string(), value.to_string());
+        }
+        return Ok(Some(Directive {
+            name,
+            line: line_number,
+            params,
+        }));
+    }
+
+    pub fn error(&self, message: &str) -> String {
+        return format!("line {}: {}: {}", self.line, self.name, message);
+    }
+
+    pub fn set(&mut self, key: &str, value: String) {
+        self.params.insert(key.to_string(), value);
+    }
+
+    pub fn has(&self, key: &str) -> bool {
+        return self.params.contains_key(key);
+    }
+
+    pub fn string(&self, key: &str) -> Result<&str, String> {
+        return self
+            .params
+            .get(key)
+            .map(|v| v.as_str())
+            .ok_or_else(|| self.error(&format!("missing {}", key)));
+    }
+
+    pub fn string_or<'a>(&'a self, key: &str, default: &'a str) -> &'a str {
+        return self.params.get(key).map(|v| v.as_str()).unwrap_or(default);
+    }
+
+    pub fn number(&self, key: &str) -> Result<f64, String> {
+        return parse_number(self.string(key)?).map_err(|e| self.error(&e));
+    }
+
+    pub fn number_or(&self, key: &str, default: f64) -> Result<f64, String> {
+        if self.has(key) {
+            return self.number(key);
+        }
+        return Ok(default);
+    }
+
+    pub fn vec3(&self, key: &str) -> Result<Vec3, String> {
+        return parse_vec3(self.string(key)?).map_err(|e| self.error(&e));
+    }
+
+    pub fn vec3_or(&self, key: &str, default: Vec3) -> Result<Vec3, String> {
+        if self.has(key) {
+            return self.vec3(key);
+        }
+        return Ok(default);
+    }
+}
+
+// Text of the scene file at path, or of the default scene without one, and the
+// directory the files it refers to are relative to. A gltf file on its own has no text,
+// parse_gltf_scene loads it from the path.
+pub fn read_scene(path: Option<&str>) -> Result<(String, PathBuf), String> {
+    let path = match path {
+        Some(path) => path,
+        None => return Ok((DEFAULT_SCENE.to_string(), PathBuf::from("."))),
+    };
+    let base_dir = Path::new(path)
+        .parent()
+        .unwrap_or(Path::new("."))
+        .to_path_buf();
+    if is_gltf(path) {
+        return Ok((String::new(), base_dir));
+    }
+    let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    return Ok((text, base_dir));
+}
+
+pub fn is_gltf(path: &str) -> bool {
+    return path.ends_with(".gltf") || path.ends_with(".glb");
+}
+
+// A gltf file on its own renders through its first camera. The directive is built here
+// rather than written out as text, which couldn't hold paths with spaces or a #.
+pub fn parse_gltf_scene(path: &Path) -> Result<SceneDescription, String> {
+    let mut d = Directive::new("gltf", 1);
+    let file = path.file_name().unwrap_or_default().to_string_lossy();
+    d.set("file", file.into_owned());
+    d.set("camera", String::from("0"));
+    return build_scene(vec![d], path.parent().unwrap_or(Path::new(".")));
+}
+
+pub fn parse_scene(text: &str, base_dir: &Path) -> Result<SceneDescription, String> {
+    let mut directives = vec![];
+    for (i, line) in text.lines().enumerate() {
+        if let Some(d) = Directive::parse(i + 1, line)? {
+            directives.push(d);
+        }
+    }
+    return build_scene(directives, base_dir);
+}
+
+fn build_scene(directives: Vec<Directive>, base_dir: &Path) -> Result<SceneDescription, String> {
+    // the camera needs the aspect ratio, wherever the image line is
+    let mut aspect_ratio = 16.0 / 9.0;
+    for d in directives.iter().filter(|d| d.name == "image") {
+        aspect_ratio = d.number("aspect")?;
+    }
+
+    // named materials with their ids for the material id pass, from 1 in file order
+    let mut materials: HashMap<&str, (usize, &Directive)> = HashMap::new();
+    let mut next_material_id = 1;
+    let mut world = HittableList::default();
+    let mut environment: Box<dyn Environment> = Box::new(GradientEnvironment::new(
+        Color::new(1.0, 1.0, 1.0),
+        Color::new(0.5, 0.7, 1.0),
+    ));
+    let mut lights: Vec<Box<dyn Light>> = vec![];
+    // a camera line, or a camera imported from a gltf file
+    let mut camera_directive: Option<Directive> = None;
+    let mut keyframes: Vec<&Directive> = vec![];
+    let mut animation_directive: Option<&Directive> = None;
+    let mut post: Vec<Box<dyn PostProcess>> = vec![];
+    // named shapes wait to be used once by a csg directive instead of joining the world
+    let mut shapes: HashMap<&str, Box<dyn Hittable>> = HashMap::new();
+    // distance functions, also used once by the node or sdf_object that refers to them
+    let mut sdfs: HashMap<&str, Box<dyn Sdf>> = HashMap::new();
+
+    for d in &directives {
+        match d.name.as_str() {
+            "image" => {}
+            "camera" => camera_directive = Some(d.clone()),
+            "keyframe" => keyframes.push(d),
+            "animation" => animation_directive = Some(d),
+            "environment" => {
+                environment = make_environment(d, base_dir)?;
+                if d.string_or("type", "") == "sky" && d.number_or("sun", 5.0)? > 0.0 {
+                    lights.push(Box::new(make_sky(d)?.sun_light(d.number_or("sun", 5.0)?)));
+                }
+            }
+            "light" => lights.push(make_light(d)?),
+            "post" => post.push(make_post_process(d, base_dir)?),
+            "material" => {
+                materials.insert(d.string("name")?, (next_material_id, d));
+                next_material_id += 1;
+            }
+            "sphere" => {
+                let (id, material) = find_material(d, &materials, base_dir)?;
+                let sphere = Sphere::new(d.vec3("center")?, d.number("radius")?, material);
+                let sphere = Box::new(MaterialTag::new(Box::new(sphere), id));
+                add_shape(d, sphere, base_dir, &mut world, &mut shapes)?;
+            }
+            "csg" => {
+                let mut take = |key: &str| {
+                    let name = d.string(key)?;
+                    return shapes.remove(name).ok_or_else(|| {
+                        d.error(&format!("unknown or already used shape {}", name))
+                    });
+                };
+                let operation = CsgOperation::parse(d.string("op")?).map_err(|e| d.error(&e))?;
+                let csg = Csg::new(operation, take("a")?, take("b")?);
+                add_shape(d, Box::new(csg), base_dir, &mut world, &mut shapes)?;
+            }
+            "quadric" | "torus" | "heightfield" | "mesh" | "curve" | "fur" => {
+                let (id, material) = find_material(d, &materials, base_dir)?;
+                let shape = make_surface(d, material, base_dir)?;
+                let shape = Box::new(MaterialTag::new(shape, id));
+                add_shape(d, shape, base_dir, &mut world, &mut shapes)?;
+            }
+            "volume" => {
+                let volume = make_volume(d, base_dir)?;
+                add_shape(d, volume, base_dir, &mut world, &mut shapes)?;
+            }
+            "sdf" => {
+                let name = d.string("name")?;
+                if sdfs.contains_key(name) {
+                    return Err(d.error(&format!("sdf {} is already defined", name)));
+                }
+                let sdf = make_sdf(d, &mut sdfs)?;
+                sdfs.insert(name, sdf);
+            }
+            "sdf_object" => {
+                let (id, material) = find_material(d, &materials, base_dir)?;
+                let center = d.vec3_or("center", Vec3::default())?;
+                let sdf = Box::new(SdfTranslate::new(center, take_sdf(d, "sdf", &mut sdfs)?));
+                let bounds = if d.has("bound") {
+                    Some((center, d.number("bound")?))
+                } else {
+                    None
+                };
+                let object = Box::new(SdfObject::new(sdf, material, bounds));
+                let object = Box::new(MaterialTag::new(object, id));
+                add_shape(d, object, base_dir, &mut world, &mut shapes)?;
+            }
+            "gltf" => {
+                let path = resolve_path(base_dir, d.string("file")?);
+                let gltf = load_gltf(&path)?;
+                for warning in &gltf.warnings {
+                    eprintln!("{}", d.error(warning));
+                }
+                if d.has("camera") {
+                    let index = d.number("camera")? as usize;
+                    let camera = gltf
+                        .cameras
+                        .get(index)
+                        .ok_or_else(|| d.error(&format!("the file has no camera {}", index)))?;
+                    camera_directive = Some(gltf_camera_directive(d, camera)?);
+                }
+                // the materials of the file take the next ids
+                let first_id = next_material_id;
+                let used = gltf.object_materials.iter().flatten().max();
+                next_material_id += used.map_or(0, |material| material + 1);
+                let objects: Vec<Box<dyn Hittable>> = gltf
+                    .objects
+                    .into_iter()
+                    .zip(&gltf.object_materials)
+                    .map(|(object, material)| match material {
+                        Some(material) => Box::new(MaterialTag::new(object, first_id + material)),
+                        None => object,
+                    })
+                    .collect();
+                if !objects.is_empty() {
+                    add_shape(
+                        d,
+                        Box::new(Bvh::new(objects)),
+                        base_dir,
+                        &mut world,
+                        &mut shapes,
+                    )?;
+                }
+            }
+            "random_spheres" => world.extend(random_scene(&mut next_material_id)),
+            _ => return Err(d.error("unknown directive")),
+        }
+    }
+
+    // a named shape nothing used would be missing from the image without a word
+    if let Some(name) = shapes.keys().min() {
+        return Err(format!("shape {} is never used by a csg", name));
+    }
+    if let Some(name) = sdfs.keys().min() {
+        return Err(format!("sdf {} is never used", name));
+    }
+
+    // built last so autofocus can see the whole world
+    let camera_directive = camera_directive.ok_or("missing camera")?;
+    let camera = make_camera(&camera_directive, aspect_ratio, &world, base_dir)?;
+    let animation = make_animation(&camera_directive, &keyframes, animation_directive)?;
+    let mut scene = Scene::new(world, environment);
+    for light in lights {
+        scene.add_light(light);
+    }
+    return Ok(SceneDescription {
+        scene,
+        camera,
+        aspect_ratio,
+        animation,
+        post,
+        camera_directive,
+        base_dir: base_dir.to_path_buf(),
+    });
+}
+
+fn add_shape<'a>(
+    d: &'a Directive,
+    shape: Box<dyn Hittable>,
+    base_dir: &Path,
+    world: &mut HittableList,
+    shapes: &mut HashMap<&'a str, Box<dyn Hittable>>,
+) -> Result<(), String> {
+    // any shape can be cut out by an opacity map or made partly transparent
+    let shape: Box<dyn Hittable> = if d.has("opacity_map") {
+        let path = resolve_path(base_dir, d.string("opacity_map")?);
+        let map = ImageTexture::new(load_image(&path.to_string_lossy())?);
+        Box::new(AlphaMask::new(shape, Box::new(map)))
+    } else if d.has("opacity") {
+        let opacity = d.number("opacity")?;
+        let color = Color::new(opacity, opacity, opacity);
+        Box::new(AlphaMask::new(shape, Box::new(SolidColor::new(color))))
+    } else {
+        shape
+    };
+    if d.has("name") {
+        let name = d.string("name")?;
+        if shapes.contains_key(name) {
+            return Err(d.error(&format!("shape {} is already defined", name)));
+        }
+        shapes.insert(name, shape);
+    } else {
+        world.add(shape);
+    }
+    return Ok(());
+}
+
+// The material named by the material key of d, with its id.
+fn find_material(
+    d: &Directive,
+    materials: &HashMap<&str, (usize, &Directive)>,
+    base_dir: &Path,
+) -> Result<(usize, Box<dyn Material>), String> {
+    let name = d.string("material")?;
+    let (id, material) = materials
+        .get(name)
+        .ok_or_else(|| d.error(&format!("unknown material {}", name)))?;
+    return Ok((*id, make_material(material, base_dir)?));
+}
+
+fn make_surface(
+    d: &Directive,
+    material: Box<dyn Material>,
+    base_dir: &Path,
+) -> Result<Box<dyn Hittable>, String> {
+    let center = d.vec3_or("center", Vec3::default())?;
+    if d.name == "torus" {
+        let (major, minor) = (d.number("major")?, d.number("minor")?);
+        if major <= 0.0 || minor <= 0.0 {
+            return Err(d.error("major and minor must be positive"));
+        }
+        return Ok(Box::new(Torus::new(center, major, minor, material)));
+    }
+    if d.name == "mesh" {
+        // files keep their own size and shading unless asked otherwise
+        let (mut mesh, smooth) = if d.has("file") {
+            let path = resolve_path(base_dir, d.string("file")?);
+            let path = path.to_string_lossy();
+            let mesh = match path.rsplit_once('.').map(|(_, e)| e.to_lowercase()) {
+                Some(e) if e == "ply" => read_ply(&path)?,
+                Some(e) if e == "stl" => read_stl(&path)?,
+                _ => return Err(d.error(&format!("unknown mesh file format {}", path))),
+            };
+            (mesh, "false")
+        } else {
+            let mesh = match d.string("type")? {
+                "cube" => Mesh::cube(),
+                "tetrahedron" => Mesh::tetrahedron(),
+                "octahedron" => Mesh::octahedron(),
+                "icosahedron" => Mesh::icosahedron(),
+                other => return Err(d.error(&format!("unknown mesh type {}", other))),
+            };
+            (mesh, "true")
+        };
+        mesh.transform(d.number_or("size", 1.0)?, center);
+        if d.has("subdivide") {
+            let scheme = Subdivision::parse(d.string_or("scheme", "catmull_clark"))
+                .map_err(|e| d.error(&e))?;
+            // every level makes four times the faces
+            let levels = d.number("subdivide")?;
+            if !(0.0..=6.0).contains(&levels) {
+                return Err(d.error("subdivide must be between 0 and 6"));
+            }
+            mesh = mesh.subdivide(scheme, levels as u32);
+        }
+        if d.string_or("smooth", smooth) == "true" {
+            mesh.compute_normals();
+        }
+        return Ok(Box::new(mesh.into_bvh(material)));
+    }
+    if d.name == "curve" {
+        let cp = [d.vec3("p0")?, d.vec3("p1")?, d.vec3("p2")?, d.vec3("p3")?];
+        let width = d.number("width")?;
+        let width = (width, d.number_or("width_end", width)?);
+        return Ok(Box::new(Curve::new(cp, width, Rc::from(material))));
+    }
+    if d.name == "fur" {
+        let count = d.number("count")?;
+        if count < 0.0 {
+            return Err(d.error("count must not be negative"));
+        }
+        let fur = fur(
+            center,
+            d.number("radius")?,
+            count as usize,
+            d.number("length")?,
+            d.number_or("width", 0.01)?,
+            material,
+        );
+        return Ok(Box::new(fur));
+    }
+    if d.name == "heightfield" {
+        let path = resolve_path(base_dir, d.string("file")?);
+        let image = load_image(&path.to_string_lossy())?;
+        if image.width < 2 || image.height < 2 {
+            return Err(d.error("heightfield image must be at least 2x2"));
+        }
+        let origin = d.vec3_or("origin", Vec3::default())?;
+        let heightfield = Heightfield::new(&image, origin, d.vec3("size")?, material);
+        return Ok(Box::new(heightfield));
+    }
+
+    // the upright quadrics divide by both
+    let radius_height = || {
+        let (radius, height) = (d.number("radius")?, d.number("height")?);
+        if radius <= 0.0 || height <= 0.0 {
+            return Err(d.error("radius and height must be positive"));
+        }
+        return Ok((radius, height));
+    };
+    let quadric = match d.string("type")? {
+        "ellipsoid" => {
+            let radii = d.vec3("radii")?;
+            if radii.x <= 0.0 || radii.y <= 0.0 || radii.z <= 0.0 {
+                return Err(d.error("radii must be positive"));
+            }
+            Quadric::ellipsoid(center, radii, material)
+        }
+        "cylinder" => {
+            let (radius, height) = radius_height()?;
+            Quadric::cylinder(center, radius, height, material)
+        }
+        "cone" => {
+            let (radius, height) = radius_height()?;
+            Quadric::cone(center, radius, height, material)
+        }
+        "paraboloid" => {
+            let (radius, height) = radius_height()?;
+            Quadric::paraboloid(center, radius, height, material)
+        }
+        "hyperboloid" => {
+            let (radius, height) = radius_height()?;
+            Quadric::hyperboloid(center, radius, height, material)
+        }
+        "general" => {
+            let mut coefficients = [0.0; 10];
+            let keys = ["xx", "yy", "zz", "xy", "xz", "yz", "x", "y", "z", "c"];
+            for (coefficient, key) in coefficients.iter_mut().zip(keys) {
+                *coefficient = d.number_or(key, 0.0)?;
+            }
+            Quadric::new(
+                center,
+                coefficients,
+                d.vec3("min")?,
+                d.vec3("max")?,
+                material,
+            )
+        }
+        other => return Err(d.error(&format!("unknown quadric type {}", other))),
+    };
+    return Ok(Box::new(quadric));
+}
+
+fn take_sdf(
+    d: &Directive,
+    key: &str,
+    sdfs: &mut HashMap<&str, Box<dyn Sdf>>,
+) -> Result<Box<dyn Sdf>, String> {
+    let name = d.string(key)?;
+    return sdfs
+        .remove(name)
+        .ok_or_else(|| d.error(&format!("unknown or already used sdf {}", name)));
+}
+
+// Primitives are centered on the origin, combinators refer to earlier nodes by name.
+fn make_sdf(d: &Directive, sdfs: &mut HashMap<&str, Box<dyn Sdf>>) -> Result<Box<dyn Sdf>, String> {
+    let sdf: Box<dyn Sdf> = match d.string("type")? {
+        "sphere" => Box::new(SdfSphere::new(d.number("radius")?)),
+        "box" => Box::new(SdfBox::new(
+            0.5 * d.vec3("size")?,
+            d.number_or("rounding", 0.0)?,
+        )),
+        "torus" => Box::new(SdfTorus::new(d.number("major")?, d.number("minor")?)),
+        "capsule" => Box::new(SdfCapsule::new(
+            d.vec3("a")?,
+            d.vec3("b")?,
+            d.number("radius")?,
+        )),
+        "translate" => Box::new(SdfTranslate::new(
+            d.vec3("offset")?,
+            take_sdf(d, "of", sdfs)?,
+        )),
+        "union" => Box::new(SdfUnion::new(
+            take_sdf(d, "a", sdfs)?,
+            take_sdf(d, "b", sdfs)?,
+        )),
+        "smooth_union" => Box::new(SdfSmoothUnion::new(
+            take_sdf(d, "a", sdfs)?,
+            take_sdf(d, "b", sdfs)?,
+            d.number_or("k", 0.25)?,
+        )),
+        "repeat" => Box::new(SdfRepeat::new(d.vec3("period")?, take_sdf(d, "of", sdfs)?)),
+        "twist" => Box::new(SdfTwist::new(d.number("amount")?, take_sdf(d, "of", sdfs)?)),
+        other => return Err(d.error(&format!("unknown sdf type {}", other))),
+    };
+    return Ok(sdf);
+}
+
+// keyframe frame=.. with any of lookfrom lookat vfov focus_dist, unset values are carried
+// over from the previous keyframe or the camera.
+// animation type=keyframes interpolation=linear|catmull_rom, or type=turntable frames=..
+fn make_animation(
+    camera: &Directive,
+    keyframes: &[&Directive],
+    animation: Option<&Directive>,
+) -> Result<Option<Box<dyn CameraAnimation>>, String> {
+    let optional =
+        |d: &Directive, key: &str, previous: Option<f64>| -> Result<Option<f64>, String> {
+            if d.has(key) {
+                return Ok(Some(d.number(key)?));
+            }
+            return Ok(previous);
+        };
+    let base = CameraState {
+        lookfrom: camera.vec3("lookfrom")?,
+        lookat: camera.vec3("lookat")?,
+        vfov: optional(camera, "vfov", None)?,
+        focus_dist: optional(camera, "focus_dist", None)?,
+    };
+
+    let kind = match animation {
+        Some(d) => d.string_or("type", "keyframes"),
+        None if keyframes.is_empty() => return Ok(None),
+        None => "keyframes",
+    };
+    match kind {
+        "turntable" => {
+            let d = animation.unwrap();
+            return Ok(Some(Box::new(Turntable::new(
+                base,
+                d.number("frames")? as u32,
+            ))));
+        }
+        "keyframes" => {
+            if keyframes.is_empty() {
+                return Err(animation.unwrap().error("no keyframes"));
+            }
+            let interpolation =
+                match animation.map_or("linear", |d| d.string_or("interpolation", "linear")) {
+                    "linear" => Interpolation::Linear,
+                    "catmull_rom" => Interpolation::CatmullRom,
+                    other => return Err(format!("unknown interpolation {}", other)),
+                };
+            let mut previous = base;
+            let mut states = vec![];
+            for d in keyframes {
+                let state = CameraState {
+                    lookfrom: d.vec3_or("lookfrom", previous.lookfrom)?,
+                    lookat: d.vec3_or("lookat", previous.lookat)?,
+                    vfov: optional(d, "vfov", previous.vfov)?,
+                    focus_dist: optional(d, "focus_dist", previous.focus_dist)?,
+                };
+                states.push((d.number("frame")?, state));
+                previous = state;
+            }
+            return Ok(Some(Box::new(KeyframePath::new(states, interpolation))));
+        }
+        other => return Err(format!("unknown animation type {}", other)),
+    }
+}
+
+// The camera line equivalent to a camera imported from a gltf file.
+fn gltf_camera_directive(d: &Directive, camera: &GltfCamera) -> Result<Directive, String> {
+    let projection = match camera.projection {
+        Projection::Perspective { vfov } => format!("type=perspective vfov={}", vfov),
+        Projection::Orthographic { height } => format!("type=orthographic height={}", height),
+    };
+    let line = format!(
+        "camera {} lookfrom={} lookat={} vup={}",
+        projection,
+        format_vec3(camera.lookfrom),
+        format_vec3(camera.lookat),
+        format_vec3(camera.vup)
+    );
+    return Ok(Directive::parse(d.line, &line)?.unwrap());
+}
+
+fn format_vec3(v: Vec3) -> String {
+    return format!("{},{},{}", v.x, v.y, v.z);
+}
+
+fn make_camera(
+    d: &Directive,
+    aspect_ratio: f64,
+    world: &HittableList,
+    base_dir: &Path,
+) -> Result<Box<dyn Camera>, String> {
+    let lookfrom = d.vec3("lookfrom")?;
+    let lookat = d.vec3("lookat")?;
+    let vup = d.vec3_or("vup", Vec3::new(0.0, 1.0, 0.0))?;
+    let camera: Box<dyn Camera> = match d.string_or("type", "perspective") {
+        "perspective" => Box::new(make_perspective_camera(d, aspect_ratio, world, base_dir)?),
+        "orthographic" => Box::new(OrthographicCamera::new(
+            lookfrom,
+            lookat,
+            vup,
+            d.number("height")?,
+            aspect_ratio,
+        )),
+        "fisheye" => Box::new(FisheyeCamera::new(
+            lookfrom,
+            lookat,
+            vup,
+            d.number_or("fov", 180.0)?,
+            aspect_ratio,
+        )),
+        "equirectangular" => Box::new(EquirectangularCamera::new(lookfrom, lookat, vup)),
+        "tiltshift" => {
+            let shift = d.string_or("shift", "0,0");
+            let (x, y) = shift
+                .split_once(',')
+                .ok_or_else(|| d.error(&format!("invalid shift {}", shift)))?;
+            Box::new(TiltShiftCamera::new(
+                lookfrom,
+                lookat,
+                vup,
+                d.number_or("vfov", 40.0)?,
+                aspect_ratio,
+                d.number_or("aperture", 0.0)?,
+                d.number_or("focus_dist", (lookfrom - lookat).length())?,
+                d.number_or("tilt", 0.0)?,
+                (parse_number(x)?, parse_number(y)?),
+            ))
+        }
+        other => return Err(d.error(&format!("unknown camera type {}", other))),
+    };
+    return Ok(camera);
+}
+
+// Either vfov and aperture, or a physical lens with focal_length and fstop in front of a
+// sensor (width in millimetres, 36 by default). Scene units are metres.
+fn make_perspective_camera(
+    d: &Directive,
+    aspect_ratio: f64,
+    world: &HittableList,
+    base_dir: &Path,
+) -> Result<PerspectiveCamera, String> {
+    let lookfrom = d.vec3("lookfrom")?;
+    let lookat = d.vec3("lookat")?;
+    let (vfov, aperture) = if d.has("focal_length") {
+        let focal_length = d.number("focal_length")?;
+        let sensor_width = d.number_or("sensor", 36.0)?;
+        let vfov = physical_vfov(sensor_width, focal_length, aspect_ratio);
+        let aperture = if d.has("fstop") {
+            physical_aperture(focal_length, d.number("fstop")?)
+        } else {
+            0.0
+        };
+        (vfov, aperture)
+    } else {
+        (d.number_or("vfov", 40.0)?, d.number_or("aperture", 0.0)?)
+    };
+
+    let mut camera = PerspectiveCamera::new(
+        lookfrom,
+        lookat,
+        d.vec3_or("vup", Vec3::new(0.0, 1.0, 0.0))?,
+        vfov,
+        aspect_ratio,
+        aperture,
+        d.number_or("focus_dist", (lookfrom - lookat).length())?,
+    );
+
+    let shape: Box<dyn Aperture> = match d.string_or("aperture_shape", "circle") {
+        "circle" => Box::new(CircularAperture {}),
+        "polygon" => Box::new(PolygonAperture::new(
+            d.number_or("blades", 6.0)? as u32,
+            degrees_to_radians(d.number_or("blade_rotation", 0.0)?),
+        )),
+        file => {
+            let path = resolve_path(base_dir, file);
+            Box::new(ImageAperture::new(&load_image(&path.to_string_lossy())?))
+        }
+    };
+    camera.set_aperture_shape(shape);
+
+    if d.has("autofocus") {
+        let point = d.string("autofocus")?;
+        let (s, t) = point
+            .split_once(',')
+            .ok_or_else(|| d.error(&format!("invalid autofocus point {}", point)))?;
+        // autofocus is given in image coordinates with t = 0 at the top
+        let focus_dist = camera.autofocus(world, parse_number(s)?, 1.0 - parse_number::<f64>(t)?);
+        if focus_dist.is_none() {
+            eprintln!(
+                "{}",
+                d.error("autofocus ray hit nothing, keeping focus_dist")
+            );
+        }
+    }
+    return Ok(camera);
+}
+
+fn make_sky(d: &Directive) -> Result<PreethamSky, String> {
+    let sun_dir = PreethamSky::sun_direction(
+        d.number_or("elevation", 45.0)?,
+        d.number_or("azimuth", 0.0)?,
+    );
+    return Ok(PreethamSky::new(sun_dir, d.number_or("turbidity", 3.0)?));
+}
+
+fn make_environment(d: &Directive, base_dir: &Path) -> Result<Box<dyn Environment>, String> {
+    let intensity = d.number_or("intensity", 1.0)?;
+    let environment: Box<dyn Environment> = match d.string_or("type", "gradient") {
+        "gradient" => Box::new(GradientEnvironment::new(
+            intensity * d.vec3_or("bottom", Color::new(1.0, 1.0, 1.0))?,
+            intensity * d.vec3_or("top", Color::new(0.5, 0.7, 1.0))?,
+        )),
+        "constant" => Box::new(ConstantEnvironment::new(intensity * d.vec3("color")?)),
+        "hdr" => {
+            let path = resolve_path(base_dir, d.string("file")?);
+            let image = read_hdr(&path.to_string_lossy())?;
+            Box::new(ImageEnvironment::new(
+                image,
+                d.number_or("rotation", 0.0)?,
+                intensity,
+            ))
+        }
+        "sky" => Box::new(make_sky(d)?),
+        other => return Err(d.error(&format!("unknown environment type {}", other))),
+    };
+    return Ok(environment);
+}
+
+fn make_light(d: &Directive) -> Result<Box<dyn Light>, String> {
+    let color = d.vec3_or("color", Color::new(1.0, 1.0, 1.0))?;
+    let light: Box<dyn Light> = match d.string("type")? {
+        "point" => Box::new(PointLight::new(
+            d.vec3("position")?,
+            d.number("power")? * color,
+        )),
+        "spot" => {
+            let position = d.vec3("position")?;
+            let target = d.vec3("target")?;
+            let inner = degrees_to_radians(d.number_or("inner", 20.0)?);
+            let outer = degrees_to_radians(d.number_or("outer", 30.0)?);
+            SpotLight::check(position, target, inner, outer).map_err(|e| d.error(&e))?;
+            Box::new(SpotLight::new(
+                position,
+                target,
+                d.number("power")? * color,
+                inner,
+                outer,
+            ))
+        }
+        "directional" => {
+            let direction = d.vec3("direction")?;
+            DirectionalLight::check(direction).map_err(|e| d.error(&e))?;
+            Box::new(DirectionalLight::new(
+                direction,
+                d.number("irradiance")? * color,
+            ))
+        }
+        other => return Err(d.error(&format!("unknown light type {}", other))),
+    };
+    return Ok(light);
+}
+
+fn make_post_process(d: &Directive, base_dir: &Path) -> Result<Box<dyn PostProcess>, String> {
+    let stage: Box<dyn PostProcess> = match d.string("type")? {
+        "exposure" => Box::new(Exposure::new(d.number("stops")?)),
+        "white_balance" => {
+            // the green gain is scaled by 1 - tint
+            let tint = d.number_or("tint", 0.0)?;
+            if tint <= -1.0 || tint >= 1.0 {
+                return Err(d.error("tint must be between -1 and 1"));
+            }
+            Box::new(WhiteBalance::new(d.number("temperature")?, tint))
+        }
+        "vignette" => Box::new(Vignette::new(
+            d.number_or("strength", 0.5)?,
+            d.number_or("falloff", 2.0)?,
+        )),
+        "bloom" => Box::new(Bloom::new(
+            d.number_or("threshold", 1.0)?,
+            d.number_or("radius", 0.02)?,
+            d.number_or("intensity", 0.5)?,
+        )),
+        "chromatic_aberration" => {
+            Box::new(ChromaticAberration::new(d.number_or("strength", 0.005)?))
+        }
+        "distortion" => Box::new(LensDistortion::new(
+            d.number_or("k1", 0.0)?,
+            d.number_or("k2", 0.0)?,
+        )),
+        "lut" => {
+            let path = resolve_path(base_dir, d.string("file")?);
+            Box::new(ColorLut::load(&path.to_string_lossy())?)
+        }
+        other => return Err(d.error(&format!("unknown post process type {}", other))),
+    };
+    return Ok(stage);
+}
+
+fn make_volume(d: &Directive, base_dir: &Path) -> Result<Box<dyn Hittable>, String> {
+    let (grid, file_bounds) = match d.string("type")? {
+        "constant" => (DensityGrid::constant(1.0), None),
+        // fractal noise filling the box, or shaped into a ball for clouds
+        kind @ ("noise" | "cloud") => {
+            let resolution = d.number_or("resolution", 64.0)? as usize;
+            if !(2..=256).contains(&resolution) {
+                return Err(d.error("resolution must be between 2 and 256"));
+            }
+            let grid = DensityGrid::noise(
+                resolution,
+                d.number_or("frequency", 4.0)?,
+                d.number_or("octaves", 5.0)? as u32,
+                d.number_or("threshold", 0.4)?.clamp(0.0, 0.99),
+                kind == "cloud",
+                d.number_or("seed", 0.0)? as u32,
+            );
+            (grid, None)
+        }
+        "file" => {
+            let path = resolve_path(base_dir, d.string("file")?);
+            let (grid, bounds) = read_vol(&path.to_string_lossy())?;
+            (grid, Some(bounds))
+        }
+        other => return Err(d.error(&format!("unknown volume type {}", other))),
+    };
+    // files bring their own box unless min and max move it
+    let bounds = match file_bounds {
+        Some(bounds) if !d.has("min") => bounds,
+        _ => Aabb::new(d.vec3("min")?, d.vec3("max")?),
+    };
+    let phase = HenyeyGreenstein::new(
+        d.vec3_or("albedo", Color::new(0.9, 0.9, 0.9))?,
+        d.number_or("g", 0.0)?,
+    );
+    let density = d.number_or("density", 1.0)?;
+    return Ok(Box::new(GridVolume::new(bounds, grid, density, phase)));
+}
+
+fn make_material(d: &Directive, base_dir: &Path) -> Result<Box<dyn Material>, String> {
+    let material: Box<dyn Material> = match d.string("type")? {
+        "lambertian" if d.string_or("texture", "") == "vertex_color" => {
+            let fallback = d.vec3_or("albedo", Color::new(0.8, 0.8, 0.8))?;
+            Box::new(Lambertian::textured(Box::new(VertexColor::new(fallback))))
+        }
+        "lambertian" => Box::new(Lambertian::new(d.vec3("albedo")?)),
+        "metal" => Box::new(Metal::new(d.vec3("albedo")?, d.number_or("fuzz", 0.0)?)),
+        "dielectric" if d.has("abbe") => {
+            // dispersion goes as 1 / abbe
+            let abbe = d.number("abbe")?;
+            if abbe <= 0.0 {
+                return Err(d.error("abbe must be positive"));
+            }
+            Box::new(Dielectric::dispersive(d.number_or("ior", 1.5)?, abbe))
+        }
+        "dielectric" => Box::new(Dielectric::new(d.number_or("ior", 1.5)?)),
+        "subsurface" => {
+            let mean_free_path = d.vec3("mean_free_path")?;
+            if mean_free_path.x <= 0.0 || mean_free_path.y <= 0.0 || mean_free_path.z <= 0.0 {
+                return Err(d.error("mean_free_path must be positive"));
+            }
+            Box::new(Subsurface::new(
+                d.vec3("albedo")?,
+                mean_free_path,
+                d.number_or("ior", 1.4)?,
+            ))
+        }
+        other => return Err(d.error(&format!("unknown material type {}", other))),
+    };
+
+    // surface detail from a tangent space normal map or a grayscale bump map
+    let image = |key: &str| {
+        let path = resolve_path(base_dir, d.string(key)?);
+        return load_image(&path.to_string_lossy());
+    };
+    if d.has("normal_map") {
+        let map = NormalMap::new(image("normal_map")?, d.number_or("normal_strength", 1.0)?);
+        return Ok(Box::new(Perturbed::new(material, Box::new(map))));
+    }
+    if d.has("bump_map") {
+        let map = BumpMap::new(image("bump_map")?, d.number_or("bump_height", 0.02)?);
+        return Ok(Box::new(Perturbed::new(material, Box::new(map))));
+    }
+    return Ok(material);
+}
+
+pub fn resolve_path(base_dir: &Path, path: &str) -> PathBuf {
+    let path = Path::new(path);
+    if path.is_absolute() {
+        return path.to_path_buf();
+    }
+    return base_dir.join(path);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{hittable::Hittable, ray::Ray, vec3::Point};
+
+    #[test]
+    fn test_parse_directive() {
+        let d = Directive::parse(3, "sphere center=0,1,0 radius=0.5 # comment")
+            .unwrap()
+            .unwrap();
+        assert_eq!(d.name, "sphere");
+        assert_eq!(d.vec3("center").unwrap(), Vec3::new(0.0, 1.0, 0.0));
+        assert_eq!(d.number("radius").unwrap(), 0.5);
+        assert!(d.number("material").is_err());
+        assert!(Directive::parse(4, "  # only a comment").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_parse_scene() {
+        let text = "
+image aspect=2
+camera type=orthographic lookfrom=0,0,5 lookat=0,0,0 height=4
+environment type=constant color=0.1,0.2,0.3
+light type=point position=0,5,0 power=100
+material name=red type=lambertian albedo=0.8,0.1,0.1
+sphere center=0,0,0 radius=1 material=red
+post type=exposure stops=1
+post type=vignette strength=0.3
+";
+        let description = parse_scene(text, Path::new(".")).unwrap();
+        assert_eq!(description.aspect_ratio, 2.0);
+        assert_eq!(description.post.len(), 2);
+        assert_eq!(description.scene.lights.len(), 1);
+
+        let ray = description.camera.get_ray(0.5, 0.5);
+        let hit = description
+            .scene
+            .world
+            .hit(&ray, 0.001, f64::INFINITY)
+            .unwrap();
+        assert!((hit.p.z - 1.0).abs() < 0.000001);
+        let miss = description.camera.get_ray(0.0, 0.0);
+        assert!(description
+            .scene
+            .world
+            .hit(&miss, 0.001, f64::INFINITY)
+            .is_none());
+        assert_eq!(
+            description.scene.environment.emitted(miss.dir),
+            Color::new(0.1, 0.2, 0.3)
+        );
+
+        let text = "post type=white_balance temperature=3200 tint=1\n";
+        let error = parse_scene(text, Path::new(".")).err().unwrap();
+        assert_eq!(error, "line 1: post: tint must be between -1 and 1");
+    }
+
+    #[test]
+    fn test_keyframes() {
+        let text = "
+camera lookfrom=0,0,10 lookat=0,0,0 vfov=30
+keyframe frame=0
+keyframe frame=10 lookfrom=10,0,10 vfov=50
+";
+        let description = parse_scene(text, Path::new(".")).unwrap();
+        let animation = description.animation.as_ref().unwrap();
+        assert_eq!(animation.frame_range(), (0, 10));
+        let state = animation.state_at(5.0);
+        assert!((state.lookfrom.x - 5.0).abs() < 0.000001);
+        assert!((state.vfov.unwrap() - 40.0).abs() < 0.000001);
+        assert!(description.camera_at(5.0).is_ok());
+    }
+
+    #[test]
+    fn test_unknown_material() {
+        let text = "camera lookfrom=0,0,5 lookat=0,0,0\nsphere center=0,0,0 radius=1 material=x\n";
+        let error = parse_scene(text, Path::new(".")).err().unwrap();
+        assert!(error.starts_with("line 2"));
+
+        let text = "material name=glass type=dielectric abbe=0\nsphere radius=1 material=glass\n";
+        let error = parse_scene(text, Path::new(".")).err().unwrap();
+        assert!(error.ends_with("abbe must be positive"), "{}", error);
+    }
+
+    #[test]
+    fn test_csg() {
+        let text = "
+camera lookfrom=0,0,5 lookat=0,0,0
+material name=glass type=dielectric
+sphere name=outer center=0,0,0 radius=1 material=glass
+sphere name=inner center=0,0,0 radius=0.5 material=glass
+csg op=difference a=outer b=inner
+";
+        let description = parse_scene(text, Path::new(".")).unwrap();
+        let ray = Ray::new(Point::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        let intervals = description.scene.world.intervals(&ray);
+        assert_eq!(intervals.len(), 2);
+
+        // shapes are consumed by the first csg that uses them
+        let reused = format!("{}csg op=union a=outer b=inner\n", text);
+        let error = parse_scene(&reused, Path::new(".")).err().unwrap();
+        assert!(error.contains("already used shape outer"));
+
+        // and have to be used, under names of their own
+        let unused = text.replace("csg op=difference a=outer b=inner\n", "");
+        let error = parse_scene(&unused, Path::new(".")).err().unwrap();
+        assert_eq!(error, "shape inner is never used by a csg");
+        let twice = text.replace("name=inner", "name=outer");
+        let error = parse_scene(&twice, Path::new(".")).err().unwrap();
+        assert!(
+            error.contains("line 5: sphere: shape outer is already defined"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn test_sdf() {
+        let text = "
+camera lookfrom=0,0,5 lookat=0,0,0
+material name=red type=lambertian albedo=0.8,0.1,0.1
+sdf name=body type=sphere radius=1
+sdf name=ring type=torus major=1.2 minor=0.1
+sdf name=both type=smooth_union a=body b=ring k=0.2
+sdf_object sdf=both material=red center=0,1,0 bound=1.5
+";
+        let description = parse_scene(text, Path::new(".")).unwrap();
+        let ray = Ray::new(Point::new(0.0, 1.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        let record = description
+            .scene
+            .world
+            .hit(&ray, 0.001, f64::INFINITY)
+            .unwrap();
+        assert!(record.t < 4.0);
+
+        let unknown = "camera lookfrom=0,0,5 lookat=0,0,0\nsdf name=a type=twist of=b amount=1\n";
+        let error = parse_scene(unknown, Path::new(".")).err().unwrap();
+        assert!(error.contains("unknown or already used sdf b"));
+
+        let unused = format!("{}sdf name=spare type=sphere radius=1\n", text);
+        let error = parse_scene(&unused, Path::new(".")).err().unwrap();
+        assert_eq!(error, "sdf spare is never used");
+        let twice = text.replace("name=ring", "name=body");
+        let error = parse_scene(&twice, Path::new(".")).err().unwrap();
+        assert!(
+            error.contains("line 5: sdf: sdf body is already defined"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn test_surfaces() {
+        let text = "
+camera lookfrom=0,0,5 lookat=0,0,0
+material name=red type=lambertian albedo=0.8,0.1,0.1
+quadric type=general xx=1 zz=1 c=-1 min=-1,-1,-1 max=1,1,1 material=red
+torus center=0,0,-3 major=2 minor=0.5 material=red
+";
+        let description = parse_scene(text, Path::new(".")).unwrap();
+        let ray = Ray::new(Point::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        let record = description
+            .scene
+            .world
+            .hit(&ray, 0.001, f64::INFINITY)
+            .unwrap();
+        assert!((record.t - 4.0).abs() <= 0.000001);
+        assert_eq!(record.object_id, 0);
+
+        for (line, error) in [
+            ("torus major=2 minor=0", "major and minor must be positive"),
+            (
+                "quadric type=ellipsoid radii=1,0,1",
+                "radii must be positive",
+            ),
+            (
+                "quadric type=cone radius=1 height=0",
+                "radius and height must be positive",
+            ),
+            (
+                "quadric type=cylinder radius=-1 height=2",
+                "radius and height must be positive",
+            ),
+        ] {
+            let text = format!(
+                "material name=red type=lambertian albedo=0.8,0.1,0.1\n{} material=red\n",
+                line
+            );
+            let result = parse_scene(&text, Path::new("."));
+            assert!(result.err().unwrap().ends_with(error), "{}", line);
+        }
+    }
+
+    #[test]
+    fn test_material_ids() {
+        let text = "
+camera lookfrom=0,0,5 lookat=0,0,0
+material name=red type=lambertian albedo=0.8,0.1,0.1
+material name=blue type=lambertian albedo=0.1,0.1,0.8
+sphere center=-2,0,0 radius=0.5 material=red
+sphere center=0,0,0 radius=0.5 material=blue
+sphere center=2,0,0 radius=0.5 material=red
+";
+        let description = parse_scene(text, Path::new(".")).unwrap();
+        let ids: Vec<usize> = [-2.0, 0.0, 2.0]
+            .iter()
+            .map(|x| {
+                let ray = Ray::new(Point::new(*x, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+                let world = &description.scene.world;
+                return world.hit(&ray, 0.001, f64::INFINITY).unwrap().material_id;
+            })
+            .collect();
+        assert_eq!(ids, vec![1, 2, 1]);
+    }
+
+    #[test]
+    fn test_meshes_and_curves() {
+        let text = "
+camera lookfrom=0,0,5 lookat=0,0,0
+material name=red type=lambertian albedo=0.8,0.1,0.1
+mesh type=cube size=1 subdivide=2 scheme=loop material=red
+curve p0=-1,2,0 p1=0,2,0 p2=0,2,0 p3=1,2,0 width=0.1 material=red
+fur center=0,-3,0 radius=0.5 count=10 length=0.2 material=red
+";
+        let description = parse_scene(text, Path::new(".")).unwrap();
+        let ray = Ray::new(Point::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        let record = description
+            .scene
+            .world
+            .hit(&ray, 0.001, f64::INFINITY)
+            .unwrap();
+        assert!(record.t > 4.0 && record.t < 4.5);
+        assert_eq!(record.object_id, 0);
+
+        let ray = Ray::new(Point::new(0.5, 2.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        let record = description
+            .scene
+            .world
+            .hit(&ray, 0.001, f64::INFINITY)
+            .unwrap();
+        assert!((record.t - 5.0).abs() <= 0.000001);
+        assert_eq!(record.object_id, 1);
+
+        for (line, error) in [
+            (
+                "mesh type=cube subdivide=-1",
+                "subdivide must be between 0 and 6",
+            ),
+            (
+                "mesh type=cube subdivide=40",
+                "subdivide must be between 0 and 6",
+            ),
+            (
+                "fur radius=1 count=-5 length=0.2",
+                "count must not be negative",
+            ),
+        ] {
+            let text = format!(
+                "material name=red type=lambertian albedo=0.8,0.1,0.1\n{} material=red\n",
+                line
+            );
+            let result = parse_scene(&text, Path::new("."));
+            assert!(result.err().unwrap().ends_with(error), "{}", line);
+        }
+    }
+
+    #[test]
+    fn test_gltf() {
+        let path = std::env::temp_dir().join("scene_file_test_gltf.gltf");
+        std::fs::write(&path, include_str!("../scenes/cubes.gltf")).unwrap();
+        let text = format!("gltf file={}\n", path.to_string_lossy());
+        let error = parse_scene(&text, Path::new(".")).err().unwrap();
+        assert_eq!(error, "missing camera");
+
+        let text = format!("gltf file={} camera=0\n", path.to_string_lossy());
+        let description = parse_scene(&text, Path::new(".")).unwrap();
+        assert_eq!(
+            description.camera_directive.string("type").unwrap(),
+            "perspective"
+        );
+        let lookfrom = description.camera_directive.vec3("lookfrom").unwrap();
+        assert!((lookfrom - Point::new(0.0, 2.2, 6.0)).length() <= 0.000001);
+
+        // straight down onto the glass cube in the middle
+        let ray = Ray::new(Point::new(0.0, 5.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        let record = description
+            .scene
+            .world
+            .hit(&ray, 0.001, f64::INFINITY)
+            .unwrap();
+        assert!((record.t - 3.5).abs() <= 0.000001);
+
+        let text = format!("gltf file={} camera=3\n", path.to_string_lossy());
+        let error = parse_scene(&text, Path::new(".")).err().unwrap();
+        assert!(error.contains("the file has no camera 3"));
+        std::fs::remove_file(&path).unwrap();
+
+        // a file given on its own may have any name
+        let path = std::env::temp_dir().join("scene file #2.gltf");
+        std::fs::write(&path, include_str!("../scenes/cubes.gltf")).unwrap();
+        assert!(is_gltf(&path.to_string_lossy()));
+        let description = parse_gltf_scene(&path).unwrap();
+        let lookfrom = description.camera_directive.vec3("lookfrom").unwrap();
+        assert!((lookfrom - Point::new(0.0, 2.2, 6.0)).length() <= 0.000001);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_vertex_colors() {
+        let path = std::env::temp_dir().join("scene_file_test_vertex_colors.ply");
+        let ply = "ply
+format ascii 1.0
+element vertex 3
+property float x
+property float y
+property float z
+property uchar red
+property uchar green
+property uchar blue
+element face 1
+property list uchar int vertex_indices
+end_header
+-1 -1 0 255 0 0
+1 -1 0 255 0 0
+0 1 0 255 0 0
+3 0 1 2
+";
+        std::fs::write(&path, ply).unwrap();
+        let text = format!(
+            "camera lookfrom=0,0,5 lookat=0,0,0
+material name=painted type=lambertian texture=vertex_color albedo=0,0,1
+mesh file={} size=2 material=painted
+sphere center=0,0,-10 radius=1 material=painted
+",
+            path.to_string_lossy()
+        );
+        let description = parse_scene(&text, Path::new(".")).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let ray = Ray::new(Point::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        let world = &description.scene.world;
+        let record = world.hit(&ray, 0.001, f64::INFINITY).unwrap();
+        assert!((record.t - 5.0).abs() <= 0.000001);
+        let albedo = record.material.albedo(&record);
+        assert!((albedo - Color::new(1.0, 0.0, 0.0)).length() <= 0.000001);
+
+        // surfaces without vertex colors fall back to the albedo
+        let record = world.hit(&ray, 5.5, f64::INFINITY).unwrap();
+        let albedo = record.material.albedo(&record);
+        assert!((albedo - Color::new(0.0, 0.0, 1.0)).length() <= 0.000001);
+    }
+}
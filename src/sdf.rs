@@ -0,0 +1,409 @@
+use crate::{
+    aabb::Aabb,
+    hittable::{HitRecord, Hittable},
+    material::Material,
+    ray::Ray,
+    vec3::{Point, Vec3},
+};
+
+// Signed distance to a surface, negative inside. Distances may underestimate but must not
+// overestimate or the marcher steps through the surface.
+pub trait Sdf {
+    fn distance(&self, p: Point) -> f64;
+}
+
+pub struct SdfSphere {
+    radius: f64,
+}
+
+impl SdfSphere {
+    pub fn new(radius: f64) -> SdfSphere {
+        return SdfSphere { radius };
+    }
+}
+
+impl Sdf for SdfSphere {
+    fn distance(&self, p: Point) -> f64 {
+        return p.length() - self.radius;
+    }
+}
+
+// Box with the given half extents, edges rounded by rounding.
+pub struct SdfBox {
+    half_extents: Vec3,
+    rounding: f64,
+}
+
+impl SdfBox {
+    pub fn new(half_extents: Vec3, rounding: f64) -> SdfBox {
+        return SdfBox {
+            half_extents,
+            rounding,
+        };
+    }
+}
+
+impl Sdf for SdfBox {
+    fn distance(&self, p: Point) -> f64 {
+        let r = self.rounding;
+        let q = Vec3::new(
+            p.x.abs() - self.half_extents.x + r,
+            p.y.abs() - self.half_extents.y + r,
+            p.z.abs() - self.half_extents.z + r,
+        );
+        let outside = Vec3::new(q.x.max(0.0), q.y.max(0.0), q.z.max(0.0)).length();
+        let inside = q.x.max(q.y).max(q.z).min(0.0);
+        return outside + inside - r;
+    }
+}
+
+// Ring around the y axis.
+pub struct SdfTorus {
+    major_radius: f64,
+    minor_radius: f64,
+}
+
+impl SdfTorus {
+    pub fn new(major_radius: f64, minor_radius: f64) -> SdfTorus {
+        return SdfTorus {
+            major_radius,
+            minor_radius,
+        };
+    }
+}
+
+impl Sdf for SdfTorus {
+    fn distance(&self, p: Point) -> f64 {
+        let ring = (p.x * p.x + p.z * p.z).sqrt() - self.major_radius;
+        return (ring * ring + p.y * p.y).sqrt() - self.minor_radius;
+    }
+}
+
+// Segment from a to b swept by a sphere.
+pub struct SdfCapsule {
+    a: Point,
+    b: Point,
+    radius: f64,
+}
+
+impl SdfCapsule {
+    pub fn new(a: Point, b: Point, radius: f64) -> SdfCapsule {
+        return SdfCapsule { a, b, radius };
+    }
+}
+
+impl Sdf for SdfCapsule {
+    fn distance(&self, p: Point) -> f64 {
+        let pa = p - self.a;
+        let ba = self.b - self.a;
+        let h = (Vec3::dot(pa, ba) / Vec3::dot(ba, ba)).clamp(0.0, 1.0);
+        return (pa - h * ba).length() - self.radius;
+    }
+}
+
+pub struct SdfTranslate {
+    offset: Vec3,
+    inner: Box<dyn Sdf>,
+}
+
+impl SdfTranslate {
+    pub fn new(offset: Vec3, inner: Box<dyn Sdf>) -> SdfTranslate {
+        return SdfTranslate { offset, inner };
+    }
+}
+
+impl Sdf for SdfTranslate {
+    fn distance(&self, p: Point) -> f64 {
+        return self.inner.distance(p - self.offset);
+    }
+}
+
+pub struct SdfUnion {
+    a: Box<dyn Sdf>,
+    b: Box<dyn Sdf>,
+}
+
+impl SdfUnion {
+    pub fn new(a: Box<dyn Sdf>, b: Box<dyn Sdf>) -> SdfUnion {
+        return SdfUnion { a, b };
+    }
+}
+
+impl Sdf for SdfUnion {
+    fn distance(&self, p: Point) -> f64 {
+        return self.a.distance(p).min(self.b.distance(p));
+    }
+}
+
+// Union blended over a distance of about k, a plain union for k <= 0.
+pub struct SdfSmoothUnion {
+    a: Box<dyn Sdf>,
+    b: Box<dyn Sdf>,
+    k: f64,
+}
+
+impl SdfSmoothUnion {
+    pub fn new(a: Box<dyn Sdf>, b: Box<dyn Sdf>, k: f64) -> SdfSmoothUnion {
+        return SdfSmoothUnion { a, b, k };
+    }
+}
+
+impl Sdf for SdfSmoothUnion {
+    fn distance(&self, p: Point) -> f64 {
+        let a = self.a.distance(p);
+        let b = self.b.distance(p);
+        if self.k <= 0.0 {
+            return a.min(b);
+        }
+        let h = (0.5 + 0.5 * (b - a) / self.k).clamp(0.0, 1.0);
+        return b + (a - b) * h - self.k * h * (1.0 - h);
+    }
+}
+
+// Infinite copies on a grid, a period of 0 leaves that axis alone.
+pub struct SdfRepeat {
+    period: Vec3,
+    inner: Box<dyn Sdf>,
+}
+
+impl SdfRepeat {
+    pub fn new(period: Vec3, inner: Box<dyn Sdf>) -> SdfRepeat {
+        return SdfRepeat { period, inner };
+    }
+}
+
+impl Sdf for SdfRepeat {
+    fn distance(&self, p: Point) -> f64 {
+        let wrap = |v: f64, period: f64| {
+            if period <= 0.0 {
+                return v;
+            }
+            return v - period * (v / period).round();
+        };
+        let q = Vec3::new(
+            wrap(p.x, self.period.x),
+            wrap(p.y, self.period.y),
+            wrap(p.z, self.period.z),
+        );
+        return self.inner.distance(q);
+    }
+}
+
+// Rotates around the y axis by amount radians per unit of height.
+pub struct SdfTwist {
+    amount: f64,
+    inner: Box<dyn Sdf>,
+}
+
+impl SdfTwist {
+    pub fn new(amount: f64, inner: Box<dyn Sdf>) -> SdfTwist {
+        return SdfTwist { amount, inner };
+    }
+}
+
+impl Sdf for SdfTwist {
+    fn distance(&self, p: Point) -> f64 {
+        let angle = self.amount * p.y;
+        let (sin, cos) = angle.sin_cos();
+        let q = Vec3::new(cos * p.x + sin * p.z, p.y, -sin * p.x + cos * p.z);
+        // twisting stretches space by up to this much at distance r from the axis
+        let r = (p.x * p.x + p.z * p.z).sqrt();
+        let stretch = (1.0 + (self.amount * r).powi(2)).sqrt();
+        return self.inner.distance(q) / stretch;
+    }
+}
+
+const MAX_STEPS: u32 = 512;
+// beyond this the march gives up when the object has no bounds
+const MAX_DISTANCE: f64 = 1000.0;
+// smallest step in world units, keeps grazing rays moving and makes crossings detectable
+const MIN_STEP: f64 = 1e-4;
+const NORMAL_EPSILON: f64 = 1e-5;
+
+// Sphere traces a distance function. Hits are where the distance changes sign, refined by
+// bisection, so rays leaving a surface do not find it again at their origin.
+pub struct SdfObject {
+    sdf: Box<dyn Sdf>,
+    material: Box<dyn Material>,
+    // bounding sphere, clips the march
+    bounds: Option<(Point, f64)>,
+}
+
+impl SdfObject {
+    pub fn new(
+        sdf: Box<dyn Sdf>,
+        material: Box<dyn Material>,
+        bounds: Option<(Point, f64)>,
+    ) -> SdfObject {
+        return SdfObject {
+            sdf,
+            material,
+            bounds,
+        };
+    }
+
+    // central differences
+    fn normal(&self, p: Point) -> Vec3 {
+        let e = NORMAL_EPSILON;
+        let dx = Vec3::new(e, 0.0, 0.0);
+        let dy = Vec3::new(0.0, e, 0.0);
+        let dz = Vec3::new(0.0, 0.0, e);
+        return Vec3::new(
+            self.sdf.distance(p + dx) - self.sdf.distance(p - dx),
+            self.sdf.distance(p + dy) - self.sdf.distance(p - dy),
+            self.sdf.distance(p + dz) - self.sdf.distance(p - dz),
+        )
+        .unit_vector();
+    }
+
+    fn bisect(&self, ray: &Ray, t0: f64, t1: f64, inside_at_t0: bool) -> f64 {
+        let mut t0 = t0;
+        let mut t1 = t1;
+        for _ in 0..32 {
+            let mid = 0.5 * (t0 + t1);
+            if (self.sdf.distance(ray.at(mid)) < 0.0) == inside_at_t0 {
+                t0 = mid;
+            } else {
+                t1 = mid;
+            }
+        }
+        return 0.5 * (t0 + t1);
+    }
+
+    // range of t to march, None when the ray misses the bounds
+    fn march_range(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<(f64, f64)> {
+        let length = ray.dir.length();
+        let (center, radius) = match self.bounds {
+            Some(v) => v,
+            None => {
+                let limit = MAX_DISTANCE / length;
+                return Some((t_min.max(-limit), t_max.min(limit)));
+            }
+        };
+        let oc = ray.org - center;
+        let a = Vec3::dot(ray.dir, ray.dir);
+        let b = Vec3::dot(ray.dir, oc);
+        let c = Vec3::dot(oc, oc) - radius * radius;
+        let d = b * b - a * c;
+        if d <= 0.0 {
+            return None;
+        }
+        let root = d.sqrt();
+        let start = t_min.max((-b - root) / a);
+        let end = t_max.min((-b + root) / a);
+        if start >= end {
+            return None;
+        }
+        return Some((start, end));
+    }
+}
+
+impl Hittable for SdfObject {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        let (start, end) = self.march_range(ray, t_min, t_max)?;
+        let length = ray.dir.length();
+
+        let mut t = start;
+        let mut distance = self.sdf.distance(ray.at(t));
+        for _ in 0..MAX_STEPS {
+            let step = distance.abs().max(MIN_STEP) / length;
+            let next_t = t + step;
+            if next_t > end {
+                return None;
+            }
+            let next_distance = self.sdf.distance(ray.at(next_t));
+            if (distance < 0.0) != (next_distance < 0.0) {
+                let t = self.bisect(ray, t, next_t, distance < 0.0);
+                let p = ray.at(t);
+                let outward_normal = self.normal(p);
+                let front_face = HitRecord::is_front_face(ray, outward_normal);
+                let normal = HitRecord::get_normal(front_face, outward_normal);
+                return Some(HitRecord::new(p, normal, t, front_face, &*self.material));
+            }
+            t = next_t;
+            distance = next_distance;
+        }
+        return None;
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let (center, radius) = self.bounds?;
+        return Some(Aabb::new(center, center).expand(radius));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lambertian::Lambertian, vec3::Color};
+
+    const EPSILON: f64 = 0.000001;
+
+    fn object(sdf: Box<dyn Sdf>, bounds: Option<(Point, f64)>) -> SdfObject {
+        let material = Box::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        return SdfObject::new(sdf, material, bounds);
+    }
+
+    #[test]
+    fn test_sphere_matches_analytic() {
+        let sdf = Box::new(SdfTranslate::new(
+            Vec3::new(0.0, 0.0, -5.0),
+            Box::new(SdfSphere::new(1.0)),
+        ));
+        let sphere = object(sdf, None);
+        let ray = Ray::new(Point::default(), Vec3::new(0.0, 0.0, -2.0));
+        let record = sphere.hit(&ray, 0.001, f64::INFINITY).unwrap();
+        assert!((record.t - 2.0).abs() <= EPSILON);
+        assert!((record.normal.z - 1.0).abs() <= 0.0001);
+        assert!(record.front_face);
+
+        // leaving from the surface towards the inside finds the far side
+        let inside = Ray::new(record.p, Vec3::new(0.0, 0.0, -1.0));
+        let exit = sphere.hit(&inside, 0.0001, f64::INFINITY).unwrap();
+        assert!((exit.t - 2.0).abs() <= 0.0001);
+        assert!(!exit.front_face);
+
+        // and leaving towards the outside finds nothing
+        let outside = Ray::new(record.p, Vec3::new(0.0, 0.0, 1.0));
+        assert!(sphere.hit(&outside, 0.0001, f64::INFINITY).is_none());
+    }
+
+    #[test]
+    fn test_bounds_and_intervals() {
+        let torus = object(
+            Box::new(SdfTorus::new(1.0, 0.25)),
+            Some((Point::default(), 1.5)),
+        );
+        let ray = Ray::new(Point::new(-5.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        // through both sides of the ring
+        let intervals = torus.intervals(&ray);
+        assert_eq!(intervals.len(), 2);
+        assert!((intervals[0].enter.t - 3.75).abs() <= EPSILON);
+        assert!((intervals[1].exit.t - 6.25).abs() <= EPSILON);
+
+        let above = Ray::new(Point::new(-5.0, 2.0, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        assert!(torus.hit(&above, 0.001, f64::INFINITY).is_none());
+    }
+
+    #[test]
+    fn test_combinators() {
+        let p = Point::new(0.3, 0.0, 0.0);
+        let a = || Box::new(SdfSphere::new(0.5));
+        let b = || Box::new(SdfTranslate::new(Vec3::new(1.0, 0.0, 0.0), a()));
+        let union = SdfUnion::new(a(), b()).distance(p);
+        let smooth = SdfSmoothUnion::new(a(), b(), 0.3).distance(p);
+        assert!((union + 0.2).abs() <= EPSILON);
+        assert!(smooth <= union);
+        let sharp = SdfSmoothUnion::new(a(), b(), 0.0).distance(p);
+        assert!((sharp - union).abs() <= EPSILON);
+
+        let repeat = SdfRepeat::new(Vec3::new(2.0, 0.0, 0.0), a());
+        assert!((repeat.distance(Point::new(4.3, 0.0, 0.0)) + 0.2).abs() <= EPSILON);
+
+        // no twist at y = 0
+        let twist = SdfTwist::new(2.0, Box::new(SdfBox::new(Vec3::new(1.0, 1.0, 0.2), 0.0)));
+        let expected = 0.3 / 2.0_f64.sqrt();
+        assert!((twist.distance(Point::new(0.0, 0.0, 0.5)) - expected).abs() <= EPSILON);
+        assert!(twist.distance(Point::new(0.0, 0.0, 0.1)) < 0.0);
+    }
+}
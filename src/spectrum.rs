@@ -0,0 +1,136 @@
+use std::sync::OnceLock;
+
+use crate::{
+    sampler::random,
+    vec3::{Color, Vec3},
+};
+
+// Helpers for the spectral mode, in which every path carries a single wavelength in
+// nanometres and colors are turned into the value of a spectrum at that wavelength.
+
+pub const WAVELENGTH_MIN: f64 = 380.0;
+pub const WAVELENGTH_MAX: f64 = 720.0;
+
+// wavelengths of the Fraunhofer lines used to define Abbe numbers
+const WAVELENGTH_D: f64 = 587.6;
+const WAVELENGTH_F: f64 = 486.1;
+const WAVELENGTH_C: f64 = 656.3;
+
+pub fn sample_wavelength() -> f64 {
+    let xi = random();
+    return WAVELENGTH_MIN + xi * (WAVELENGTH_MAX - WAVELENGTH_MIN);
+}
+
+fn logistic(x: f64) -> f64 {
+    return 1.0 / (1.0 + (-x).exp());
+}
+
+// Smooth spectrum for an RGB color, mixing a blue, a green and a red band that add up to
+// one everywhere. White stays a flat spectrum and reflectances stay within [0, 1].
+pub fn rgb_to_spectrum(color: Color, wavelength: f64) -> f64 {
+    let blue = 1.0 - logistic((wavelength - 488.0) / 8.0);
+    let red = logistic((wavelength - 588.0) / 8.0);
+    let green = 1.0 - blue - red;
+    return color.x * red + color.y * green + color.z * blue;
+}
+
+// CIE 1931 color matching functions, fitted with piecewise gaussians after Wyman, Sloan
+// and Shirley, "Simple Analytic Approximations to the CIE XYZ Color Matching Functions".
+pub fn cie_xyz(wavelength: f64) -> Vec3 {
+    let g = |mu: f64, sigma_below: f64, sigma_above: f64| {
+        let sigma = if wavelength < mu {
+            sigma_below
+        } else {
+            sigma_above
+        };
+        let t = (wavelength - mu) / sigma;
+        return (-0.5 * t * t).exp();
+    };
+    return Vec3::new(
+        1.056 * g(599.8, 37.9, 31.0) + 0.362 * g(442.0, 16.0, 26.7) - 0.065 * g(501.1, 20.4, 26.2),
+        0.821 * g(568.8, 46.9, 40.5) + 0.286 * g(530.9, 16.3, 31.1),
+        1.217 * g(437.0, 11.8, 36.0) + 0.681 * g(459.0, 26.0, 13.8),
+    );
+}
+
+fn xyz_to_rgb(xyz: Vec3) -> Color {
+    return Color::new(
+        3.2404542 * xyz.x - 1.5371385 * xyz.y - 0.4985314 * xyz.z,
+        -0.9692660 * xyz.x + 1.8760108 * xyz.y + 0.0415560 * xyz.z,
+        0.0556434 * xyz.x - 0.2040259 * xyz.y + 1.0572252 * xyz.z,
+    );
+}
+
+// linear sRGB of a flat spectrum of value 1, used to keep white white
+fn white() -> Color {
+    static WHITE: OnceLock<Color> = OnceLock::new();
+    return *WHITE.get_or_init(|| {
+        let steps = 3400;
+        let step = (WAVELENGTH_MAX - WAVELENGTH_MIN) / steps as f64;
+        let mut xyz = Vec3::default();
+        for i in 0..steps {
+            xyz += cie_xyz(WAVELENGTH_MIN + (i as f64 + 0.5) * step) * step;
+        }
+        return xyz_to_rgb(xyz);
+    });
+}
+
+// Linear RGB estimate of a pixel from the radiance found at one uniformly sampled
+// wavelength. Averaged over wavelengths a flat spectrum of 1 gives (1, 1, 1).
+pub fn spectral_to_rgb(radiance: f64, wavelength: f64) -> Color {
+    let rgb = xyz_to_rgb(cie_xyz(wavelength)) * (radiance * (WAVELENGTH_MAX - WAVELENGTH_MIN));
+    return rgb / white();
+}
+
+// Cauchy's equation n = a + b / wavelength^2 matched to the refractive index at the
+// d line and the Abbe number, which is smaller for glasses that disperse more.
+pub fn cauchy_ior(ior: f64, abbe: f64, wavelength: f64) -> f64 {
+    let inverse_square = |l: f64| 1.0 / (l * l);
+    let b = (ior - 1.0) / (abbe * (inverse_square(WAVELENGTH_F) - inverse_square(WAVELENGTH_C)));
+    let a = ior - b * inverse_square(WAVELENGTH_D);
+    return a + b * inverse_square(wavelength);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPSILON: f64 = 0.000001;
+
+    fn average(color: Color) -> Color {
+        let steps = 3400;
+        let step = (WAVELENGTH_MAX - WAVELENGTH_MIN) / steps as f64;
+        let mut sum = Color::default();
+        for i in 0..steps {
+            let wavelength = WAVELENGTH_MIN + (i as f64 + 0.5) * step;
+            sum += spectral_to_rgb(rgb_to_spectrum(color, wavelength), wavelength);
+        }
+        return sum / steps as f64;
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let white = average(Color::new(1.0, 1.0, 1.0));
+        assert!((white - Color::new(1.0, 1.0, 1.0)).length() <= EPSILON);
+        assert!((rgb_to_spectrum(Color::new(0.5, 0.5, 0.5), 450.0) - 0.5).abs() <= EPSILON);
+
+        // primaries come back close to themselves
+        for primary in [
+            Color::new(1.0, 0.0, 0.0),
+            Color::new(0.0, 1.0, 0.0),
+            Color::new(0.0, 0.0, 1.0),
+        ] {
+            assert!((average(primary) - primary).length() < 0.05);
+        }
+    }
+
+    #[test]
+    fn test_cauchy_ior() {
+        // a crown glass with an Abbe number of 64
+        assert!((cauchy_ior(1.5168, 64.0, WAVELENGTH_D) - 1.5168).abs() <= EPSILON);
+        let spread =
+            cauchy_ior(1.5168, 64.0, WAVELENGTH_F) - cauchy_ior(1.5168, 64.0, WAVELENGTH_C);
+        assert!((spread - 0.5168 / 64.0).abs() <= EPSILON);
+        assert!(cauchy_ior(1.5168, 64.0, 400.0) > cauchy_ior(1.5168, 64.0, 700.0));
+    }
+}
@@ -1,3 +1,6 @@
+use std::f64::consts::PI;
+
+use crate::aabb::Aabb;
 use crate::hittable::{HitRecord, Hittable};
 use crate::material::Material;
 use crate::ray::Ray;
@@ -20,7 +23,7 @@ impl Sphere {
 }
 
 impl Hittable for Sphere {
-    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
         let oc = ray.org - self.center;
         let a = Vec3::dot(ray.dir, ray.dir);
         let b = Vec3::dot(ray.dir, oc);
@@ -35,7 +38,9 @@ impl Hittable for Sphere {
                 let outward_normal = (p - self.center).unit_vector();
                 let front_face = HitRecord::is_front_face(ray, outward_normal);
                 let normal = HitRecord::get_normal(front_face, outward_normal);
-                let record = HitRecord::new(p, normal, t, front_face, &*self.material);
+                let mut record = HitRecord::new(p, normal, t, front_face, &*self.material);
+                (record.u, record.v) = sphere_uv(outward_normal);
+                (record.dpdu, record.dpdv) = sphere_derivatives(outward_normal, self.radius);
                 return Some(record);
             }
 
@@ -45,11 +50,36 @@ impl Hittable for Sphere {
                 let outward_normal = (p - self.center).unit_vector();
                 let front_face = HitRecord::is_front_face(ray, outward_normal);
                 let normal = HitRecord::get_normal(front_face, outward_normal);
-                let record = HitRecord::new(p, normal, t, front_face, &*self.material);
+                let mut record = HitRecord::new(p, normal, t, front_face, &*self.material);
+                (record.u, record.v) = sphere_uv(outward_normal);
+                (record.dpdu, record.dpdv) = sphere_derivatives(outward_normal, self.radius);
                 return Some(record);
             }
         }
 
         return None;
     }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let r = Vec3::new(self.radius, self.radius, self.radius);
+        return Some(Aabb::new(self.center - r, self.center + r));
+    }
+}
+
+// u goes around the y axis starting at -x, v from the bottom pole to the top
+pub fn sphere_uv(n: Vec3) -> (f64, f64) {
+    let theta = (-n.y).clamp(-1.0, 1.0).acos();
+    let phi = (-n.z).atan2(n.x) + PI;
+    return (phi / (2.0 * PI), theta / PI);
+}
+
+// dp/du and dp/dv of the parameterization above, zero at the poles
+fn sphere_derivatives(n: Vec3, radius: f64) -> (Vec3, Vec3) {
+    let sin_theta = (n.x * n.x + n.z * n.z).sqrt();
+    if sin_theta < 1e-9 {
+        return (Vec3::default(), Vec3::default());
+    }
+    let dpdu = 2.0 * PI * radius * Vec3::new(n.z, 0.0, -n.x);
+    let dpdv = PI * radius * Vec3::new(-n.x * n.y / sin_theta, sin_theta, -n.z * n.y / sin_theta);
+    return (dpdu, dpdv);
 }
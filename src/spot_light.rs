@@ -0,0 +1,159 @@
+use std::f64::consts::PI;
+
+use crate::{
+    light::{Light, LightEmission, LightSample},
+    onb::Onb,
+    ray::Ray,
+    sampler::random,
+    vec3::{Color, Point, Vec3},
+};
+
+// Point light restricted to a cone. Intensity is full inside the inner angle and
+// falls off smoothly to zero at the outer angle; power is the flux in watts.
+pub struct SpotLight {
+    position: Point,
+    dir: Vec3,
+    intensity: Color,
+    cos_inner: f64,
+    cos_outer: f64,
+}
+
+impl SpotLight {
+    pub fn new(
+        position: Point,
+        target: Point,
+        power: Color,
+        inner_angle: f64,
+        outer_angle: f64,
+    ) -> SpotLight {
+        let cos_inner = inner_angle.cos();
+        let cos_outer = outer_angle.cos();
+        // solid angle of the cone, counting the falloff region as half
+        let solid_angle = 2.0 * PI * (1.0 - 0.5 * (cos_inner + cos_outer));
+        return SpotLight {
+            position,
+            dir: (target - position).unit_vector(),
+            intensity: power / solid_angle,
+            cos_inner,
+            cos_outer,
+        };
+    }
+
+    // Parameters new() can make a light from: a cone needs a direction, and a falloff band
+    // between the angles to divide by.
+    pub fn check(
+        position: Point,
+        target: Point,
+        inner_angle: f64,
+        outer_angle: f64,
+    ) -> Result<(), String> {
+        if position == target {
+            return Err(String::from("spot light target is its position"));
+        }
+        if inner_angle >= outer_angle {
+            return Err(String::from(
+                "spot light inner angle must be smaller than the outer angle",
+            ));
+        }
+        return Ok(());
+    }
+
+    fn falloff(&self, cos_theta: f64) -> f64 {
+        if cos_theta >= self.cos_inner {
+            return 1.0;
+        }
+        if cos_theta <= self.cos_outer {
+            return 0.0;
+        }
+        let t = (cos_theta - self.cos_outer) / (self.cos_inner - self.cos_outer);
+        return t * t * (3.0 - 2.0 * t);
+    }
+
+    // emitted directions are picked uniformly inside the outer cone
+    fn cone_pdf(&self) -> f64 {
+        return 1.0 / (2.0 * PI * (1.0 - self.cos_outer));
+    }
+}
+
+impl Light for SpotLight {
+    fn sample_li(&self, p: Point) -> Option<LightSample> {
+        let to_light = self.position - p;
+        let distance_squared = to_light.length_double();
+        if distance_squared == 0.0 {
+            return None;
+        }
+        let distance = distance_squared.sqrt();
+        let dir = to_light / distance;
+        let falloff = self.falloff(Vec3::dot(-1.0 * dir, self.dir));
+        if falloff == 0.0 {
+            return None;
+        }
+        return Some(LightSample {
+            dir,
+            distance,
+            li: self.intensity * (falloff / distance_squared),
+        });
+    }
+
+    fn position(&self) -> Option<Point> {
+        return Some(self.position);
+    }
+
+    fn sample_le(&self) -> Option<LightEmission> {
+        let cos_theta = 1.0 - random() * (1.0 - self.cos_outer);
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let phi = 2.0 * PI * random();
+        let dir = Onb::new(self.dir).local(Vec3::new(
+            sin_theta * phi.cos(),
+            sin_theta * phi.sin(),
+            cos_theta,
+        ));
+        let pdf = self.cone_pdf();
+        return Some(LightEmission {
+            ray: Ray::new(self.position, dir),
+            le: self.intensity * (self.falloff(cos_theta) / pdf),
+            pdf,
+        });
+    }
+
+    fn pdf_le(&self, dir: Vec3) -> f64 {
+        if Vec3::dot(dir.unit_vector(), self.dir) < self.cos_outer {
+            return 0.0;
+        }
+        return self.cone_pdf();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::degrees_to_radians;
+
+    #[test]
+    fn test_cone() {
+        let light = SpotLight::new(
+            Point::new(0.0, 1.0, 0.0),
+            Point::new(0.0, 0.0, 0.0),
+            Color::new(1.0, 1.0, 1.0),
+            degrees_to_radians(20.0),
+            degrees_to_radians(30.0),
+        );
+
+        let center = light.sample_li(Point::new(0.0, 0.0, 0.0)).unwrap();
+        // tan(25 deg) is inside the falloff band
+        let edge = light.sample_li(Point::new(0.466, 0.0, 0.0)).unwrap();
+        assert!(edge.li.x > 0.0);
+        assert!(edge.li.x < center.li.x);
+        assert!(light.sample_li(Point::new(1.0, 0.0, 0.0)).is_none());
+    }
+
+    #[test]
+    fn test_check() {
+        let position = Point::new(0.0, 1.0, 0.0);
+        let target = Point::new(0.0, 0.0, 0.0);
+        assert!(SpotLight::check(position, target, 0.3, 0.5).is_ok());
+        assert!(SpotLight::check(position, position, 0.3, 0.5).is_err());
+        assert!(SpotLight::check(position, target, 0.5, 0.5).is_err());
+        assert!(SpotLight::check(position, target, 0.5, 0.3).is_err());
+    }
+}
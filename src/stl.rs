@@ -0,0 +1,142 @@
+use std::collections::HashMap;
+
+use crate::{mesh::Mesh, vec3::Point};
+
+// Reader for ascii and binary STL files. The facet normals are ignored, the triangles
+// are wound counter-clockwise as the format requires. Corners at exactly the same
+// position are merged so the mesh can be smoothed.
+pub fn read_stl(path: &str) -> Result<Mesh, String> {
+    let data = std::fs::read(path).map_err(|e| format!("{}: {}", path, e))?;
+    return parse_stl(&data).map_err(|e| format!("{}: {}", path, e));
+}
+
+pub fn parse_stl(data: &[u8]) -> Result<Mesh, String> {
+    // binary files may start with "solid" too, their size gives them away
+    let count = data
+        .get(80..84)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as usize);
+    let corners = match count {
+        Some(count) if data.len() == 84 + 50 * count => parse_binary(data, count),
+        _ if data.starts_with(b"solid") => parse_ascii(data)?,
+        _ => return Err(String::from("not an stl file")),
+    };
+
+    let mut positions = vec![];
+    let mut indices: HashMap<[u64; 3], usize> = HashMap::new();
+    let mut faces = Vec::with_capacity(corners.len() / 3);
+    for triangle in corners.chunks_exact(3) {
+        let face = triangle
+            .iter()
+            .map(|p| {
+                let key = [p.x.to_bits(), p.y.to_bits(), p.z.to_bits()];
+                return *indices.entry(key).or_insert_with(|| {
+                    positions.push(*p);
+                    return positions.len() - 1;
+                });
+            })
+            .collect();
+        faces.push(face);
+    }
+    return Ok(Mesh::new(positions, faces));
+}
+
+fn parse_binary(data: &[u8], count: usize) -> Vec<Point> {
+    let mut corners = Vec::with_capacity(3 * count);
+    for i in 0..count {
+        // normal, three corners and an attribute word
+        let facet = &data[84 + 50 * i..84 + 50 * (i + 1)];
+        let float = |j: usize| {
+            let b = &facet[4 * j..4 * j + 4];
+            return f32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64;
+        };
+        for corner in 1..4 {
+            corners.push(Point::new(
+                float(3 * corner),
+                float(3 * corner + 1),
+                float(3 * corner + 2),
+            ));
+        }
+    }
+    return corners;
+}
+
+fn parse_ascii(data: &[u8]) -> Result<Vec<Point>, String> {
+    let text = String::from_utf8_lossy(data);
+    let mut corners = vec![];
+    let mut facet = 0;
+    for (i, line) in text.lines().enumerate() {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        match tokens.as_slice() {
+            ["vertex", x, y, z] => {
+                let number = |s: &str| {
+                    return s
+                        .parse::<f64>()
+                        .map_err(|_| format!("line {}: invalid number {}", i + 1, s));
+                };
+                corners.push(Point::new(number(x)?, number(y)?, number(z)?));
+                facet += 1;
+            }
+            ["endfacet"] => {
+                if facet != 3 {
+                    return Err(format!("line {}: facets need three vertices", i + 1));
+                }
+                facet = 0;
+            }
+            _ => {}
+        }
+    }
+    return Ok(corners);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPSILON: f64 = 0.000001;
+
+    #[test]
+    fn test_ascii() {
+        let text = "solid part
+  facet normal 0 0 1
+    outer loop
+      vertex 0 0 0
+      vertex 1 0 0
+      vertex 1 1 0
+    endloop
+  endfacet
+  facet normal 0 0 1
+    outer loop
+      vertex 0 0 0
+      vertex 1 1 0
+      vertex 0 1.5e0 0
+    endloop
+  endfacet
+endsolid part
+";
+        let mesh = parse_stl(text.as_bytes()).unwrap();
+        assert_eq!(mesh.positions.len(), 4);
+        assert_eq!(mesh.faces, vec![vec![0, 1, 2], vec![0, 2, 3]]);
+        assert!((mesh.positions[3].y - 1.5).abs() <= EPSILON);
+
+        let broken = text.replace("      vertex 1 0 0\n", "");
+        assert!(parse_stl(broken.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_binary() {
+        // the header starts with "solid" like some exporters write it
+        let mut data = b"solid but binary".to_vec();
+        data.resize(80, 0);
+        data.extend_from_slice(&1u32.to_le_bytes());
+        for v in [
+            0.0f32, 0.0, 1.0, 0.0, 0.0, 0.0, 2.0, 0.0, 0.0, 0.0, 2.0, 0.0,
+        ] {
+            data.extend_from_slice(&v.to_le_bytes());
+        }
+        data.extend_from_slice(&[0, 0]);
+
+        let mesh = parse_stl(&data).unwrap();
+        assert_eq!(mesh.faces, vec![vec![0, 1, 2]]);
+        assert!((mesh.positions[1].x - 2.0).abs() <= EPSILON);
+    }
+}
@@ -0,0 +1,199 @@
+use crate::{
+    dielectric::refract,
+    hittable::HitRecord,
+    material::{Material, Scattered},
+    ray::Ray,
+    sampler::{random, random_index},
+    vec3::{Color, Vec3},
+};
+
+// Homogeneous medium filling a closed object, with per channel coefficients in inverse
+// scene units and the refractive index of the surface around it.
+pub struct Medium {
+    sigma_s: Color,
+    sigma_t: Color,
+    pub ior: f64,
+}
+
+impl Medium {
+    // albedo is the share of interactions that scatter rather than absorb and
+    // mean_free_path the average distance between interactions, both per channel
+    pub fn new(albedo: Color, mean_free_path: Color, ior: f64) -> Medium {
+        let sigma_t = Color::new(
+            1.0 / mean_free_path.x,
+            1.0 / mean_free_path.y,
+            1.0 / mean_free_path.z,
+        );
+        return Medium {
+            sigma_s: albedo * sigma_t,
+            sigma_t,
+            ior,
+        };
+    }
+
+    pub fn transmittance(&self, distance: f64) -> Color {
+        return Color::new(
+            (-self.sigma_t.x * distance).exp(),
+            (-self.sigma_t.y * distance).exp(),
+            (-self.sigma_t.z * distance).exp(),
+        );
+    }
+
+    // Distance to the next interaction along a channel picked at random. The weights
+    // below divide by the density averaged over the three channels.
+    pub fn sample_distance(&self) -> f64 {
+        let sigma_t = match random_index(3) {
+            0 => self.sigma_t.x,
+            1 => self.sigma_t.y,
+            _ => self.sigma_t.z,
+        };
+        return -(1.0 - random()).ln() / sigma_t;
+    }
+
+    // weight of a walk that scatters at distance
+    pub fn scatter_weight(&self, distance: f64) -> Color {
+        let transmittance = self.transmittance(distance);
+        let density = self.sigma_t * transmittance;
+        let pdf = (density.x + density.y + density.z) / 3.0;
+        return self.sigma_s * transmittance / pdf;
+    }
+
+    // weight of a walk that reaches a surface at distance without interacting
+    pub fn pass_weight(&self, distance: f64) -> Color {
+        let transmittance = self.transmittance(distance);
+        let probability = (transmittance.x + transmittance.y + transmittance.z) / 3.0;
+        return transmittance / probability;
+    }
+
+    // share of light leaving through the surface at cos_theta from the inside
+    pub fn exit_transmission(&self, cos_theta: f64) -> f64 {
+        return 1.0 - reflectance(cos_theta, self.ior, self.ior);
+    }
+}
+
+// Schlick's approximation, taken at the angle on the outside of the surface, and 1
+// under total internal reflection.
+fn reflectance(cos_theta: f64, eta_in_over_out: f64, ior: f64) -> f64 {
+    let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+    if eta_in_over_out * sin_theta > 1.0 {
+        return 1.0;
+    }
+    let cos_outside = if eta_in_over_out < 1.0 {
+        cos_theta
+    } else {
+        (1.0 - (eta_in_over_out * sin_theta).powi(2)).sqrt()
+    };
+    let r0 = ((1.0 - ior) / (1.0 + ior)).powi(2);
+    return r0 + (1.0 - r0) * (1.0 - cos_outside).powi(5);
+}
+
+// Translucent material like skin, wax or marble. The smooth surface reflects or
+// refracts like a dielectric and refracted rays random walk through the medium inside
+// until they leave again. Like Dielectric it needs closed objects.
+pub struct Subsurface {
+    albedo: Color,
+    medium: Medium,
+}
+
+impl Subsurface {
+    pub fn new(albedo: Color, mean_free_path: Color, ior: f64) -> Subsurface {
+        return Subsurface {
+            albedo,
+            medium: Medium::new(albedo, mean_free_path, ior),
+        };
+    }
+}
+
+impl Material for Subsurface {
+    fn scatter(&self, ray_in: &Ray, record: &HitRecord) -> Option<Scattered> {
+        let eta_in_over_out = if record.front_face {
+            1.0 / self.medium.ior
+        } else {
+            self.medium.ior
+        };
+        let unit_ray_in_dir = ray_in.dir.unit_vector();
+        let cos_theta = f64::min(Vec3::dot(-1.0 * unit_ray_in_dir, record.normal), 1.0);
+
+        let reflect = reflectance(cos_theta, eta_in_over_out, self.medium.ior);
+        let dir = if random() < reflect {
+            unit_ray_in_dir.reflect(record.normal)
+        } else {
+            refract(unit_ray_in_dir, record.normal, eta_in_over_out)
+        };
+        return Some(Scattered::new(
+            Ray::new(record.p, dir),
+            Color::new(1.0, 1.0, 1.0),
+        ));
+    }
+
+    fn albedo(&self, _: &HitRecord) -> Color {
+        return self.albedo;
+    }
+
+    fn medium(&self) -> Option<&Medium> {
+        return Some(&self.medium);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        environment::ConstantEnvironment, hittable_list::HittableList, integrator::ray_color,
+        scene::Scene, sphere::Sphere, vec3::Point,
+    };
+
+    const EPSILON: f64 = 0.000001;
+
+    #[test]
+    fn test_medium_weights() {
+        // gray media scatter with their albedo and pass unweighted
+        let gray = Medium::new(Color::new(0.8, 0.8, 0.8), Color::new(0.5, 0.5, 0.5), 1.3);
+        assert!((gray.scatter_weight(0.7) - Color::new(0.8, 0.8, 0.8)).length() <= EPSILON);
+        assert!((gray.pass_weight(0.7) - Color::new(1.0, 1.0, 1.0)).length() <= EPSILON);
+
+        // walks across a slab come through with the transmittance of each channel
+        let medium = Medium::new(Color::new(0.9, 0.9, 0.9), Color::new(1.0, 0.5, 0.1), 1.3);
+        let count = 100000;
+        let mut passed = Color::default();
+        for _ in 0..count {
+            if medium.sample_distance() > 0.4 {
+                passed += medium.pass_weight(0.4);
+            }
+        }
+        let expected = medium.transmittance(0.4);
+        assert!((passed / count as f64 - expected).length() < 0.01);
+    }
+
+    #[test]
+    fn test_reflectance() {
+        assert!((reflectance(1.0, 1.0 / 1.5, 1.5) - 0.04).abs() <= EPSILON);
+        // grazing from the inside is totally reflected
+        assert!((reflectance(0.1, 1.5, 1.5) - 1.0).abs() <= EPSILON);
+        let medium = Medium::new(Color::new(0.5, 0.5, 0.5), Color::new(1.0, 1.0, 1.0), 1.5);
+        assert!((medium.exit_transmission(1.0) - 0.96).abs() <= EPSILON);
+    }
+
+    #[test]
+    fn test_white_furnace() {
+        // a medium that never absorbs is invisible in a uniform environment
+        let material = Subsurface::new(Color::new(1.0, 1.0, 1.0), Color::new(0.3, 0.3, 0.3), 1.4);
+        let mut world = HittableList::default();
+        world.add(Box::new(Sphere::new(
+            Point::default(),
+            1.0,
+            Box::new(material),
+        )));
+        let environment = Box::new(ConstantEnvironment::new(Color::new(1.0, 1.0, 1.0)));
+        let scene = Scene::new(world, environment);
+
+        let count = 2000;
+        let mut sum = Color::default();
+        for _ in 0..count {
+            let ray = Ray::new(Point::new(0.3, 0.2, 5.0), Vec3::new(0.0, 0.0, -1.0));
+            sum += ray_color(&ray, &scene, 50);
+        }
+        let mean = sum / count as f64;
+        assert!((mean.x - 1.0).abs() < 0.05);
+    }
+}
@@ -0,0 +1,95 @@
+use std::f64::consts::PI;
+
+use crate::{
+    light::{Light, LightSample},
+    onb::Onb,
+    sampler::random,
+    vec3::{Color, Point, Vec3},
+};
+
+// Distant disk light. The irradiance is measured on a surface facing the sun, and
+// the angular radius spreads it over a cone to give soft shadows.
+pub struct SunLight {
+    dir: Vec3,
+    cos_max: f64,
+    radiance: Color,
+    irradiance: Color,
+}
+
+impl SunLight {
+    pub fn new(dir: Vec3, angular_radius: f64, irradiance: Color) -> SunLight {
+        let sin_max = angular_radius.sin();
+        let radiance = if sin_max > 0.0 {
+            irradiance / (PI * sin_max * sin_max)
+        } else {
+            irradiance
+        };
+        return SunLight {
+            dir: dir.unit_vector(),
+            cos_max: angular_radius.cos(),
+            radiance,
+            irradiance,
+        };
+    }
+}
+
+impl Light for SunLight {
+    fn sample_li(&self, _: Point) -> Option<LightSample> {
+        if self.cos_max >= 1.0 {
+            return Some(LightSample {
+                dir: self.dir,
+                distance: f64::INFINITY,
+                li: self.irradiance,
+            });
+        }
+
+        let cos_theta = 1.0 - random() * (1.0 - self.cos_max);
+        let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
+        let phi = 2.0 * PI * random();
+        let dir = Onb::new(self.dir).local(Vec3::new(
+            sin_theta * phi.cos(),
+            sin_theta * phi.sin(),
+            cos_theta,
+        ));
+        let pdf = 1.0 / (2.0 * PI * (1.0 - self.cos_max));
+        return Some(LightSample {
+            dir,
+            distance: f64::INFINITY,
+            li: self.radiance / pdf,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::degrees_to_radians;
+
+    const EPSILON: f64 = 0.000001;
+
+    #[test]
+    fn test_samples_stay_in_cone() {
+        let radius = degrees_to_radians(2.0);
+        let sun = SunLight::new(Vec3::new(0.0, 1.0, 1.0), radius, Color::new(1.0, 1.0, 1.0));
+        for _ in 0..100 {
+            let s = sun.sample_li(Point::default()).unwrap();
+            assert!(
+                Vec3::dot(s.dir, Vec3::new(0.0, 1.0, 1.0).unit_vector()) >= radius.cos() - EPSILON
+            );
+            assert!((s.dir.length() - 1.0).abs() <= EPSILON);
+        }
+    }
+
+    #[test]
+    fn test_contribution_close_to_irradiance() {
+        // for small cones the estimate matches the irradiance of a point-like sun
+        let sun = SunLight::new(
+            Vec3::new(0.0, 1.0, 0.0),
+            degrees_to_radians(0.5),
+            Color::new(3.0, 2.0, 1.0),
+        );
+        let s = sun.sample_li(Point::default()).unwrap();
+        assert!((s.li.x - 3.0).abs() < 0.001);
+        assert!((s.li.z - 1.0).abs() < 0.001);
+    }
+}
@@ -0,0 +1,71 @@
+use crate::{hittable::HitRecord, image::Image, vec3::Color};
+
+// Color that varies over a surface, looked up at a hit.
+pub trait Texture {
+    fn value(&self, record: &HitRecord) -> Color;
+}
+
+pub struct SolidColor {
+    color: Color,
+}
+
+impl SolidColor {
+    pub fn new(color: Color) -> SolidColor {
+        return SolidColor { color };
+    }
+}
+
+impl Texture for SolidColor {
+    fn value(&self, _: &HitRecord) -> Color {
+        return self.color;
+    }
+}
+
+// The interpolated vertex colors of meshes that have them, such as scanned PLY models.
+// Other surfaces get the fallback color.
+pub struct VertexColor {
+    fallback: Color,
+}
+
+impl VertexColor {
+    pub fn new(fallback: Color) -> VertexColor {
+        return VertexColor { fallback };
+    }
+}
+
+impl Texture for VertexColor {
+    fn value(&self, record: &HitRecord) -> Color {
+        return record.color.unwrap_or(self.fallback);
+    }
+}
+
+// An image stretched over the surface parameterization.
+pub struct ImageTexture {
+    image: Image,
+}
+
+impl ImageTexture {
+    pub fn new(image: Image) -> ImageTexture {
+        return ImageTexture { image };
+    }
+}
+
+impl Texture for ImageTexture {
+    fn value(&self, record: &HitRecord) -> Color {
+        return sample_image(&self.image, record.u, record.v);
+    }
+}
+
+// Bilinear image lookup for maps over a surface. v runs up the image and both
+// coordinates repeat outside [0, 1].
+pub fn sample_image(image: &Image, u: f64, v: f64) -> Color {
+    let x = u.rem_euclid(1.0) * image.width as f64 - 0.5;
+    let y = (1.0 - v.rem_euclid(1.0)) * image.height as f64 - 0.5;
+    let (x0, y0) = (x.floor(), y.floor());
+    let (fx, fy) = (x - x0, y - y0);
+    let wrap = |i: f64, n: u32| i.rem_euclid(n as f64) as u32;
+    let get = |dx: f64, dy: f64| image.get(wrap(x0 + dx, image.width), wrap(y0 + dy, image.height));
+    let top = (1.0 - fx) * get(0.0, 0.0) + fx * get(1.0, 0.0);
+    let bottom = (1.0 - fx) * get(0.0, 1.0) + fx * get(1.0, 1.0);
+    return (1.0 - fy) * top + fy * bottom;
+}
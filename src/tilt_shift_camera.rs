@@ -0,0 +1,138 @@
+use crate::{
+    camera::{look_at_basis, Camera},
+    math::degrees_to_radians,
+    ray::Ray,
+    vec3::{Point, Vec3},
+};
+
+// Thin lens camera with a shifted image window and a tilted plane of focus.
+// Shift moves the window by a fraction of its size without rotating the camera, which
+// keeps verticals parallel. Tilt rotates the focus plane around the horizontal axis
+// (Scheimpflug), so a receding ground plane can be in focus at a wide aperture.
+pub struct TiltShiftCamera {
+    origin: Point,
+    lower_left_corner: Vec3,
+    horizontal: Vec3,
+    vertical: Vec3,
+    u: Vec3,
+    v: Vec3,
+    focus_point: Point,
+    focus_normal: Vec3,
+    lens_radius: f64,
+}
+
+impl TiltShiftCamera {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        lookfrom: Point,
+        lookat: Point,
+        vup: Vec3,
+        vfov: f64,
+        aspect_ratio: f64,
+        aperture: f64,
+        focus_dist: f64,
+        tilt: f64,
+        shift: (f64, f64),
+    ) -> TiltShiftCamera {
+        let (u, v, w) = look_at_basis(lookfrom, lookat, vup);
+        let viewport_height = 2.0 * (degrees_to_radians(vfov) * 0.5).tan();
+        let viewport_width = viewport_height * aspect_ratio;
+
+        // window at unit distance, relative to the origin
+        let horizontal = viewport_width * u;
+        let vertical = viewport_height * v;
+        let lower_left_corner = (shift.0 - 0.5) * horizontal + (shift.1 - 0.5) * vertical - w;
+
+        let tilt = degrees_to_radians(tilt);
+        return TiltShiftCamera {
+            origin: lookfrom,
+            lower_left_corner,
+            horizontal,
+            vertical,
+            u,
+            v,
+            focus_point: lookfrom - focus_dist * w,
+            focus_normal: tilt.cos() * w + tilt.sin() * v,
+            lens_radius: aperture * 0.5,
+        };
+    }
+}
+
+impl Camera for TiltShiftCamera {
+    fn get_ray(&self, s: f64, t: f64) -> Ray {
+        // find where the pinhole ray meets the plane of focus, then aim the lens sample at it
+        let pinhole_dir = self.lower_left_corner + s * self.horizontal + t * self.vertical;
+        let denominator = Vec3::dot(pinhole_dir, self.focus_normal);
+        let focus = if denominator.abs() > 1e-9 {
+            let distance =
+                Vec3::dot(self.focus_point - self.origin, self.focus_normal) / denominator;
+            if distance > 0.0 {
+                self.origin + distance * pinhole_dir
+            } else {
+                // the ray runs away from the tilted plane, keep it sharp at infinity
+                return Ray::new(self.origin, pinhole_dir);
+            }
+        } else {
+            return Ray::new(self.origin, pinhole_dir);
+        };
+
+        let rd = self.lens_radius * Vec3::random_in_unit_disk();
+        let offset = self.u * rd.x + self.v * rd.y;
+        return Ray::new(self.origin + offset, focus - self.origin - offset);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPSILON: f64 = 0.000001;
+
+    fn camera(aperture: f64, tilt: f64, shift: (f64, f64)) -> TiltShiftCamera {
+        return TiltShiftCamera::new(
+            Point::new(0.0, 0.0, 0.0),
+            Point::new(0.0, 0.0, -1.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            90.0,
+            1.0,
+            aperture,
+            2.0,
+            tilt,
+            shift,
+        );
+    }
+
+    #[test]
+    fn test_center_and_shift() {
+        let center = camera(0.0, 0.0, (0.0, 0.0)).get_ray(0.5, 0.5);
+        assert!((center.org - Point::new(0.0, 0.0, 0.0)).length() <= EPSILON);
+        assert!((center.dir.unit_vector() - Vec3::new(0.0, 0.0, -1.0)).length() <= EPSILON);
+
+        // the window moves by half its width, the camera still looks down -z
+        let shifted = camera(0.0, 0.0, (0.5, 0.0));
+        let dir = shifted.get_ray(0.5, 0.5).dir;
+        assert!((dir / -dir.z - Vec3::new(1.0, 0.0, -1.0)).length() <= EPSILON);
+        let left = shifted.get_ray(0.0, 0.5).dir;
+        assert!(left.x.abs() <= EPSILON);
+        let top = shifted.get_ray(0.5, 1.0).dir;
+        assert!((top / -top.z - Vec3::new(1.0, 1.0, -1.0)).length() <= EPSILON);
+    }
+
+    #[test]
+    fn test_tilted_focal_plane() {
+        // tilted by 45 degrees the plane of focus is z = -2 - y
+        let camera = camera(0.5, 45.0, (0.0, 0.0));
+        for t in [0.25, 0.5, 0.75] {
+            // rays through the whole lens meet on the plane
+            let a = camera.get_ray(0.5, t);
+            let b = camera.get_ray(0.5, t);
+            let (pa, pb) = (a.at(1.0), b.at(1.0));
+            assert!((pa - pb).length() <= EPSILON);
+            assert!((pa.z + 2.0 + pa.y).abs() <= EPSILON);
+        }
+        // nearer at the bottom of the image, farther at the top
+        assert!(camera.get_ray(0.5, 0.25).at(1.0).z > -2.0);
+        assert!((camera.get_ray(0.5, 0.5).at(1.0).z + 2.0).abs() <= EPSILON);
+        assert!(camera.get_ray(0.5, 0.75).at(1.0).z < -2.0);
+    }
+}
@@ -0,0 +1,152 @@
+use std::f64::consts::PI;
+
+use crate::{
+    aabb::Aabb,
+    hittable::{HitRecord, Hittable},
+    material::Material,
+    polynomial::{polish_root, solve_quartic},
+    ray::Ray,
+    vec3::{Point, Vec3},
+};
+
+// Ring around the y axis through center. major_radius is the distance from the center to
+// the middle of the tube, minor_radius the radius of the tube.
+pub struct Torus {
+    center: Point,
+    major_radius: f64,
+    minor_radius: f64,
+    material: Box<dyn Material>,
+}
+
+impl Torus {
+    pub fn new(
+        center: Point,
+        major_radius: f64,
+        minor_radius: f64,
+        material: Box<dyn Material>,
+    ) -> Torus {
+        return Torus {
+            center,
+            major_radius,
+            minor_radius,
+            material,
+        };
+    }
+}
+
+impl Hittable for Torus {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        let length = ray.dir.length();
+        let d = ray.dir / length;
+        let bound = self.major_radius + self.minor_radius;
+
+        // The quartic loses precision far from the torus, so the origin is first moved up
+        // to the bounding sphere.
+        let oc = ray.org - self.center;
+        let b = Vec3::dot(oc, d);
+        let c = Vec3::dot(oc, oc) - bound * bound;
+        if b * b - c <= 0.0 {
+            return None;
+        }
+        let shift = (-b - (b * b - c).sqrt()).max(0.0);
+        let o = oc + shift * d;
+
+        // (|p|^2 + R^2 - r^2)^2 = 4 R^2 (px^2 + pz^2) with p = o + s d
+        let r2 = self.major_radius * self.major_radius;
+        let f = Vec3::dot(o, d);
+        let k = Vec3::dot(o, o) + r2 - self.minor_radius * self.minor_radius;
+        let coefficients = [
+            k * k - 4.0 * r2 * (o.x * o.x + o.z * o.z),
+            4.0 * f * k - 8.0 * r2 * (o.x * d.x + o.z * d.z),
+            4.0 * f * f + 2.0 * k - 4.0 * r2 * (d.x * d.x + d.z * d.z),
+            4.0 * f,
+            1.0,
+        ];
+
+        let mut closest: Option<f64> = None;
+        for s in solve_quartic(coefficients) {
+            let s = polish_root(&coefficients, s);
+            let t = (s + shift) / length;
+            if t_min < t && t < t_max && closest.is_none_or(|c| t < c) {
+                closest = Some(t);
+            }
+        }
+        let t = closest?;
+
+        let p = ray.at(t);
+        let local = p - self.center;
+        let radial = Vec3::new(local.x, 0.0, local.z).unit_vector();
+        let outward_normal = (local - self.major_radius * radial).unit_vector();
+        let front_face = HitRecord::is_front_face(ray, outward_normal);
+        let normal = HitRecord::get_normal(front_face, outward_normal);
+        let mut record = HitRecord::new(p, normal, t, front_face, &*self.material);
+        // u around the y axis, v around the tube starting at the outer equator
+        let ring_distance = (local.x * local.x + local.z * local.z).sqrt() - self.major_radius;
+        record.u = (local.z.atan2(local.x) + PI) / (2.0 * PI);
+        record.v = (local.y.atan2(ring_distance) + PI) / (2.0 * PI);
+        record.dpdu = 2.0 * PI * Vec3::new(-local.z, 0.0, local.x);
+        record.dpdv = 2.0 * PI * (ring_distance * Vec3::new(0.0, 1.0, 0.0) - local.y * radial);
+        return Some(record);
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let outer = self.major_radius + self.minor_radius;
+        let extent = Vec3::new(outer, self.minor_radius, outer);
+        return Some(Aabb::new(self.center - extent, self.center + extent));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lambertian::Lambertian, vec3::Color};
+
+    const EPSILON: f64 = 0.000001;
+
+    fn torus() -> Torus {
+        let material = Box::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        return Torus::new(Point::new(0.0, 1.0, 0.0), 2.0, 0.5, material);
+    }
+
+    #[test]
+    fn test_hit_through_ring() {
+        let torus = torus();
+        let ray = Ray::new(Point::new(-10.0, 1.0, 0.0), Vec3::new(2.0, 0.0, 0.0));
+        let record = torus.hit(&ray, 0.001, f64::INFINITY).unwrap();
+        assert!((record.t - 3.75).abs() <= EPSILON);
+        assert!((record.normal.x + 1.0).abs() <= EPSILON);
+
+        // the next crossing is the inner wall of the same side
+        let record = torus.hit(&ray, 3.76, f64::INFINITY).unwrap();
+        assert!((record.t - 4.25).abs() <= EPSILON);
+        assert!(!record.front_face);
+
+        // straight down the hole
+        let down = Ray::new(Point::new(0.0, 10.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        assert!(torus.hit(&down, 0.001, f64::INFINITY).is_none());
+    }
+
+    #[test]
+    fn test_hit_from_far_away() {
+        let torus = torus();
+        let ray = Ray::new(Point::new(0.0, 1.25, 1000.0), Vec3::new(0.0, 0.0, -1.0));
+        let record = torus.hit(&ray, 0.001, f64::INFINITY).unwrap();
+        // outer side of the tube a quarter of its radius above the equator
+        let expected = 2.0 + (0.5_f64 * 0.5 - 0.25 * 0.25).sqrt();
+        assert!((record.p.z - expected).abs() <= 0.0001);
+    }
+
+    #[test]
+    fn test_scene_scale() {
+        // the ring test at a thousandth and a thousand times the size
+        for k in [0.001, 1000.0] {
+            let material = Box::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+            let torus = Torus::new(Point::new(0.0, k, 0.0), 2.0 * k, 0.5 * k, material);
+            let ray = Ray::new(Point::new(-10.0 * k, k, 0.0), Vec3::new(2.0 * k, 0.0, 0.0));
+            let record = torus.hit(&ray, 0.001, f64::INFINITY).unwrap();
+            assert!((record.t - 3.75).abs() <= EPSILON, "{}", k);
+            let record = torus.hit(&ray, 3.76, f64::INFINITY).unwrap();
+            assert!((record.t - 4.25).abs() <= EPSILON, "{}", k);
+        }
+    }
+}
@@ -0,0 +1,157 @@
+use std::rc::Rc;
+
+use crate::{
+    aabb::Aabb,
+    hittable::{HitRecord, Hittable},
+    material::Material,
+    ray::Ray,
+    vec3::{Color, Point, Vec3},
+};
+
+// One triangle of a mesh. Triangles of the same mesh share the material.
+pub struct Triangle {
+    p: [Point; 3],
+    // vertex normals for smooth shading, the face normal when None
+    n: Option<[Vec3; 3]>,
+    uv: [(f64, f64); 3],
+    colors: Option<[Color; 3]>,
+    material: Rc<dyn Material>,
+}
+
+impl Triangle {
+    pub fn new(
+        p: [Point; 3],
+        n: Option<[Vec3; 3]>,
+        uv: [(f64, f64); 3],
+        material: Rc<dyn Material>,
+    ) -> Triangle {
+        return Triangle {
+            p,
+            n,
+            uv,
+            colors: None,
+            material,
+        };
+    }
+
+    pub fn set_colors(&mut self, colors: Option<[Color; 3]>) {
+        self.colors = colors;
+    }
+
+    // dp/du and dp/dv from the uv differences along the edges, zero without uvs
+    fn derivatives(&self) -> (Vec3, Vec3) {
+        let [(u0, v0), (u1, v1), (u2, v2)] = self.uv;
+        let (du1, dv1, du2, dv2) = (u1 - u0, v1 - v0, u2 - u0, v2 - v0);
+        let determinant = du1 * dv2 - dv1 * du2;
+        if determinant.abs() < 1e-12 {
+            return (Vec3::default(), Vec3::default());
+        }
+        let e1 = self.p[1] - self.p[0];
+        let e2 = self.p[2] - self.p[0];
+        let dpdu = (dv2 * e1 - dv1 * e2) / determinant;
+        let dpdv = (du1 * e2 - du2 * e1) / determinant;
+        return (dpdu, dpdv);
+    }
+}
+
+impl Hittable for Triangle {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        let [p0, p1, p2] = self.p;
+        let (t, b1, b2) = intersect_triangle(ray, p0, p1, p2, t_min, t_max)?;
+        let b0 = 1.0 - b1 - b2;
+
+        // the side is decided by the geometry so refraction stays consistent
+        let face_normal = Vec3::cross(p1 - p0, p2 - p0).unit_vector();
+        let front_face = HitRecord::is_front_face(ray, face_normal);
+        let outward_normal = match self.n {
+            Some([n0, n1, n2]) => (b0 * n0 + b1 * n1 + b2 * n2).unit_vector(),
+            None => face_normal,
+        };
+        let normal = HitRecord::get_normal(front_face, outward_normal);
+        let mut record = HitRecord::new(ray.at(t), normal, t, front_face, &*self.material);
+        record.u = b0 * self.uv[0].0 + b1 * self.uv[1].0 + b2 * self.uv[2].0;
+        record.v = b0 * self.uv[0].1 + b1 * self.uv[1].1 + b2 * self.uv[2].1;
+        record.color = self.colors.map(|[c0, c1, c2]| b0 * c0 + b1 * c1 + b2 * c2);
+        (record.dpdu, record.dpdv) = self.derivatives();
+        return Some(record);
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        // padded so triangles in an axis plane do not get flat boxes
+        return Some(Aabb::from_points(&self.p).expand(1e-9));
+    }
+}
+
+// Moller-Trumbore. Returns t and the barycentric weights of p1 and p2.
+pub fn intersect_triangle(
+    ray: &Ray,
+    p0: Point,
+    p1: Point,
+    p2: Point,
+    t_min: f64,
+    t_max: f64,
+) -> Option<(f64, f64, f64)> {
+    let e1 = p1 - p0;
+    let e2 = p2 - p0;
+    let pvec = Vec3::cross(ray.dir, e2);
+    let det = Vec3::dot(e1, pvec);
+    if det.abs() < 1e-12 {
+        return None;
+    }
+    let inv_det = 1.0 / det;
+    let tvec = ray.org - p0;
+    let b1 = Vec3::dot(tvec, pvec) * inv_det;
+    if !(0.0..=1.0).contains(&b1) {
+        return None;
+    }
+    let qvec = Vec3::cross(tvec, e1);
+    let b2 = Vec3::dot(ray.dir, qvec) * inv_det;
+    if b2 < 0.0 || b1 + b2 > 1.0 {
+        return None;
+    }
+    let t = Vec3::dot(e2, qvec) * inv_det;
+    if t <= t_min || t >= t_max {
+        return None;
+    }
+    return Some((t, b1, b2));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lambertian::Lambertian;
+
+    const EPSILON: f64 = 0.000001;
+
+    #[test]
+    fn test_intersect_triangle() {
+        let p0 = Point::new(0.0, 0.0, 0.0);
+        let p1 = Point::new(1.0, 0.0, 0.0);
+        let p2 = Point::new(0.0, 1.0, 0.0);
+        let ray = Ray::new(Point::new(0.25, 0.5, 2.0), Vec3::new(0.0, 0.0, -1.0));
+        let (t, b1, b2) = intersect_triangle(&ray, p0, p1, p2, 0.001, f64::INFINITY).unwrap();
+        assert!((t - 2.0).abs() <= EPSILON);
+        assert!((b1 - 0.25).abs() <= EPSILON);
+        assert!((b2 - 0.5).abs() <= EPSILON);
+
+        let outside = Ray::new(Point::new(0.75, 0.5, 2.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(intersect_triangle(&outside, p0, p1, p2, 0.001, f64::INFINITY).is_none());
+    }
+
+    #[test]
+    fn test_derivatives() {
+        // u runs along y over two units, v along -x over one
+        let material: Rc<dyn Material> = Rc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        let p = [
+            Point::new(0.0, 0.0, 0.0),
+            Point::new(0.0, 2.0, 0.0),
+            Point::new(-1.0, 0.0, 0.0),
+        ];
+        let triangle = Triangle::new(p, None, [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0)], material);
+        let ray = Ray::new(Point::new(-0.2, 0.2, 1.0), Vec3::new(0.0, 0.0, -1.0));
+        let record = triangle.hit(&ray, 0.001, f64::INFINITY).unwrap();
+        assert!((record.dpdu - Vec3::new(0.0, 2.0, 0.0)).length() <= EPSILON);
+        assert!((record.dpdv - Vec3::new(-1.0, 0.0, 0.0)).length() <= EPSILON);
+        assert!((record.u - 0.1).abs() <= EPSILON);
+    }
+}
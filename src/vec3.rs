@@ -1,7 +1,6 @@
 use std::ops;
 
-extern crate rand;
-use rand::Rng;
+use crate::sampler::random_range;
 
 #[derive(PartialEq, Clone, Copy, Debug)]
 pub struct Vec3 {
@@ -27,11 +26,10 @@ impl Vec3 {
     }
 
     pub fn random(min: f64, max: f64) -> Vec3 {
-        let mut rng = rand::thread_rng();
         return Vec3::new(
-            rng.gen_range(min..max),
-            rng.gen_range(min..max),
-            rng.gen_range(min..max),
+            random_range(min, max),
+            random_range(min, max),
+            random_range(min, max),
         );
     }
 
@@ -46,8 +44,7 @@ impl Vec3 {
 
     pub fn random_in_unit_disk() -> Vec3 {
         loop {
-            let mut rng = rand::thread_rng();
-            let p = Vec3::new(rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0), 0.0);
+            let p = Vec3::new(random_range(-1.0, 1.0), random_range(-1.0, 1.0), 0.0);
             if p.length_double() < 1.0 {
                 return p;
             }
@@ -55,9 +52,8 @@ impl Vec3 {
     }
 
     pub fn random_unit_vector() -> Vec3 {
-        let mut rng = rand::thread_rng();
-        let a: f64 = rng.gen_range(0.0..2.0) * std::f64::consts::PI;
-        let z: f64 = rng.gen_range(-1.0..1.0);
+        let a: f64 = random_range(0.0, 2.0) * std::f64::consts::PI;
+        let z: f64 = random_range(-1.0, 1.0);
         let r: f64 = (1.0 - z * z).sqrt();
         return Vec3::new(r * f64::cos(a), r * f64::sin(a), z);
     }
@@ -145,6 +141,14 @@ impl ops::Mul<f64> for Vec3 {
     }
 }
 
+impl ops::Div<Vec3> for Vec3 {
+    type Output = Vec3;
+
+    fn div(self, rhs: Vec3) -> Vec3 {
+        return Vec3::new(self.x / rhs.x, self.y / rhs.y, self.z / rhs.z);
+    }
+}
+
 impl ops::Div<f64> for Vec3 {
     type Output = Vec3;
 
@@ -0,0 +1,227 @@
+use std::f64::consts::PI;
+
+use crate::{
+    aabb::Aabb,
+    density_grid::DensityGrid,
+    hittable::{HitRecord, Hittable},
+    material::{Material, Scattered},
+    onb::Onb,
+    ray::Ray,
+    sampler::random,
+    vec3::{Color, Vec3},
+};
+
+// Henyey-Greenstein phase function, scattering forward for positive g, backward for
+// negative g and evenly for 0. Used as the material of points inside volumes.
+pub struct HenyeyGreenstein {
+    albedo: Color,
+    g: f64,
+}
+
+impl HenyeyGreenstein {
+    pub fn new(albedo: Color, g: f64) -> HenyeyGreenstein {
+        return HenyeyGreenstein {
+            albedo,
+            g: g.clamp(-0.99, 0.99),
+        };
+    }
+
+    // density over the sphere of directions for the angle between the incoming and
+    // outgoing directions of travel
+    fn phase(&self, cos_theta: f64) -> f64 {
+        let g = self.g;
+        let denominator = 1.0 + g * g - 2.0 * g * cos_theta;
+        return (1.0 - g * g) / (4.0 * PI * denominator * denominator.sqrt());
+    }
+
+    fn sample(&self, dir: Vec3) -> Vec3 {
+        let g = self.g;
+        let xi = random();
+        let cos_theta = if g.abs() < 1e-3 {
+            1.0 - 2.0 * xi
+        } else {
+            let s = (1.0 - g * g) / (1.0 - g + 2.0 * g * xi);
+            (1.0 + g * g - s * s) / (2.0 * g)
+        };
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let phi = 2.0 * PI * random();
+        let onb = Onb::new(dir);
+        return onb.local(Vec3::new(
+            sin_theta * phi.cos(),
+            sin_theta * phi.sin(),
+            cos_theta,
+        ));
+    }
+}
+
+impl Material for HenyeyGreenstein {
+    fn scatter(&self, ray_in: &Ray, record: &HitRecord) -> Option<Scattered> {
+        // sampled in proportion to the phase function, which leaves the albedo
+        let dir = self.sample(ray_in.dir);
+        return Some(Scattered::new(Ray::new(record.p, dir), self.albedo));
+    }
+
+    fn eval(&self, ray_in: &Ray, _: &HitRecord, dir: Vec3) -> Color {
+        let cos_theta = Vec3::dot(ray_in.dir.unit_vector(), dir.unit_vector());
+        return self.albedo * self.phase(cos_theta);
+    }
+
+    fn pdf(&self, ray_in: &Ray, _: &HitRecord, dir: Vec3) -> f64 {
+        return self.phase(Vec3::dot(ray_in.dir.unit_vector(), dir.unit_vector()));
+    }
+
+    fn albedo(&self, _: &HitRecord) -> Color {
+        return self.albedo;
+    }
+
+    fn has_surface(&self) -> bool {
+        return false;
+    }
+}
+
+// Participating medium in a box whose extinction is density times a grid stretched over
+// the box. hit() finds where a ray is scattered by delta tracking against the largest
+// extinction in the grid and transmittance() estimates the light getting through by
+// ratio tracking.
+pub struct GridVolume {
+    bounds: Aabb,
+    grid: DensityGrid,
+    density: f64,
+    phase: HenyeyGreenstein,
+}
+
+impl GridVolume {
+    pub fn new(
+        bounds: Aabb,
+        grid: DensityGrid,
+        density: f64,
+        phase: HenyeyGreenstein,
+    ) -> GridVolume {
+        return GridVolume {
+            bounds,
+            grid,
+            density,
+            phase,
+        };
+    }
+
+    fn extinction(&self, ray: &Ray, t: f64) -> f64 {
+        let local = (ray.at(t) - self.bounds.min) / (self.bounds.max - self.bounds.min);
+        return self.density * self.grid.lookup(local);
+    }
+
+    // Tentative collisions along the ray inside [t_min, t_max] against the majorant,
+    // with the extinction at each. Stops early when visit returns false.
+    fn track(&self, ray: &Ray, t_min: f64, t_max: f64, mut visit: impl FnMut(f64, f64) -> bool) {
+        let majorant = self.density * self.grid.max();
+        let (mut t, t_end) = match self.bounds.interval(ray, t_min, t_max) {
+            Some(interval) if majorant > 0.0 => interval,
+            _ => return,
+        };
+        let speed = ray.dir.length();
+        loop {
+            t += -(1.0 - random()).ln() / (majorant * speed);
+            if t >= t_end || !visit(t, self.extinction(ray, t) / majorant) {
+                return;
+            }
+        }
+    }
+}
+
+impl Hittable for GridVolume {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        let mut collision = None;
+        self.track(ray, t_min, t_max, |t, ratio| {
+            if random() < ratio {
+                collision = Some(t);
+                return false;
+            }
+            return true;
+        });
+
+        // points in a volume have no surface, the normal faces back along the ray
+        let t = collision?;
+        let normal = -1.0 * ray.dir.unit_vector();
+        return Some(HitRecord::new(ray.at(t), normal, t, true, &self.phase));
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        return Some(self.bounds);
+    }
+
+    fn transmittance(&self, ray: &Ray, t_min: f64, t_max: f64) -> f64 {
+        let mut transmittance = 1.0;
+        self.track(ray, t_min, t_max, |_, ratio| {
+            transmittance *= 1.0 - ratio.min(1.0);
+            return transmittance > 0.0;
+        });
+        return transmittance;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vec3::Point;
+
+    const EPSILON: f64 = 0.000001;
+
+    fn fog(density: f64) -> GridVolume {
+        let bounds = Aabb::new(Point::new(-1.0, -1.0, -1.0), Point::new(1.0, 1.0, 1.0));
+        let phase = HenyeyGreenstein::new(Color::new(0.9, 0.9, 0.9), 0.0);
+        return GridVolume::new(bounds, DensityGrid::constant(1.0), density, phase);
+    }
+
+    #[test]
+    fn test_tracking() {
+        // through 2 units of fog at density 0.5 a share of exp(-1) of the rays gets through
+        let volume = fog(0.5);
+        let ray = Ray::new(Point::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -2.0));
+        let count = 20000;
+        let passed = (0..count)
+            .filter(|_| volume.hit(&ray, 0.001, f64::INFINITY).is_none())
+            .count();
+        let expected = (-1.0f64).exp();
+        assert!((passed as f64 / count as f64 - expected).abs() < 0.02);
+
+        // ratio tracking on a grid twice as dense at the far end
+        let bounds = Aabb::new(Point::new(-1.0, -1.0, -1.0), Point::new(1.0, 1.0, 1.0));
+        let phase = HenyeyGreenstein::new(Color::new(0.9, 0.9, 0.9), 0.0);
+        let ramp = DensityGrid::new(1, 1, 2, vec![1.0, 0.5]);
+        let volume = GridVolume::new(bounds, ramp, 1.0, phase);
+        let mean = (0..count)
+            .map(|_| volume.transmittance(&ray, 0.001, f64::INFINITY))
+            .sum::<f64>()
+            / count as f64;
+        // the density runs linearly from 1 to 0.5 over the middle unit, flat outside
+        let expected = (-(0.5 * 1.0 + 0.75 + 0.5 * 0.5f64)).exp();
+        assert!((mean - expected).abs() < 0.01);
+
+        let outside = Ray::new(Point::new(2.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!((volume.transmittance(&outside, 0.001, f64::INFINITY) - 1.0).abs() <= EPSILON);
+    }
+
+    #[test]
+    fn test_henyey_greenstein() {
+        let phase = HenyeyGreenstein::new(Color::new(1.0, 1.0, 1.0), 0.6);
+        let dir = Vec3::new(0.0, 1.0, 0.0);
+
+        // sampled directions have a mean cosine of g
+        let count = 20000;
+        let mean = (0..count)
+            .map(|_| Vec3::dot(phase.sample(dir), dir))
+            .sum::<f64>()
+            / count as f64;
+        assert!((mean - 0.6).abs() < 0.02);
+
+        // the density integrates to one over the sphere
+        let steps = 2000;
+        let integral: f64 = (0..steps)
+            .map(|i| {
+                let cos_theta = -1.0 + 2.0 * (i as f64 + 0.5) / steps as f64;
+                return 2.0 * PI * phase.phase(cos_theta) * 2.0 / steps as f64;
+            })
+            .sum();
+        assert!((integral - 1.0).abs() < 0.001);
+    }
+}